@@ -0,0 +1,1327 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{Receiver, Recover, RecvError, RecvTimeoutError, TryRecvError, Wait};
+
+// The receive operations shared by Receiver and its adapters, so adapters can wrap each
+// other, as in `rx.filter(p).map(f)`.
+pub trait RecvSource {
+    type Item;
+
+    fn recv(&mut self) -> Result<Self::Item, RecvError>;
+
+    fn try_recv(&mut self) -> Result<Self::Item, TryRecvError>;
+
+    fn recv_deadline(&mut self, deadline: Instant) -> Result<Self::Item, RecvTimeoutError>;
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Self::Item, RecvTimeoutError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_deadline(deadline),
+            None => Ok(self.recv()?),
+        }
+    }
+}
+
+// Applies `f` to each message as it is received; see Receiver::map.
+pub struct MappedReceiver<R, F> {
+    rx: R,
+    f: F,
+}
+
+// Yields only messages passing `pred`; see Receiver::filter. Messages that fail it are
+// taken off the channel and dropped, so other receivers never see them either.
+pub struct FilteredReceiver<R, P> {
+    rx: R,
+    pred: P,
+}
+
+// Skips messages whose key equals that of the last one yielded; see Receiver::dedup.
+pub struct Dedup<R, K, F> {
+    rx: R,
+    key: F,
+    last: Option<K>,
+}
+
+// Runs of messages sharing a key; see Receiver::group_by.
+pub struct GroupBy<T, K, F> {
+    rx: Receiver<T>,
+    key: F,
+    // The first message of the next group, taken while finishing the last one.
+    next: Option<(K, T)>,
+    max_len: usize,
+    max_wait: Option<Duration>,
+}
+
+// Batches of up to `n` messages; see Receiver::chunks.
+pub struct Chunks<T> {
+    rx: Receiver<T>,
+    n: usize,
+}
+
+// Batches closed by size or age; see Receiver::chunk_timeout.
+pub struct ChunkTimeout<T> {
+    rx: Receiver<T>,
+    max: usize,
+    max_wait: Duration,
+}
+
+// The last message of each burst; see Receiver::debounce.
+pub struct Debounced<T> {
+    rx: Receiver<T>,
+    quiet: Duration,
+}
+
+// At most one message per interval; see Receiver::sample and Receiver::throttle.
+pub struct Sampled<T> {
+    rx: Receiver<T>,
+    interval: Duration,
+    keep_first: bool,
+    next_at: Cell<Option<Instant>>,
+    n_skipped: Cell<u64>,
+}
+
+// Pairs of messages, one from each receiver; see zip.
+pub struct ZipReceiver<A, B> {
+    a: Receiver<A>,
+    b: Receiver<B>,
+    held_a: Cell<Option<A>>,
+    held_b: Cell<Option<B>>,
+}
+
+// Receives from `a` and `b` in lockstep. A message already taken from one side is held
+// inside the ZipReceiver while it blocks for the other side's, for as long as that takes.
+pub fn zip<A, B>(a: Receiver<A>, b: Receiver<B>) -> ZipReceiver<A, B> {
+    ZipReceiver {
+        a,
+        b,
+        held_a: Cell::new(None),
+        held_b: Cell::new(None),
+    }
+}
+
+// One half of a split receiver; see Receiver::partition.
+pub struct PartReceiver<T> {
+    part: Arc<Partition<T>>,
+    side: usize,
+}
+
+struct Partition<T> {
+    rx: Receiver<T>,
+    pred: Box<dyn Fn(&T) -> bool + Send + Sync>,
+    state: Mutex<PartState<T>>,
+    // Signalled when a message is stashed, the reader steps down, or the channel ends.
+    changed: Condvar,
+}
+
+// Messages from several receivers; see merge.
+pub struct MergedReceiver<T> {
+    inputs: Vec<Receiver<T>>,
+    // Where the next scan starts, one past the input last received from.
+    next: Cell<usize>,
+    biased: bool,
+}
+
+// How long MergedReceiver::recv blocks on one input before checking the others again.
+const MERGE_POLL: Duration = Duration::from_millis(2);
+
+// Receives from whichever of `receivers` has a message, scanning them round-robin so a
+// busy input cannot starve the rest. With all inputs empty, recv waits on one of them
+// for a short while at a time, so messages on the others can be picked up that late.
+pub fn merge<T>(receivers: Vec<Receiver<T>>) -> MergedReceiver<T> {
+    MergedReceiver {
+        inputs: receivers,
+        next: Cell::new(0),
+        biased: false,
+    }
+}
+
+// Only one half at a time blocks on the channel. It stashes messages for the other half
+// in that half's pending queue, which the other half drains before reading itself.
+struct PartState<T> {
+    pending: [VecDeque<T>; 2],
+    open: [bool; 2],
+    reading: bool,
+    ended: bool,
+}
+
+// Held by the reading half; dropping it steps down, even when the predicate panics, so
+// the other half does not wait on a reader that is gone.
+struct Reading<'a, T> {
+    part: &'a Partition<T>,
+}
+
+impl<T> RecvSource for Receiver<T> {
+    type Item = T;
+
+    fn recv(&mut self) -> Result<T, RecvError> {
+        Receiver::recv(self)
+    }
+
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        Receiver::try_recv(self)
+    }
+
+    fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        Receiver::recv_deadline(self, deadline)
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> MappedReceiver<Self, F> {
+        MappedReceiver { rx: self, f }
+    }
+
+    pub fn filter<P: FnMut(&T) -> bool>(self, pred: P) -> FilteredReceiver<Self, P> {
+        FilteredReceiver { rx: self, pred }
+    }
+
+    // Skips each message equal to the one yielded before it, so a run of repeats comes
+    // out once. Keeps a clone of the last message to compare against.
+    pub fn dedup(self) -> Dedup<Self, T, fn(&T) -> T>
+    where
+        T: Clone + PartialEq,
+    {
+        self.dedup_by_key(T::clone)
+    }
+
+    // Like dedup, comparing and keeping only `key(&msg)`.
+    pub fn dedup_by_key<K: PartialEq, F: FnMut(&T) -> K>(self, key: F) -> Dedup<Self, K, F> {
+        Dedup {
+            rx: self,
+            key,
+            last: None,
+        }
+    }
+
+    // Splits the receiver in two: the first half gets the messages passing `pred` and the
+    // second the rest. Each half keeps the other's messages it happens to take until the
+    // other comes for them; once a half is dropped, its messages are discarded instead.
+    pub fn partition(
+        self,
+        pred: impl Fn(&T) -> bool + Send + Sync + 'static,
+    ) -> (PartReceiver<T>, PartReceiver<T>) {
+        let part = Arc::new(Partition {
+            rx: self,
+            pred: Box::new(pred),
+            state: Mutex::new(PartState {
+                pending: [VecDeque::new(), VecDeque::new()],
+                open: [true, true],
+                reading: false,
+                ended: false,
+            }),
+            changed: Condvar::new(),
+        });
+        let accepted = PartReceiver {
+            part: Arc::clone(&part),
+            side: 0,
+        };
+        (accepted, PartReceiver { part, side: 1 })
+    }
+
+    // Collects consecutive messages with equal keys, yielding each run once a message with
+    // another key arrives or the channel ends. Runs can be capped with GroupBy::max_len
+    // and GroupBy::max_wait.
+    pub fn group_by<K: Eq, F: FnMut(&T) -> K>(self, key: F) -> GroupBy<T, K, F> {
+        GroupBy {
+            rx: self,
+            key,
+            next: None,
+            max_len: usize::MAX,
+            max_wait: None,
+        }
+    }
+
+    // Each batch waits for its first message, then takes whatever else is already queued,
+    // up to `n` in all. Panics if `n` is zero.
+    pub fn chunks(self, n: usize) -> Chunks<T> {
+        assert!(n > 0, "chunk size must be nonzero");
+        Chunks { rx: self, n }
+    }
+
+    // Each batch waits for its first message, then keeps collecting until it holds `max`
+    // messages or `max_wait` has passed since the first, so a lone message is delivered
+    // after `max_wait` at the latest. Panics if `max` is zero.
+    pub fn chunk_timeout(self, max: usize, max_wait: Duration) -> ChunkTimeout<T> {
+        assert!(max > 0, "chunk size must be nonzero");
+        ChunkTimeout {
+            rx: self,
+            max,
+            max_wait,
+        }
+    }
+
+    // Waits for a message, then keeps replacing it with newer ones for as long as each
+    // arrives within `quiet` of the one before, and yields the last of them.
+    pub fn debounce(self, quiet: Duration) -> Debounced<T> {
+        Debounced { rx: self, quiet }
+    }
+
+    // Yields the newest message, then nothing until `interval` has passed; whatever
+    // arrives in between is discarded except the newest, which is the next one yielded.
+    pub fn sample(self, interval: Duration) -> Sampled<T> {
+        Sampled {
+            rx: self,
+            interval,
+            keep_first: false,
+            next_at: Cell::new(None),
+            n_skipped: Cell::new(0),
+        }
+    }
+
+    // Like sample, but yields the first message of each interval and discards the rest.
+    pub fn throttle(self, interval: Duration) -> Sampled<T> {
+        Sampled {
+            keep_first: true,
+            ..self.sample(interval)
+        }
+    }
+}
+
+impl<R: RecvSource, U, F: FnMut(R::Item) -> U> RecvSource for MappedReceiver<R, F> {
+    type Item = U;
+
+    fn recv(&mut self) -> Result<U, RecvError> {
+        self.rx.recv().map(&mut self.f)
+    }
+
+    fn try_recv(&mut self) -> Result<U, TryRecvError> {
+        self.rx.try_recv().map(&mut self.f)
+    }
+
+    fn recv_deadline(&mut self, deadline: Instant) -> Result<U, RecvTimeoutError> {
+        self.rx.recv_deadline(deadline).map(&mut self.f)
+    }
+}
+
+impl<R: RecvSource, P: FnMut(&R::Item) -> bool> RecvSource for FilteredReceiver<R, P> {
+    type Item = R::Item;
+
+    fn recv(&mut self) -> Result<R::Item, RecvError> {
+        loop {
+            let msg = self.rx.recv()?;
+            if (self.pred)(&msg) {
+                return Ok(msg);
+            }
+        }
+    }
+
+    fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        loop {
+            let msg = self.rx.try_recv()?;
+            if (self.pred)(&msg) {
+                return Ok(msg);
+            }
+        }
+    }
+
+    fn recv_deadline(&mut self, deadline: Instant) -> Result<R::Item, RecvTimeoutError> {
+        loop {
+            let msg = self.rx.recv_deadline(deadline)?;
+            if (self.pred)(&msg) {
+                return Ok(msg);
+            }
+        }
+    }
+}
+
+impl<R: RecvSource, K: PartialEq, F: FnMut(&R::Item) -> K> RecvSource for Dedup<R, K, F> {
+    type Item = R::Item;
+
+    fn recv(&mut self) -> Result<R::Item, RecvError> {
+        loop {
+            let msg = self.rx.recv()?;
+            if self.is_new(&msg) {
+                return Ok(msg);
+            }
+        }
+    }
+
+    fn try_recv(&mut self) -> Result<R::Item, TryRecvError> {
+        loop {
+            let msg = self.rx.try_recv()?;
+            if self.is_new(&msg) {
+                return Ok(msg);
+            }
+        }
+    }
+
+    fn recv_deadline(&mut self, deadline: Instant) -> Result<R::Item, RecvTimeoutError> {
+        loop {
+            let msg = self.rx.recv_deadline(deadline)?;
+            if self.is_new(&msg) {
+                return Ok(msg);
+            }
+        }
+    }
+}
+
+impl<R, F> MappedReceiver<R, F> {
+    pub fn into_inner(self) -> R {
+        self.rx
+    }
+}
+
+impl<R, P> FilteredReceiver<R, P> {
+    pub fn into_inner(self) -> R {
+        self.rx
+    }
+}
+
+impl<R, K, F> Dedup<R, K, F> {
+    pub fn into_inner(self) -> R {
+        self.rx
+    }
+}
+
+impl<R: RecvSource, K: PartialEq, F: FnMut(&R::Item) -> K> Dedup<R, K, F> {
+    // Remembers the key of a message about to be yielded.
+    fn is_new(&mut self, msg: &R::Item) -> bool {
+        let key = (self.key)(msg);
+        if self.last.as_ref() == Some(&key) {
+            return false;
+        }
+        self.last = Some(key);
+        true
+    }
+}
+
+impl<R: RecvSource, U, F: FnMut(R::Item) -> U> MappedReceiver<R, F> {
+    pub fn map<V, G: FnMut(U) -> V>(self, f: G) -> MappedReceiver<Self, G> {
+        MappedReceiver { rx: self, f }
+    }
+
+    pub fn filter<Q: FnMut(&U) -> bool>(self, pred: Q) -> FilteredReceiver<Self, Q> {
+        FilteredReceiver { rx: self, pred }
+    }
+}
+
+impl<R: RecvSource, P: FnMut(&R::Item) -> bool> FilteredReceiver<R, P> {
+    pub fn map<U, F: FnMut(R::Item) -> U>(self, f: F) -> MappedReceiver<Self, F> {
+        MappedReceiver { rx: self, f }
+    }
+
+    pub fn filter<Q: FnMut(&R::Item) -> bool>(self, pred: Q) -> FilteredReceiver<Self, Q> {
+        FilteredReceiver { rx: self, pred }
+    }
+}
+
+impl<T> Chunks<T> {
+    // The last batch before the channel ends may be short; never empty.
+    pub fn recv(&self) -> Result<Vec<T>, RecvError> {
+        self.rx.recv_many(self.n)
+    }
+
+    pub fn into_inner(self) -> Receiver<T> {
+        self.rx
+    }
+}
+
+impl<R: RecvSource, K: PartialEq, F: FnMut(&R::Item) -> K> Iterator for Dedup<R, K, F> {
+    type Item = R::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<A, B> ZipReceiver<A, B> {
+    // Fails once either side ends; an unpaired message from the other side is then left
+    // for into_remainder. Takes from a side that has a message queued before blocking.
+    pub fn recv(&self) -> Result<(A, B), RecvError> {
+        let mut held_a = self.held_a.take();
+        let mut held_b = self.held_b.take();
+        let pair = self.fill(&mut held_a, &mut held_b);
+        self.held_a.set(held_a);
+        self.held_b.set(held_b);
+        pair
+    }
+
+    // Any message received from one side that never got a partner.
+    pub fn into_remainder(self) -> (Option<A>, Option<B>) {
+        (self.held_a.into_inner(), self.held_b.into_inner())
+    }
+
+    pub fn into_inner(self) -> (Receiver<A>, Receiver<B>) {
+        (self.a, self.b)
+    }
+
+    fn fill(&self, held_a: &mut Option<A>, held_b: &mut Option<B>) -> Result<(A, B), RecvError> {
+        if held_a.is_none() {
+            match self.a.try_recv() {
+                Ok(msg) => *held_a = Some(msg),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+            }
+        }
+        if held_b.is_none() {
+            match self.b.try_recv() {
+                Ok(msg) => *held_b = Some(msg),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+            }
+        }
+        if held_a.is_none() {
+            *held_a = Some(self.a.recv()?);
+        }
+        if held_b.is_none() {
+            *held_b = Some(self.b.recv()?);
+        }
+        Ok((held_a.take().unwrap(), held_b.take().unwrap()))
+    }
+}
+
+impl<A, B> Iterator for ZipReceiver<A, B> {
+    type Item = (A, B);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> Partition<T> {
+    fn side_of(&self, msg: &T) -> usize {
+        if (self.pred)(msg) {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+impl<T> PartState<T> {
+    fn stash(&mut self, side: usize, msg: T) {
+        if self.open[side] {
+            self.pending[side].push_back(msg);
+        }
+    }
+}
+
+impl<T> PartReceiver<T> {
+    // Fails once the channel has ended and nothing is left for this half.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let part = &*self.part;
+        let mut state = part.state.lock().recover();
+        loop {
+            if let Some(msg) = state.pending[self.side].pop_front() {
+                return Ok(msg);
+            }
+            if state.ended {
+                return Err(RecvError);
+            }
+            if !state.reading {
+                break;
+            }
+            state = part.changed.wait_on(state);
+        }
+        state.reading = true;
+        drop(state);
+        let _reading = Reading { part };
+        loop {
+            let received = part.rx.recv().map(|msg| (part.side_of(&msg), msg));
+            let mut state = part.state.lock().recover();
+            part.changed.notify_all();
+            match received {
+                Ok((side, msg)) if side == self.side => return Ok(msg),
+                Ok((side, msg)) => state.stash(side, msg),
+                Err(err) => {
+                    state.ended = true;
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    // While the other half is blocked reading, only this half's stashed messages are
+    // looked at; the reader passes along anything new for this half straight away.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let part = &*self.part;
+        let mut state = part.state.lock().recover();
+        if let Some(msg) = state.pending[self.side].pop_front() {
+            return Ok(msg);
+        }
+        if state.ended {
+            return Err(TryRecvError::Disconnected);
+        }
+        if state.reading {
+            return Err(TryRecvError::Empty);
+        }
+        loop {
+            match part.rx.try_recv() {
+                Ok(msg) => {
+                    let side = part.side_of(&msg);
+                    if side == self.side {
+                        return Ok(msg);
+                    }
+                    state.stash(side, msg);
+                    part.changed.notify_all();
+                }
+                Err(TryRecvError::Empty) => return Err(TryRecvError::Empty),
+                Err(TryRecvError::Disconnected) => {
+                    state.ended = true;
+                    part.changed.notify_all();
+                    return Err(TryRecvError::Disconnected);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for Reading<'_, T> {
+    fn drop(&mut self) {
+        self.part.state.lock().recover().reading = false;
+        self.part.changed.notify_all();
+    }
+}
+
+impl<T> Iterator for PartReceiver<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> Drop for PartReceiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.part.state.lock().recover();
+        state.open[self.side] = false;
+        let pending = mem::take(&mut state.pending[self.side]);
+        drop(state);
+        drop(pending);
+    }
+}
+
+impl<T, K: Eq, F: FnMut(&T) -> K> GroupBy<T, K, F> {
+    // Splits longer runs into groups of `max_len`. Panics if `max_len` is zero.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        assert!(max_len > 0, "group size must be nonzero");
+        self.max_len = max_len;
+        self
+    }
+
+    // Yields a group once it has been collecting for `max_wait`, even though its key has
+    // not changed; the run carries on in the next group.
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = Some(max_wait);
+        self
+    }
+
+    pub fn recv(&mut self) -> Result<(K, Vec<T>), RecvError> {
+        let (key, first) = match self.next.take() {
+            Some(next) => next,
+            None => {
+                let msg = self.rx.recv()?;
+                ((self.key)(&msg), msg)
+            }
+        };
+        let deadline = self
+            .max_wait
+            .and_then(|max_wait| Instant::now().checked_add(max_wait));
+        let mut group = vec![first];
+        while group.len() < self.max_len {
+            let received = match deadline {
+                Some(deadline) => self.rx.recv_deadline(deadline).ok(),
+                None => self.rx.recv().ok(),
+            };
+            let Some(msg) = received else {
+                break;
+            };
+            let msg_key = (self.key)(&msg);
+            if msg_key != key {
+                self.next = Some((msg_key, msg));
+                break;
+            }
+            group.push(msg);
+        }
+        Ok((key, group))
+    }
+
+    pub fn into_inner(self) -> Receiver<T> {
+        self.rx
+    }
+}
+
+impl<T, K: Eq, F: FnMut(&T) -> K> Iterator for GroupBy<T, K, F> {
+    type Item = (K, Vec<T>);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> MergedReceiver<T> {
+    // Scans the inputs in their original order every time instead of round-robin, so an
+    // input only gets a turn while all those before it are empty. This starves later
+    // inputs for as long as earlier ones keep up, which is the point, e.g. to put a
+    // shutdown channel ahead of a data channel.
+    pub fn biased(mut self) -> Self {
+        self.biased = true;
+        self
+    }
+
+    // Fails once every input has ended.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        loop {
+            let idx = match self.poll() {
+                Ok(msg) => return Ok(msg),
+                Err(Some(idx)) => idx,
+                Err(None) => return Err(RecvError),
+            };
+            if let Ok(msg) = self.inputs[idx].recv_timeout(MERGE_POLL) {
+                self.next.set(idx + 1);
+                return Ok(msg);
+            }
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self.poll() {
+            Ok(msg) => Ok(msg),
+            Err(Some(_)) => Err(TryRecvError::Empty),
+            Err(None) => Err(TryRecvError::Disconnected),
+        }
+    }
+
+    pub fn into_inner(self) -> Vec<Receiver<T>> {
+        self.inputs
+    }
+
+    // Takes one message from the first input that has one, or finds an input that is
+    // empty but still open.
+    fn poll(&self) -> Result<T, Option<usize>> {
+        let n_inputs = self.inputs.len();
+        let mut open = None;
+        for i in 0..n_inputs {
+            let start = if self.biased { 0 } else { self.next.get() };
+            let idx = (start + i) % n_inputs;
+            match self.inputs[idx].try_recv() {
+                Ok(msg) => {
+                    self.next.set(idx + 1);
+                    return Ok(msg);
+                }
+                Err(TryRecvError::Empty) => {
+                    open.get_or_insert(idx);
+                }
+                Err(TryRecvError::Disconnected) => {}
+            }
+        }
+        Err(open)
+    }
+}
+
+impl<T> Iterator for MergedReceiver<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> Iterator for Chunks<T> {
+    type Item = Vec<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> ChunkTimeout<T> {
+    // The last batch before the channel ends may be short; never empty.
+    pub fn recv(&self) -> Result<Vec<T>, RecvError> {
+        let mut batch = vec![self.rx.recv()?];
+        let deadline = Instant::now().checked_add(self.max_wait);
+        while batch.len() < self.max {
+            let next = match deadline {
+                Some(deadline) => self.rx.recv_deadline(deadline).ok(),
+                None => self.rx.recv().ok(),
+            };
+            match next {
+                Some(msg) => batch.push(msg),
+                None => break,
+            }
+        }
+        Ok(batch)
+    }
+
+    pub fn into_inner(self) -> Receiver<T> {
+        self.rx
+    }
+}
+
+impl<T> Iterator for ChunkTimeout<T> {
+    type Item = Vec<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> Debounced<T> {
+    // When the channel ends mid-burst, the pending message is yielded straight away.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut latest = self.rx.recv()?;
+        while let Ok(msg) = self.rx.recv_timeout(self.quiet) {
+            latest = msg;
+        }
+        Ok(latest)
+    }
+
+    pub fn into_inner(self) -> Receiver<T> {
+        self.rx
+    }
+}
+
+impl<T> Iterator for Debounced<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> Sampled<T> {
+    // When the channel ends, a message held back by sample is yielded without waiting
+    // out the interval.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut latest = None;
+        if let Some(next_at) = self.next_at.get() {
+            loop {
+                match self.rx.recv_deadline(next_at) {
+                    Ok(msg) => self.hold(&mut latest, msg),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return latest.ok_or(RecvError),
+                }
+            }
+        }
+        let mut msg = match latest {
+            Some(msg) => msg,
+            None => self.rx.recv()?,
+        };
+        if !self.keep_first {
+            while let Ok(newer) = self.rx.try_recv() {
+                msg = newer;
+                self.skip();
+            }
+        }
+        self.next_at.set(Instant::now().checked_add(self.interval));
+        Ok(msg)
+    }
+
+    // Messages discarded so far.
+    pub fn skipped_count(&self) -> u64 {
+        self.n_skipped.get()
+    }
+
+    pub fn into_inner(self) -> Receiver<T> {
+        self.rx
+    }
+
+    fn hold(&self, latest: &mut Option<T>, msg: T) {
+        if self.keep_first || latest.replace(msg).is_some() {
+            self.skip();
+        }
+    }
+
+    fn skip(&self) {
+        self.n_skipped.set(self.n_skipped.get() + 1);
+    }
+}
+
+impl<T> Iterator for Sampled<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<R: RecvSource, U, F: FnMut(R::Item) -> U> Iterator for MappedReceiver<R, F> {
+    type Item = U;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<R: RecvSource, P: FnMut(&R::Item) -> bool> Iterator for FilteredReceiver<R, P> {
+    type Item = R::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_channel;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::thread;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Key(char),
+        Tick(u32),
+    }
+
+    #[test]
+    fn test_adapters_map_and_filter() {
+        let (tx, rx) = new_channel();
+        let mut keys = rx
+            .filter(|event| matches!(event, Event::Key(_)))
+            .map(|event| match event {
+                Event::Key(key) => key,
+                Event::Tick(_) => unreachable!(),
+            });
+        assert_eq!(keys.try_recv(), Err(TryRecvError::Empty));
+        tx.send(Event::Tick(0)).unwrap();
+        assert_eq!(keys.try_recv(), Err(TryRecvError::Empty));
+        tx.send(Event::Key('a')).unwrap();
+        tx.send(Event::Tick(1)).unwrap();
+        tx.send(Event::Key('b')).unwrap();
+        assert_eq!(keys.recv(), Ok('a'));
+        assert_eq!(keys.recv_timeout(Duration::from_millis(10)), Ok('b'));
+        tx.send(Event::Tick(2)).unwrap();
+        assert_eq!(
+            keys.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        tx.send(Event::Key('c')).unwrap();
+        drop(tx);
+        assert_eq!(keys.by_ref().collect::<String>(), "c");
+        assert_eq!(keys.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_adapters_map_chain() {
+        let (tx, rx) = new_channel();
+        let mut lens = rx
+            .map(|s: &str| s.len())
+            .filter(|len| *len > 1)
+            .map(|len| len * 10);
+        tx.send_all(["a", "bb", "ccc"]);
+        drop(tx);
+        assert_eq!(lens.by_ref().collect::<Vec<_>>(), vec![20, 30]);
+        let rx = lens.into_inner().into_inner().into_inner();
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_adapters_filtered_rejects_are_consumed() {
+        let (tx, rx) = new_channel();
+        let evens = rx.clone().filter(|val: &i32| val % 2 == 0);
+        let odds = rx.filter(|val: &i32| val % 2 == 1);
+        let evens_handle = thread::spawn(move || evens.collect::<Vec<_>>());
+        let odds_handle = thread::spawn(move || odds.collect::<Vec<_>>());
+        tx.send_all(0..1000);
+        drop(tx);
+        let evens = evens_handle.join().unwrap();
+        let odds = odds_handle.join().unwrap();
+        // a reject is consumed by whichever side took it, never passed to the other
+        assert!(evens.iter().all(|val| val % 2 == 0));
+        assert!(odds.iter().all(|val| val % 2 == 1));
+        assert!(evens.len() + odds.len() <= 1000);
+        assert!(evens.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_adapters_dedup() {
+        let (tx, rx) = new_channel();
+        let mut rx = rx.dedup();
+        tx.send_all("AAABBA".chars());
+        assert_eq!(rx.try_recv(), Ok('A'));
+        assert_eq!(rx.recv(), Ok('B'));
+        assert_eq!(rx.recv(), Ok('A'));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send('A').unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        tx.send_all("AC".chars());
+        drop(tx);
+        assert_eq!(rx.collect::<String>(), "C");
+
+        // messages need not be Clone when deduplicated by key
+        struct Reading {
+            sensor: u32,
+            value: i32,
+        }
+        let (tx, rx) = new_channel();
+        let rx = rx.dedup_by_key(|reading: &Reading| reading.value);
+        for (sensor, value) in [(1, 5), (2, 5), (3, 6), (4, 6), (5, 5)] {
+            tx.send(Reading { sensor, value }).unwrap();
+        }
+        drop(tx);
+        let sensors = rx.map(|reading| reading.sensor).collect::<Vec<_>>();
+        assert_eq!(sensors, vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn test_adapters_zip() {
+        let (tx_a, rx_a) = new_channel();
+        let (tx_b, rx_b) = new_channel();
+        let zipped = zip(rx_a, rx_b);
+        tx_a.send_all(1..=3);
+        tx_b.send_all("xy".chars());
+        drop(tx_b);
+        assert_eq!(zipped.recv(), Ok((1, 'x')));
+        assert_eq!(zipped.recv(), Ok((2, 'y')));
+        assert_eq!(zipped.recv(), Err(RecvError));
+        assert_eq!(zipped.into_remainder(), (Some(3), None));
+
+        // blocks for the side that is behind
+        let (tx_a, rx_a) = new_channel();
+        let (tx_b, rx_b) = new_channel::<&str>();
+        let zipped = zip(rx_a, rx_b);
+        let producer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx_b.send("buf").unwrap();
+            tx_b
+        });
+        tx_a.send(7).unwrap();
+        assert_eq!(zipped.recv(), Ok((7, "buf")));
+        let tx_b = producer.join().unwrap();
+
+        // both sides end together
+        tx_a.send(8).unwrap();
+        tx_b.send("last").unwrap();
+        drop(tx_a);
+        drop(tx_b);
+        assert_eq!(zipped.recv(), Ok((8, "last")));
+        assert_eq!(zipped.recv(), Err(RecvError));
+        assert_eq!(zipped.into_remainder(), (None, None));
+
+        let (tx_a, rx_a) = new_channel::<i32>();
+        let (tx_b, rx_b) = new_channel();
+        let mut zipped = zip(rx_a, rx_b);
+        tx_b.send_all(0..2);
+        drop(tx_a);
+        assert_eq!(zipped.next(), None);
+        // nothing was taken from the side still open
+        let (_, rx_b) = zipped.into_inner();
+        assert_eq!(rx_b.len(), 2);
+    }
+
+    #[test]
+    fn test_adapters_partition() {
+        let (tx, rx) = new_channel();
+        let (evens, odds) = rx.partition(|val| val % 2 == 0);
+        tx.send_all([1, 3, 2, 5, 4]);
+        assert_eq!(evens.recv(), Ok(2));
+        assert_eq!(evens.try_recv(), Ok(4));
+        assert_eq!(evens.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(odds.try_recv(), Ok(1));
+        tx.send(7).unwrap();
+        drop(tx);
+        assert_eq!(odds.collect::<Vec<_>>(), vec![3, 5, 7]);
+        assert_eq!(evens.recv(), Err(RecvError));
+        assert_eq!(evens.try_recv(), Err(TryRecvError::Disconnected));
+
+        // messages for a dropped half are discarded
+        let (tx, rx) = new_channel();
+        let (evens, odds) = rx.partition(|val| val % 2 == 0);
+        drop(odds);
+        tx.send_all(0..6);
+        drop(tx);
+        assert_eq!(evens.collect::<Vec<_>>(), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_adapters_partition_panicking_pred() {
+        let (tx, rx) = new_channel();
+        let (evens, odds) = rx.partition(|val: &i32| {
+            assert!(*val != 13, "unlucky");
+            val % 2 == 0
+        });
+        tx.send(13).unwrap();
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| odds.recv())).is_err());
+        // the panicking reader stepped down, so either half can read again
+        tx.send_all([1, 2]);
+        assert_eq!(evens.recv(), Ok(2));
+        assert_eq!(odds.recv(), Ok(1));
+    }
+
+    #[test]
+    fn test_adapters_partition_threads() {
+        let n_msgs = 10_000;
+        let (tx, rx) = new_channel();
+        let (oks, errs) = rx.partition(|res: &Result<i32, i32>| res.is_ok());
+        let producers = (0..4)
+            .map(|i| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for val in (i..n_msgs).step_by(4) {
+                        let res = if val % 3 == 0 { Err(val) } else { Ok(val) };
+                        tx.send(res).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(tx);
+        let oks = thread::spawn(move || oks.map(Result::unwrap).collect::<Vec<_>>());
+        let errs = thread::spawn(move || errs.map(Result::unwrap_err).collect::<Vec<_>>());
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        let mut oks = oks.join().unwrap();
+        let mut errs = errs.join().unwrap();
+        oks.sort();
+        errs.sort();
+        assert_eq!(
+            oks,
+            (0..n_msgs).filter(|val| val % 3 != 0).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            errs,
+            (0..n_msgs).filter(|val| val % 3 == 0).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_adapters_group_by() {
+        let (tx, rx) = new_channel();
+        let groups = rx.group_by(|&(key, _)| key);
+        tx.send_all([('a', 1), ('a', 2), ('b', 3), ('b', 4), ('b', 5), ('a', 6)]);
+        drop(tx);
+        let groups = groups
+            .map(|(key, group)| (key, group.into_iter().map(|(_, val)| val).collect()))
+            .collect::<Vec<(char, Vec<i32>)>>();
+        assert_eq!(
+            groups,
+            vec![('a', vec![1, 2]), ('b', vec![3, 4, 5]), ('a', vec![6])]
+        );
+
+        let (tx, rx) = new_channel();
+        let mut groups = rx.group_by(|val: &i32| val / 10).max_len(2);
+        tx.send_all([10, 11, 12, 20]);
+        drop(tx);
+        assert_eq!(groups.recv(), Ok((1, vec![10, 11])));
+        assert_eq!(groups.recv(), Ok((1, vec![12])));
+        assert_eq!(groups.recv(), Ok((2, vec![20])));
+        assert_eq!(groups.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_adapters_group_by_max_wait() {
+        let (tx, rx) = new_channel();
+        let mut groups = rx
+            .group_by(|val: &i32| val / 10)
+            .max_wait(Duration::from_millis(50));
+        tx.send_all([10, 11]);
+        let started_at = Instant::now();
+        assert_eq!(groups.recv(), Ok((1, vec![10, 11])));
+        assert!(started_at.elapsed() >= Duration::from_millis(50));
+        tx.send_all([12, 20]);
+        assert_eq!(groups.recv(), Ok((1, vec![12])));
+        drop(tx);
+        assert_eq!(groups.recv(), Ok((2, vec![20])));
+        assert_eq!(groups.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_adapters_merge() {
+        let (txs, rxs): (Vec<_>, Vec<_>) = (0..3).map(|_| new_channel()).unzip();
+        let merged = merge(rxs);
+        let producers = txs
+            .into_iter()
+            .enumerate()
+            .map(|(i, tx)| {
+                thread::spawn(move || {
+                    for val in 0..20 {
+                        tx.send((i, val)).unwrap();
+                        thread::sleep(Duration::from_millis(i as u64 * 2));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut received = merged.collect::<Vec<_>>();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        received.sort();
+        let expected = (0..3)
+            .flat_map(|i| (0..20).map(move |val| (i, val)))
+            .collect::<Vec<_>>();
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_adapters_merge_round_robin() {
+        let (busy_tx, busy_rx) = new_channel();
+        let (quiet_tx, quiet_rx) = new_channel();
+        let merged = merge(vec![busy_rx, quiet_rx]);
+        busy_tx.send_all(0..100);
+        quiet_tx.send(-1).unwrap();
+        let first = [merged.recv(), merged.recv()];
+        assert!(first.contains(&Ok(-1)));
+
+        assert_eq!(merged.try_recv(), Ok(1));
+        drop(quiet_tx);
+        assert_eq!(merged.try_recv(), Ok(2));
+        drop(busy_tx);
+        assert_eq!(merged.count(), 97);
+    }
+
+    #[test]
+    fn test_adapters_merge_biased() {
+        #[derive(Debug, PartialEq)]
+        enum Event {
+            Ctrl(i32),
+            Data(i32),
+        }
+        let (ctrl_tx, ctrl_rx) = new_channel();
+        let (data_tx, data_rx) = new_channel();
+        let merged = merge(vec![ctrl_rx, data_rx]).biased();
+        data_tx.send_all((0..50).map(Event::Data));
+        ctrl_tx.send_all((0..50).map(Event::Ctrl));
+        for i in 0..50 {
+            assert_eq!(merged.recv(), Ok(Event::Ctrl(i)));
+        }
+        assert_eq!(merged.recv(), Ok(Event::Data(0)));
+
+        // control messages arriving later still go first
+        ctrl_tx.send(Event::Ctrl(50)).unwrap();
+        assert_eq!(merged.try_recv(), Ok(Event::Ctrl(50)));
+        drop(ctrl_tx);
+        drop(data_tx);
+        assert_eq!(merged.count(), 49);
+    }
+
+    #[test]
+    fn test_adapters_chunks() {
+        let (tx, rx) = new_channel();
+        let chunks = rx.chunks(4);
+        let producer = thread::spawn(move || {
+            let mut next = 0;
+            for burst in [1, 9, 3, 0, 4, 7, 2] {
+                tx.send_all(next..next + burst);
+                next += burst;
+                thread::sleep(Duration::from_millis(5));
+            }
+            next
+        });
+        let batches = chunks.collect::<Vec<_>>();
+        let n_sent = producer.join().unwrap();
+        assert!(batches.iter().all(|batch| (1..=4).contains(&batch.len())));
+        assert_eq!(batches.concat(), (0..n_sent).collect::<Vec<_>>());
+
+        let (tx, rx) = new_channel();
+        let mut chunks = rx.chunks(3);
+        tx.send_all(0..5);
+        drop(tx);
+        assert_eq!(chunks.recv(), Ok(vec![0, 1, 2]));
+        assert_eq!(chunks.next(), Some(vec![3, 4]));
+        assert_eq!(chunks.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_adapters_chunk_timeout() {
+        let (tx, rx) = new_channel();
+        let chunks = rx.chunk_timeout(3, Duration::from_millis(50));
+        tx.send_all(0..4);
+        assert_eq!(chunks.recv(), Ok(vec![0, 1, 2]));
+
+        // a lone message goes out once max_wait has passed
+        let started_at = Instant::now();
+        assert_eq!(chunks.recv(), Ok(vec![3]));
+        let waited = started_at.elapsed();
+        assert!(waited >= Duration::from_millis(50));
+        assert!(waited < Duration::from_secs(1));
+
+        // the wait counts from the first message of the batch
+        let producer = thread::spawn(move || {
+            tx.send(4).unwrap();
+            thread::sleep(Duration::from_millis(20));
+            tx.send(5).unwrap();
+            thread::sleep(Duration::from_millis(80));
+            tx.send(6).unwrap();
+            tx
+        });
+        assert_eq!(chunks.recv(), Ok(vec![4, 5]));
+        assert_eq!(chunks.recv(), Ok(vec![6]));
+
+        let tx = producer.join().unwrap();
+        tx.send(7).unwrap();
+        drop(tx);
+        let started_at = Instant::now();
+        assert_eq!(chunks.recv(), Ok(vec![7]));
+        assert!(started_at.elapsed() < Duration::from_millis(50));
+        assert_eq!(chunks.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_adapters_debounce() {
+        let (tx, rx) = new_channel();
+        let debounced = rx.debounce(Duration::from_millis(200));
+        let producer = thread::spawn(move || {
+            // (gap before sending, message)
+            let script = [(0, 1), (10, 2), (10, 3), (500, 4), (250, 5), (10, 6)];
+            for (gap, msg) in script {
+                thread::sleep(Duration::from_millis(gap));
+                tx.send(msg).unwrap();
+            }
+            thread::sleep(Duration::from_millis(10));
+            tx.send(7).unwrap();
+        });
+        assert_eq!(debounced.recv(), Ok(3));
+        assert_eq!(debounced.recv(), Ok(4));
+
+        // the burst is cut short by the sender going away
+        let started_at = Instant::now();
+        assert_eq!(debounced.recv(), Ok(7));
+        assert!(started_at.elapsed() < Duration::from_millis(200));
+        assert_eq!(debounced.recv(), Err(RecvError));
+        producer.join().unwrap();
+    }
+
+    #[test]
+    fn test_adapters_sample() {
+        let interval = Duration::from_millis(100);
+        let (tx, rx) = new_channel();
+        let sampled = rx.sample(interval);
+        tx.send_all(0..10);
+        let started_at = Instant::now();
+        assert_eq!(sampled.recv(), Ok(9));
+        assert_eq!(sampled.skipped_count(), 9);
+        assert!(started_at.elapsed() < interval);
+
+        tx.send(10).unwrap();
+        tx.send(11).unwrap();
+        assert_eq!(sampled.recv(), Ok(11));
+        assert!(started_at.elapsed() >= interval);
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+        assert_eq!(sampled.skipped_count(), 10);
+
+        // the held back message is flushed once the sender goes away
+        tx.send(12).unwrap();
+        drop(tx);
+        let started_at = Instant::now();
+        assert_eq!(sampled.recv(), Ok(12));
+        assert!(started_at.elapsed() < interval);
+        assert_eq!(sampled.recv(), Err(RecvError));
+        assert_eq!(sampled.skipped_count(), 10);
+    }
+
+    #[test]
+    fn test_adapters_throttle() {
+        let interval = Duration::from_millis(100);
+        let (tx, rx) = new_channel();
+        let throttled = rx.throttle(interval);
+        tx.send_all(1..4);
+        let started_at = Instant::now();
+        assert_eq!(throttled.recv(), Ok(1));
+
+        let producer = thread::spawn(move || {
+            tx.send(4).unwrap();
+            thread::sleep(Duration::from_millis(200));
+            tx.send(5).unwrap();
+        });
+        assert_eq!(throttled.recv(), Ok(5));
+        assert!(started_at.elapsed() >= Duration::from_millis(200));
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+        assert_eq!(throttled.skipped_count(), 3);
+        producer.join().unwrap();
+        assert_eq!(throttled.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_adapters_send() {
+        fn assert_send<S: Send>(_: &S) {}
+        let (_tx, rx) = new_channel::<i32>();
+        let rx = rx.filter(|val| *val > 0).map(|val| val.to_string());
+        assert_send(&rx);
+    }
+}
@@ -0,0 +1,361 @@
+use std::mem::MaybeUninit;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{Recover, RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError, Wait};
+
+// Fixed-capacity ring stored inline; `len` tells a full ring from an empty one, since
+// `head` alone is the same for both.
+struct Ring<T, const N: usize> {
+    buf: [MaybeUninit<T>; N],
+    head: usize,
+    len: usize,
+}
+
+struct Inner<T, const N: usize> {
+    ring: Ring<T, N>,
+    n_senders: usize,
+    n_receivers: usize,
+}
+
+// Shared state of a channel made by new_static_channel. The ring lives inside this one
+// allocation, so sending and receiving never allocate.
+pub struct StaticChannel<T, const N: usize> {
+    inner: Mutex<Inner<T, N>>,
+    available: Condvar,
+    not_full: Condvar,
+}
+
+pub struct ArraySender<T, const N: usize> {
+    shared: Arc<StaticChannel<T, N>>,
+}
+
+pub struct ArrayReceiver<T, const N: usize> {
+    shared: Arc<StaticChannel<T, N>>,
+}
+
+impl<T, const N: usize> Ring<T, N> {
+    fn new() -> Self {
+        Self {
+            buf: [const { MaybeUninit::uninit() }; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    fn push(&mut self, msg: T) {
+        assert!(!self.is_full());
+        let idx = (self.head + self.len) % N;
+        self.buf[idx].write(msg);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: the `len` slots from `head` (wrapping) are initialized, and the slot is
+        // marked uninitialized again by advancing `head` before it can be read twice
+        let msg = unsafe { self.buf[self.head].assume_init_read() };
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(msg)
+    }
+}
+
+impl<T, const N: usize> Drop for Ring<T, N> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+// Like new_bounded, with room for `N` messages in an inline ring instead of a VecDeque.
+// `N` must be nonzero, which is checked at compile time.
+pub fn new_static_channel<T, const N: usize>() -> (ArraySender<T, N>, ArrayReceiver<T, N>) {
+    const { assert!(N > 0, "static channel capacity must be nonzero") };
+    let inner = Inner {
+        ring: Ring::new(),
+        n_senders: 1,
+        n_receivers: 1,
+    };
+    let shared = Arc::new(StaticChannel {
+        inner: Mutex::new(inner),
+        available: Condvar::new(),
+        not_full: Condvar::new(),
+    });
+    let tx = ArraySender {
+        shared: shared.clone(),
+    };
+    let rx = ArrayReceiver { shared };
+    (tx, rx)
+}
+
+impl<T, const N: usize> ArraySender<T, N> {
+    // Blocks while the ring is full.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        loop {
+            if inner_guard.n_receivers == 0 {
+                return Err(SendError::Disconnected(msg));
+            }
+            if !inner_guard.ring.is_full() {
+                break;
+            }
+            inner_guard = self.shared.not_full.wait_on(inner_guard);
+        }
+        inner_guard.ring.push(msg);
+        drop(inner_guard);
+        self.shared.available.notify_one();
+        Ok(())
+    }
+
+    pub fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        if inner_guard.n_receivers == 0 {
+            return Err(TrySendError::Disconnected(msg));
+        }
+        if inner_guard.ring.is_full() {
+            return Err(TrySendError::Full(msg));
+        }
+        inner_guard.ring.push(msg);
+        drop(inner_guard);
+        self.shared.available.notify_one();
+        Ok(())
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T, const N: usize> Clone for ArraySender<T, N> {
+    fn clone(&self) -> Self {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_senders += 1;
+        drop(inner_guard);
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for ArraySender<T, N> {
+    fn drop(&mut self) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_senders -= 1;
+        let is_channel_close = inner_guard.n_senders == 0;
+        drop(inner_guard);
+        if is_channel_close {
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+impl<T, const N: usize> ArrayReceiver<T, N> {
+    // Returns Err(RecvError) once every sender is gone and the ring is empty.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        loop {
+            if let Some(msg) = inner_guard.ring.pop() {
+                drop(inner_guard);
+                self.shared.not_full.notify_one();
+                return Ok(msg);
+            }
+            if inner_guard.n_senders == 0 {
+                return Err(RecvError);
+            }
+            inner_guard = self.shared.available.wait_on(inner_guard);
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        match inner_guard.ring.pop() {
+            Some(msg) => {
+                drop(inner_guard);
+                self.shared.not_full.notify_one();
+                Ok(msg)
+            }
+            None if inner_guard.n_senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut inner_guard = self.shared.inner.lock().recover();
+        loop {
+            if let Some(msg) = inner_guard.ring.pop() {
+                drop(inner_guard);
+                self.shared.not_full.notify_one();
+                return Ok(msg);
+            }
+            if inner_guard.n_senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            inner_guard = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    self.shared
+                        .available
+                        .wait_on_timeout(inner_guard, deadline - now)
+                }
+                None => self.shared.available.wait_on(inner_guard),
+            };
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T, const N: usize> Clone for ArrayReceiver<T, N> {
+    fn clone(&self) -> Self {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_receivers += 1;
+        drop(inner_guard);
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayReceiver<T, N> {
+    fn drop(&mut self) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_receivers -= 1;
+        if inner_guard.n_receivers > 0 {
+            return;
+        }
+        // blocked senders must wake up to report the disconnect
+        self.shared.not_full.notify_all();
+        // nobody can take these anymore; drop them one at a time outside the lock, since
+        // there is nowhere to move the whole ring without allocating
+        while let Some(msg) = inner_guard.ring.pop() {
+            drop(inner_guard);
+            drop(msg);
+            inner_guard = self.shared.inner.lock().recover();
+        }
+    }
+}
+
+impl<T, const N: usize> Iterator for ArrayReceiver<T, N> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::{self, sleep};
+
+    #[test]
+    fn test_static_channel_wraparound() {
+        let (tx, rx) = new_static_channel::<i32, 3>();
+        for round in 0..10 {
+            tx.send(round * 2).unwrap();
+            tx.send(round * 2 + 1).unwrap();
+            assert_eq!(rx.recv(), Ok(round * 2));
+            assert_eq!(rx.recv(), Ok(round * 2 + 1));
+        }
+        let inner_guard = rx.shared.inner.lock().unwrap();
+        assert_eq!((inner_guard.ring.head, inner_guard.ring.len), (20 % 3, 0));
+    }
+
+    #[test]
+    fn test_static_channel_full_and_empty() {
+        let (tx, rx) = new_static_channel::<i32, 2>();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(rx.try_recv(), Ok(1));
+        tx.send(3).unwrap();
+        assert_eq!(tx.try_send(4), Err(TrySendError::Full(4)));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_static_channel_send_blocks_when_full() {
+        let (tx, rx) = new_static_channel::<i32, 1>();
+        tx.send(1).unwrap();
+        let tx_handle = thread::spawn(move || {
+            tx.send(2).unwrap();
+            tx.send(3).unwrap();
+            tx.send(4)
+        });
+        sleep(Duration::from_millis(20));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        sleep(Duration::from_millis(20));
+        drop(rx);
+        assert_eq!(tx_handle.join().unwrap(), Err(SendError::Disconnected(4)));
+    }
+
+    #[test]
+    fn test_static_channel_closes() {
+        let (tx, mut rx) = new_static_channel::<i32, 4>();
+        let tx1 = tx.clone();
+        tx.send(1).unwrap();
+        drop(tx);
+        tx1.send(2).unwrap();
+        drop(tx1);
+        assert_eq!(rx.by_ref().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_static_channel_drops_messages_once() {
+        let counter = Arc::new(());
+        let (tx, rx) = new_static_channel::<Arc<()>, 3>();
+        // wrap around so the remaining messages straddle the end of the ring
+        for _ in 0..2 {
+            tx.send(Arc::clone(&counter)).unwrap();
+        }
+        drop(rx.recv());
+        drop(rx.recv());
+        for _ in 0..3 {
+            tx.send(Arc::clone(&counter)).unwrap();
+        }
+        assert_eq!(Arc::strong_count(&counter), 4);
+        drop(rx.recv());
+        assert_eq!(Arc::strong_count(&counter), 3);
+
+        // dropping the last receiver releases what is left
+        let rx1 = rx.clone();
+        drop(rx);
+        assert_eq!(Arc::strong_count(&counter), 3);
+        drop(rx1);
+        assert_eq!(Arc::strong_count(&counter), 1);
+        assert!(tx.send(Arc::clone(&counter)).is_err());
+        assert_eq!(Arc::strong_count(&counter), 1);
+
+        // a ring dropped with messages in it, wrapped around, drops each exactly once
+        let mut ring = Ring::<Arc<()>, 3>::new();
+        for _ in 0..3 {
+            ring.push(Arc::clone(&counter));
+        }
+        drop(ring.pop());
+        ring.push(Arc::clone(&counter));
+        assert_eq!(Arc::strong_count(&counter), 4);
+        drop(ring);
+        assert_eq!(Arc::strong_count(&counter), 1);
+    }
+}
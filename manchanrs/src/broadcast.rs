@@ -0,0 +1,393 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::{BroadcastRecvError, BroadcastTryRecvError, Recover, SendError, Wait};
+
+struct Inner<T> {
+    // the last `capacity` messages sent
+    ring: VecDeque<T>,
+    // sequence number of ring[0]; the next message sent gets `head + ring.len()`
+    head: u64,
+    capacity: usize,
+    n_senders: usize,
+    n_receivers: usize,
+    // from BroadcastBuilder::retain_history: sends with no receivers are kept, not refused
+    retain_history: bool,
+}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+}
+
+pub struct BroadcastSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+// Reads the ring through its own cursor, so receivers never take messages from each
+// other. A clone starts at the same position.
+pub struct BroadcastReceiver<T> {
+    shared: Arc<Shared<T>>,
+    // sequence number of the next message to receive
+    next: u64,
+}
+
+// Options for a broadcast channel; see broadcast.
+pub struct BroadcastBuilder {
+    capacity: usize,
+    retain_history: bool,
+}
+
+// Every receiver gets every message, as a clone. The channel keeps the last `capacity`
+// messages, and a send never waits: once the ring is full it overwrites the oldest, and a
+// receiver that had not seen it gets Lagged on its next receive. `capacity` must be
+// nonzero.
+pub fn broadcast<T: Clone>(capacity: usize) -> (BroadcastSender<T>, BroadcastReceiver<T>) {
+    BroadcastBuilder::new(capacity).build()
+}
+
+impl BroadcastBuilder {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            retain_history: false,
+        }
+    }
+
+    // Keeps messages sent while no receiver exists, e.g. before the first
+    // subscribe_with_history, instead of failing those sends.
+    pub fn retain_history(mut self) -> Self {
+        self.retain_history = true;
+        self
+    }
+
+    pub fn build<T: Clone>(self) -> (BroadcastSender<T>, BroadcastReceiver<T>) {
+        assert!(self.capacity > 0, "broadcast capacity must be nonzero");
+        let shared = Arc::new(Shared {
+            inner: Mutex::new(Inner {
+                ring: VecDeque::with_capacity(self.capacity),
+                head: 0,
+                capacity: self.capacity,
+                n_senders: 1,
+                n_receivers: 1,
+                retain_history: self.retain_history,
+            }),
+            available: Condvar::new(),
+        });
+        let tx = BroadcastSender {
+            shared: Arc::clone(&shared),
+        };
+        (tx, BroadcastReceiver { shared, next: 0 })
+    }
+}
+
+impl<T> Inner<T> {
+    fn tail(&self) -> u64 {
+        self.head + self.ring.len() as u64
+    }
+}
+
+impl<T: Clone> Inner<T> {
+    // Clones the message at cursor `next` and advances it, or moves a lagging cursor up
+    // to the oldest message still held.
+    fn read(&self, next: &mut u64) -> Result<T, BroadcastTryRecvError> {
+        if *next < self.head {
+            let n_missed = self.head - *next;
+            *next = self.head;
+            return Err(BroadcastTryRecvError::Lagged(n_missed));
+        }
+        if let Some(msg) = self.ring.get((*next - self.head) as usize) {
+            *next += 1;
+            return Ok(msg.clone());
+        }
+        if self.n_senders == 0 {
+            return Err(BroadcastTryRecvError::Disconnected);
+        }
+        Err(BroadcastTryRecvError::Empty)
+    }
+}
+
+impl<T: Clone> BroadcastSender<T> {
+    // Fails only when no receiver remains, in which case the message is not kept, unless
+    // the channel retains history.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        if inner_guard.n_receivers == 0 && !inner_guard.retain_history {
+            return Err(SendError::Disconnected(msg));
+        }
+        let mut overwritten = None;
+        if inner_guard.ring.len() == inner_guard.capacity {
+            overwritten = inner_guard.ring.pop_front();
+            inner_guard.head += 1;
+        }
+        inner_guard.ring.push_back(msg);
+        drop(inner_guard);
+        self.shared.available.notify_all();
+        drop(overwritten);
+        Ok(())
+    }
+
+    // A receiver that gets only messages sent from now on.
+    pub fn subscribe(&self) -> BroadcastReceiver<T> {
+        self.subscribe_with_history(0)
+    }
+
+    // A receiver that first replays the last `n` messages, or as many as the ring still
+    // holds, then carries on with live ones. The cursor is placed under the lock, so no
+    // send can fall between the replay and what follows.
+    pub fn subscribe_with_history(&self, n: usize) -> BroadcastReceiver<T> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_receivers += 1;
+        let n_replayed = cmp::min(n, inner_guard.ring.len());
+        BroadcastReceiver {
+            shared: Arc::clone(&self.shared),
+            next: inner_guard.tail() - n_replayed as u64,
+        }
+    }
+
+    // How many messages subscribe_with_history can replay.
+    pub fn history_len(&self) -> usize {
+        self.shared.inner.lock().recover().ring.len()
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.shared.inner.lock().recover().n_receivers
+    }
+}
+
+impl<T> Clone for BroadcastSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.inner.lock().recover().n_senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for BroadcastSender<T> {
+    fn drop(&mut self) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_senders -= 1;
+        let is_channel_close = inner_guard.n_senders == 0;
+        drop(inner_guard);
+        if is_channel_close {
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+impl<T: Clone> BroadcastReceiver<T> {
+    // Once every sender is gone, this still returns what the receiver has not yet seen
+    // before reporting Disconnected.
+    pub fn recv(&mut self) -> Result<T, BroadcastRecvError> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        loop {
+            match inner_guard.read(&mut self.next) {
+                Ok(msg) => return Ok(msg),
+                Err(BroadcastTryRecvError::Lagged(n)) => {
+                    return Err(BroadcastRecvError::Lagged(n));
+                }
+                Err(BroadcastTryRecvError::Disconnected) => {
+                    return Err(BroadcastRecvError::Disconnected);
+                }
+                Err(BroadcastTryRecvError::Empty) => {}
+            }
+            inner_guard = self.shared.available.wait_on(inner_guard);
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, BroadcastTryRecvError> {
+        self.shared.inner.lock().recover().read(&mut self.next)
+    }
+
+    // How many messages this receiver has yet to see, counting any it has lagged past.
+    pub fn len(&self) -> usize {
+        let inner_guard = self.shared.inner.lock().recover();
+        (inner_guard.tail() - self.next) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Clone for BroadcastReceiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.inner.lock().recover().n_receivers += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for BroadcastReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.inner.lock().recover().n_receivers -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::{self, sleep};
+    use std::time::Duration;
+
+    #[test]
+    fn test_broadcast_every_receiver_gets_every_message() {
+        let (tx, mut rx) = broadcast(8);
+        let mut rx2 = rx.clone();
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        let mut rx3 = tx.subscribe();
+        tx.send(5).unwrap();
+        drop(tx);
+        let drain = |rx: &mut BroadcastReceiver<i32>| {
+            let mut msgs = vec![];
+            loop {
+                match rx.recv() {
+                    Ok(msg) => msgs.push(msg),
+                    Err(err) => return (msgs, err),
+                }
+            }
+        };
+        let all = (0..6).collect::<Vec<_>>();
+        assert_eq!(
+            drain(&mut rx),
+            (all.clone(), BroadcastRecvError::Disconnected)
+        );
+        assert_eq!(drain(&mut rx2), (all, BroadcastRecvError::Disconnected));
+        assert_eq!(drain(&mut rx3), (vec![5], BroadcastRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_broadcast_lagged() {
+        let (tx, mut rx) = broadcast(4);
+        for i in 0..10 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.len(), 10);
+        assert_eq!(rx.try_recv(), Err(BroadcastTryRecvError::Lagged(6)));
+        assert_eq!(rx.len(), 4);
+        assert_eq!(rx.recv(), Ok(6));
+        tx.send(10).unwrap();
+        tx.send(11).unwrap();
+        assert_eq!(rx.recv(), Err(BroadcastRecvError::Lagged(1)));
+        assert_eq!(rx.recv(), Ok(8));
+        assert_eq!(rx.try_recv(), Ok(9));
+
+        drop(rx);
+        assert_eq!(tx.send(12), Err(SendError::Disconnected(12)));
+    }
+
+    #[test]
+    fn test_broadcast_subscribe_with_history() {
+        let (tx, _rx) = broadcast(4);
+        assert_eq!(tx.history_len(), 0);
+        let mut empty = tx.subscribe_with_history(3);
+        for i in 0..6 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(tx.history_len(), 4);
+        let mut rx2 = tx.subscribe_with_history(2);
+        let mut rx3 = tx.subscribe_with_history(10);
+        tx.send(6).unwrap();
+        assert_eq!(rx2.try_recv(), Ok(4));
+        assert_eq!(rx2.try_recv(), Ok(5));
+        assert_eq!(rx2.try_recv(), Ok(6));
+        assert_eq!(rx2.try_recv(), Err(BroadcastTryRecvError::Empty));
+        // the ring moved on by one since rx3 was placed at its oldest message
+        assert_eq!(rx3.try_recv(), Err(BroadcastTryRecvError::Lagged(1)));
+        assert_eq!(rx3.try_recv(), Ok(3));
+        assert_eq!(empty.try_recv(), Err(BroadcastTryRecvError::Lagged(3)));
+    }
+
+    #[test]
+    fn test_broadcast_history_splices_with_live() {
+        let (tx, _rx) = broadcast(1000);
+        let tx2 = tx.clone();
+        let sender = thread::spawn(move || {
+            for i in 0..500 {
+                tx.send(i).unwrap();
+                if i % 50 == 0 {
+                    sleep(Duration::from_millis(1));
+                }
+            }
+        });
+        while tx2.history_len() < 100 {
+            thread::yield_now();
+        }
+        let mut late = tx2.subscribe_with_history(50);
+        drop(tx2);
+        let mut msgs = vec![];
+        while let Ok(msg) = late.recv() {
+            msgs.push(msg);
+        }
+        sender.join().unwrap();
+        // no gap and no repeat where the replay meets live messages
+        assert!(msgs.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        assert!(msgs[0] >= 50);
+        assert_eq!(msgs.last(), Some(&499));
+    }
+
+    #[test]
+    fn test_broadcast_retain_history() {
+        let (tx, rx) = broadcast::<i32>(4);
+        drop(rx);
+        assert_eq!(tx.send(1), Err(SendError::Disconnected(1)));
+
+        let (tx, rx) = BroadcastBuilder::new(4).retain_history().build();
+        drop(rx);
+        for i in 0..6 {
+            tx.send(i).unwrap();
+        }
+        let mut rx = tx.subscribe_with_history(4);
+        drop(tx);
+        let mut msgs = vec![];
+        while let Ok(msg) = rx.recv() {
+            msgs.push(msg);
+        }
+        assert_eq!(msgs, vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_broadcast_receivers_at_different_speeds() {
+        let n_msgs = 200;
+        let (tx, rx) = broadcast(16);
+        let readers: Vec<_> = [0, 1, 5]
+            .into_iter()
+            .map(|delay_ms| {
+                let mut rx = rx.clone();
+                thread::spawn(move || {
+                    let (mut n_received, mut n_missed, mut last) = (0, 0, None);
+                    loop {
+                        match rx.recv() {
+                            Ok(msg) => {
+                                assert!(last.is_none_or(|last| msg > last));
+                                last = Some(msg);
+                                n_received += 1;
+                            }
+                            Err(BroadcastRecvError::Lagged(n)) => n_missed += n,
+                            Err(BroadcastRecvError::Disconnected) => break,
+                        }
+                        sleep(Duration::from_millis(delay_ms));
+                    }
+                    (n_received, n_missed, last)
+                })
+            })
+            .collect();
+        drop(rx);
+        // the sender never waits for the slow reader
+        for i in 0..n_msgs {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        for reader in readers {
+            let (n_received, n_missed, last) = reader.join().unwrap();
+            assert_eq!(n_received + n_missed, n_msgs);
+            assert_eq!(last, Some(n_msgs - 1));
+        }
+    }
+}
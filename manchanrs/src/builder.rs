@@ -0,0 +1,380 @@
+use std::cmp;
+use std::collections::{BinaryHeap, VecDeque};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::{
+    BuildError, ChannelId, Inner, MessageSize, MsgQueue, OrderFn, OverflowPolicy, RankedLane,
+    Receiver, SendDecision, Sender, Shared, SizeFn, Watermark, Watermarks, NEXT_CHANNEL_ID,
+};
+
+pub(crate) type OnDrop<T> = Box<dyn Fn(T) + Send + Sync>;
+pub(crate) type OnSend<T> = Box<dyn Fn(&T) -> SendDecision + Send + Sync>;
+
+// Collects channel options; the defaults build the unbounded channel of new_channel.
+// Options combine as with the matching constructors, and build checks the combination
+// instead of panicking.
+pub struct ChannelBuilder<T> {
+    capacity: Option<usize>,
+    bytes: Option<(usize, SizeFn<T>)>,
+    policy: OverflowPolicy,
+    name: Option<Arc<str>>,
+    watermarks: Option<Watermarks>,
+    on_drop: Option<OnDrop<T>>,
+    on_send: Vec<OnSend<T>>,
+    order: Option<OrderFn<T>>,
+}
+
+impl<T> ChannelBuilder<T> {
+    pub fn new() -> Self {
+        Self {
+            capacity: None,
+            bytes: None,
+            policy: OverflowPolicy::Block,
+            name: None,
+            watermarks: None,
+            on_drop: None,
+            on_send: Vec::new(),
+            order: None,
+        }
+    }
+
+    // See new_bounded.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    // Needs a capacity or byte budget to take effect.
+    pub fn overflow(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // See new_channel_named.
+    pub fn name(mut self, name: impl Into<Arc<str>>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    // See new_channel_with_watermarks.
+    pub fn watermarks(
+        mut self,
+        high: usize,
+        low: usize,
+        on_watermark: impl FnMut(Watermark) + Send + 'static,
+    ) -> Self {
+        self.watermarks = Some(Watermarks {
+            high,
+            low,
+            is_high: false,
+            on_watermark: Box::new(on_watermark),
+        });
+        self
+    }
+
+    // Gets each message the overflow policy discards. It runs on the sending thread,
+    // outside the channel lock.
+    pub fn on_drop(mut self, on_drop: impl Fn(T) + Send + Sync + 'static) -> Self {
+        self.on_drop = Some(Box::new(on_drop));
+        self
+    }
+
+    // Sees each message as it is sent, before the channel is locked, and may drop it. A
+    // dropped message counts as sent, but goes to on_drop and the dropped count instead
+    // of the queue; a panicking hook drops it too. Hooks run in the order added, and a
+    // message one of them drops is not shown to the rest. A send that then fails, e.g.
+    // on a full channel, has still been seen.
+    pub fn on_send(mut self, on_send: impl Fn(&T) -> SendDecision + Send + Sync + 'static) -> Self {
+        self.on_send.push(Box::new(on_send));
+        self
+    }
+
+    // See priority_channel_by_key.
+    pub(crate) fn order_by(
+        mut self,
+        order: impl Fn(&T, &T) -> cmp::Ordering + Send + Sync + 'static,
+    ) -> Self {
+        self.order = Some(Box::new(order));
+        self
+    }
+
+    pub fn build(self) -> Result<(Sender<T>, Receiver<T>), BuildError> {
+        if self.capacity == Some(0) {
+            return Err(BuildError::ZeroCapacity);
+        }
+        if self.bytes.is_some_and(|(max_bytes, _)| max_bytes == 0) {
+            return Err(BuildError::ZeroByteBudget);
+        }
+        if self.policy != OverflowPolicy::Block && self.capacity.is_none() && self.bytes.is_none() {
+            return Err(BuildError::OverflowWithoutBound);
+        }
+        if let Some(watermarks) = &self.watermarks {
+            if watermarks.low > watermarks.high {
+                return Err(BuildError::WatermarksReversed);
+            }
+            if self
+                .capacity
+                .is_some_and(|capacity| watermarks.high > capacity)
+            {
+                return Err(BuildError::WatermarkAboveCapacity);
+            }
+        }
+        Ok(self.finish())
+    }
+
+    // Builds without checking the options; callers vouch for them.
+    pub(crate) fn finish(self) -> (Sender<T>, Receiver<T>) {
+        let inner = Inner {
+            queue: MsgQueue {
+                watermarks: self.watermarks,
+                ranked: self.order.map(RankedLane::new),
+                ..MsgQueue::default()
+            },
+            delayed: BinaryHeap::new(),
+            n_delayed: 0,
+            n_senders: 1,
+            n_receivers: 1,
+            n_keep_alive: 0,
+            capacity: self.capacity,
+            max_bytes: self.bytes.map(|(max_bytes, _)| max_bytes),
+            n_delayed_bytes: 0,
+            policy: self.policy,
+            n_dropped: 0,
+            n_reserved: 0,
+            closed: false,
+            close_reason: None,
+            send_line: VecDeque::new(),
+            next_ticket: 0,
+            generation: 0,
+            ticker: None,
+            on_ready: None,
+        };
+        let shared = Shared {
+            inner: Mutex::new(inner),
+            available: Condvar::new(),
+            selective: Condvar::new(),
+            not_full: Condvar::new(),
+            drained: Condvar::new(),
+            below_low: Condvar::new(),
+            no_receivers: Condvar::new(),
+            size_of: self.bytes.map(|(_, size_of)| size_of),
+            id: ChannelId(NEXT_CHANNEL_ID.fetch_add(1, Ordering::Relaxed)),
+            name: self.name,
+            on_drop: self.on_drop,
+            on_send: self.on_send,
+            #[cfg(feature = "async")]
+            wakers: Mutex::default(),
+            #[cfg(all(unix, feature = "os-poll"))]
+            ready_pipe: std::sync::OnceLock::new(),
+        };
+        let arc_shared = Arc::new(shared);
+        let tx = Sender {
+            shared: arc_shared.clone(),
+        };
+        let rx = Receiver::from_shared(arc_shared);
+        (tx, rx)
+    }
+}
+
+impl<T: MessageSize> ChannelBuilder<T> {
+    // See new_bounded_bytes.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.bytes = Some((max_bytes, T::size));
+        self
+    }
+}
+
+impl<T> Default for ChannelBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TryRecvError, TrySendError};
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[test]
+    fn test_builder_defaults() {
+        let (tx, rx) = ChannelBuilder::new().build().unwrap();
+        for i in 0..100 {
+            tx.try_send(i).unwrap();
+        }
+        assert_eq!(tx.capacity(), None);
+        assert_eq!(tx.name(), None);
+        assert_eq!(rx.drain().len(), 100);
+    }
+
+    #[test]
+    fn test_builder_capacity() {
+        let (tx, _rx) = ChannelBuilder::new().capacity(2).build().unwrap();
+        assert_eq!(tx.capacity(), Some(2));
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+    }
+
+    #[test]
+    fn test_builder_overflow_and_on_drop() {
+        let dropped = Arc::new(Mutex::new(vec![]));
+        let dropped1 = Arc::clone(&dropped);
+        let (tx, rx) = ChannelBuilder::new()
+            .capacity(2)
+            .overflow(OverflowPolicy::DropOldest)
+            .on_drop(move |msg| dropped1.lock().unwrap().push(msg))
+            .build()
+            .unwrap();
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.drain(), vec![3, 4]);
+        assert_eq!(*dropped.lock().unwrap(), vec![0, 1, 2]);
+        assert_eq!(rx.dropped_count(), 3);
+
+        let n_dropped = Arc::new(AtomicUsize::new(0));
+        let n_dropped1 = Arc::clone(&n_dropped);
+        let (tx, rx) = ChannelBuilder::new()
+            .capacity(1)
+            .overflow(OverflowPolicy::DropNewest)
+            .on_drop(move |_| {
+                n_dropped1.fetch_add(1, Ordering::SeqCst);
+            })
+            .build()
+            .unwrap();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(n_dropped.load(Ordering::SeqCst), 1);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_builder_on_send() {
+        let n_seen = Arc::new(AtomicUsize::new(0));
+        let n_seen1 = Arc::clone(&n_seen);
+        let dropped = Arc::new(Mutex::new(vec![]));
+        let dropped1 = Arc::clone(&dropped);
+        let (tx, rx) = ChannelBuilder::new()
+            .on_send(move |_| {
+                n_seen1.fetch_add(1, Ordering::SeqCst);
+                SendDecision::Accept
+            })
+            .on_send(|val: &i32| match val % 2 {
+                0 => SendDecision::Accept,
+                _ => SendDecision::Drop,
+            })
+            .on_drop(move |msg| dropped1.lock().unwrap().push(msg))
+            .build()
+            .unwrap();
+        for i in 0..6 {
+            tx.send(i).unwrap();
+        }
+        tx.try_send(7).unwrap();
+        tx.send_timeout(8, Duration::ZERO).unwrap();
+        tx.reserve().unwrap().send(9);
+        assert_eq!(rx.drain(), vec![0, 2, 4, 8]);
+        assert_eq!(n_seen.load(Ordering::SeqCst), 9);
+        assert_eq!(*dropped.lock().unwrap(), vec![1, 3, 5, 7, 9]);
+        assert_eq!(rx.dropped_count(), 5);
+
+        let mut msgs = VecDeque::from([10, 11, 12]);
+        assert_eq!(tx.try_send_many(&mut msgs), 3);
+        assert_eq!(rx.drain(), vec![10, 12]);
+
+        // batched sends run the hooks on every item too
+        assert_eq!(tx.send_all(20..26), 3);
+        let mut tx1 = tx.clone();
+        tx1.extend([30, 31]);
+        assert_eq!(rx.drain(), vec![20, 22, 24, 30]);
+        assert_eq!(n_seen.load(Ordering::SeqCst), 20);
+        assert_eq!(rx.dropped_count(), 10);
+        assert_eq!(dropped.lock().unwrap()[6..], [21, 23, 25, 31]);
+    }
+
+    #[test]
+    fn test_builder_on_send_panic() {
+        let (tx, rx) = ChannelBuilder::new()
+            .on_send(|val: &i32| {
+                assert!(*val != 13, "unlucky");
+                SendDecision::Accept
+            })
+            .build()
+            .unwrap();
+        tx.send(12).unwrap();
+        tx.send(13).unwrap();
+        tx.send(14).unwrap();
+        assert_eq!(rx.drain(), vec![12, 14]);
+        assert_eq!(rx.dropped_count(), 1);
+    }
+
+    #[test]
+    fn test_builder_name() {
+        let (tx, rx) = ChannelBuilder::<i32>::new().name("jobs").build().unwrap();
+        assert_eq!(tx.name(), Some("jobs"));
+        assert_eq!(rx.name(), Some("jobs"));
+    }
+
+    #[test]
+    fn test_builder_watermarks() {
+        let marks = Arc::new(Mutex::new(vec![]));
+        let marks1 = Arc::clone(&marks);
+        let (tx, rx) = ChannelBuilder::new()
+            .capacity(4)
+            .watermarks(3, 2, move |mark| marks1.lock().unwrap().push(mark))
+            .build()
+            .unwrap();
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(*marks.lock().unwrap(), vec![Watermark::High]);
+        rx.recv().unwrap();
+        rx.recv().unwrap();
+        assert_eq!(
+            *marks.lock().unwrap(),
+            vec![Watermark::High, Watermark::Low]
+        );
+    }
+
+    #[test]
+    fn test_builder_max_bytes() {
+        struct Blob(usize);
+        impl MessageSize for Blob {
+            fn size(&self) -> usize {
+                self.0
+            }
+        }
+        let (tx, _rx) = ChannelBuilder::new().max_bytes(10).build().unwrap();
+        tx.send(Blob(6)).unwrap();
+        assert!(matches!(tx.try_send(Blob(5)), Err(TrySendError::Full(_))));
+        tx.try_send(Blob(4)).unwrap();
+    }
+
+    #[test]
+    fn test_builder_rejects_bad_options() {
+        let err = |builder: ChannelBuilder<i32>| builder.build().err().unwrap();
+        assert_eq!(
+            err(ChannelBuilder::new().capacity(0)),
+            BuildError::ZeroCapacity
+        );
+        assert_eq!(
+            err(ChannelBuilder::new().overflow(OverflowPolicy::DropOldest)),
+            BuildError::OverflowWithoutBound
+        );
+        assert_eq!(
+            err(ChannelBuilder::new().watermarks(1, 2, |_| {})),
+            BuildError::WatermarksReversed
+        );
+        assert_eq!(
+            err(ChannelBuilder::new().capacity(4).watermarks(5, 2, |_| {})),
+            BuildError::WatermarkAboveCapacity
+        );
+        assert_eq!(
+            BuildError::WatermarkAboveCapacity.to_string(),
+            "high watermark exceeds the capacity, so it can never be reached"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::{Recover, RecvError, SendError, TryRecvError, Wait};
+
+struct Inner<K, T> {
+    // pending keys in arrival order; each appears once
+    order: VecDeque<K>,
+    pending: HashMap<K, T>,
+    // values overwritten before being received, by key, since the channel was made
+    n_replaced: HashMap<K, u64>,
+    n_senders: usize,
+    has_receiver: bool,
+}
+
+struct Shared<K, T> {
+    inner: Mutex<Inner<K, T>>,
+    available: Condvar,
+}
+
+pub struct CoalescingSender<K, T> {
+    shared: Arc<Shared<K, T>>,
+}
+
+pub struct CoalescingReceiver<K, T> {
+    shared: Arc<Shared<K, T>>,
+}
+
+// Holds at most one pending value per key: a send for a key that is already pending
+// replaces its value in place, keeping the key's position, so a busy key cannot push the
+// others back. The queue is bounded by the number of distinct keys.
+pub fn coalescing<K: Eq + Hash + Clone, T>() -> (CoalescingSender<K, T>, CoalescingReceiver<K, T>) {
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(Inner {
+            order: VecDeque::new(),
+            pending: HashMap::new(),
+            n_replaced: HashMap::new(),
+            n_senders: 1,
+            has_receiver: true,
+        }),
+        available: Condvar::new(),
+    });
+    let tx = CoalescingSender {
+        shared: Arc::clone(&shared),
+    };
+    (tx, CoalescingReceiver { shared })
+}
+
+impl<K: Eq + Hash + Clone, T> CoalescingSender<K, T> {
+    pub fn send(&self, key: K, value: T) -> Result<(), SendError<(K, T)>> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        if !inner_guard.has_receiver {
+            return Err(SendError::Disconnected((key, value)));
+        }
+        let inner = &mut *inner_guard;
+        let replaced = match inner.pending.entry(key) {
+            Entry::Occupied(mut entry) => {
+                *inner.n_replaced.entry(entry.key().clone()).or_default() += 1;
+                Some(mem::replace(entry.get_mut(), value))
+            }
+            Entry::Vacant(entry) => {
+                inner.order.push_back(entry.key().clone());
+                entry.insert(value);
+                None
+            }
+        };
+        drop(inner_guard);
+        if replaced.is_none() {
+            self.shared.available.notify_one();
+        }
+        // an overwritten value is dropped outside the lock
+        drop(replaced);
+        Ok(())
+    }
+}
+
+impl<K, T> Clone for CoalescingSender<K, T> {
+    fn clone(&self) -> Self {
+        self.shared.inner.lock().recover().n_senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<K, T> Drop for CoalescingSender<K, T> {
+    fn drop(&mut self) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_senders -= 1;
+        let is_channel_close = inner_guard.n_senders == 0;
+        drop(inner_guard);
+        if is_channel_close {
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, T> CoalescingReceiver<K, T> {
+    // The oldest pending key with its latest value.
+    pub fn recv(&self) -> Result<(K, T), RecvError> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        loop {
+            if let Some(entry) = inner_guard.pop() {
+                return Ok(entry);
+            }
+            if inner_guard.n_senders == 0 {
+                return Err(RecvError);
+            }
+            inner_guard = self.shared.available.wait_on(inner_guard);
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<(K, T), TryRecvError> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        match inner_guard.pop() {
+            Some(entry) => Ok(entry),
+            None if inner_guard.n_senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    pub fn pending_keys(&self) -> usize {
+        self.shared.inner.lock().recover().order.len()
+    }
+
+    // How many values sent for `key` were overwritten before being received.
+    pub fn replaced_count(&self, key: &K) -> u64 {
+        let inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_replaced.get(key).copied().unwrap_or(0)
+    }
+}
+
+impl<K: Eq + Hash, T> Inner<K, T> {
+    fn pop(&mut self) -> Option<(K, T)> {
+        let key = self.order.pop_front()?;
+        let value = self.pending.remove(&key).unwrap();
+        Some((key, value))
+    }
+}
+
+impl<K, T> Drop for CoalescingReceiver<K, T> {
+    fn drop(&mut self) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.has_receiver = false;
+        let pending = mem::take(&mut inner_guard.pending);
+        inner_guard.order.clear();
+        drop(inner_guard);
+        drop(pending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::{self, sleep};
+    use std::time::Duration;
+
+    #[test]
+    fn test_coalescing_keeps_latest_per_key() {
+        let (tx, rx) = coalescing();
+        tx.send("a", 1).unwrap();
+        tx.send("b", 1).unwrap();
+        tx.send("a", 2).unwrap();
+        tx.send("c", 1).unwrap();
+        tx.send("a", 3).unwrap();
+        assert_eq!(rx.pending_keys(), 3);
+        assert_eq!(rx.replaced_count(&"a"), 2);
+        assert_eq!(rx.replaced_count(&"b"), 0);
+        assert_eq!(rx.recv(), Ok(("a", 3)));
+        tx.send("a", 4).unwrap();
+        assert_eq!(rx.recv(), Ok(("b", 1)));
+        assert_eq!(rx.recv(), Ok(("c", 1)));
+        assert_eq!(rx.try_recv(), Ok(("a", 4)));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(rx.recv(), Err(RecvError));
+
+        let (tx, rx) = coalescing();
+        drop(rx);
+        assert_eq!(tx.send(1, 2), Err(SendError::Disconnected((1, 2))));
+    }
+
+    #[test]
+    fn test_coalescing_hot_key_stays_flat() {
+        let n_sends = 1_000_000;
+        let (tx, rx) = coalescing();
+        let receiver = thread::spawn(move || {
+            let mut last = None;
+            while let Ok((key, value)) = rx.recv() {
+                assert_eq!(key, "hot");
+                assert!(rx.pending_keys() <= 1);
+                last = Some(value);
+                sleep(Duration::from_millis(1));
+            }
+            last
+        });
+        for i in 0..n_sends {
+            tx.send("hot", i).unwrap();
+        }
+        drop(tx);
+        assert_eq!(receiver.join().unwrap(), Some(n_sends - 1));
+    }
+}
@@ -0,0 +1,2 @@
+// Drop-in replacements for other channel APIs, built on this crate's channels.
+pub mod mpsc;
@@ -0,0 +1,391 @@
+// The std::sync::mpsc API over this crate's channels, so code written against std can
+// switch by changing its imports. Receivers are not Clone here, as in std.
+//
+// sync_channel(0) is a rendezvous channel built on a bound of one, and differs from std in
+// two ways: a send that has handed its value over returns Ok even if the receiver is then
+// dropped before taking it, and try_send succeeds whenever the slot is free rather than
+// only when a receiver is already waiting.
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+pub use crate::{RecvError, RecvTimeoutError, TryRecvError};
+
+pub struct Sender<T> {
+    inner: crate::Sender<T>,
+}
+
+pub struct SyncSender<T> {
+    inner: crate::Sender<T>,
+    // from sync_channel(0): send also waits for a receiver to take the value
+    rendezvous: bool,
+}
+
+pub struct Receiver<T> {
+    inner: crate::Receiver<T>,
+}
+
+pub struct Iter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+pub struct TryIter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+
+// Carries the unsent message.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+// Both variants carry the unsent message.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    Full(T),
+    Disconnected(T),
+}
+
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let (tx, rx) = crate::new_channel();
+    (Sender { inner: tx }, Receiver { inner: rx })
+}
+
+pub fn sync_channel<T>(bound: usize) -> (SyncSender<T>, Receiver<T>) {
+    let (tx, rx) = crate::new_bounded(bound.max(1));
+    let tx = SyncSender {
+        inner: tx,
+        rendezvous: bound == 0,
+    };
+    (tx, Receiver { inner: rx })
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.inner
+            .send(t)
+            .map_err(|err| SendError(err.into_inner()))
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Sender {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+impl<T> SyncSender<T> {
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        if self.rendezvous {
+            let handle = self
+                .inner
+                .send_tracked(t)
+                .map_err(|err| SendError(err.into_inner()))?;
+            // dropped by a departing receiver after the handover, which std would report
+            // with the value; it is gone by now
+            let _ = handle.wait();
+            return Ok(());
+        }
+        self.inner
+            .send(t)
+            .map_err(|err| SendError(err.into_inner()))
+    }
+
+    pub fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        self.inner.try_send(t).map_err(|err| match err {
+            crate::TrySendError::Full(t) => TrySendError::Full(t),
+            crate::TrySendError::Disconnected(t) | crate::TrySendError::Closed(t) => {
+                TrySendError::Disconnected(t)
+            }
+        })
+    }
+}
+
+impl<T> Clone for SyncSender<T> {
+    fn clone(&self) -> Self {
+        SyncSender {
+            inner: self.inner.clone(),
+            rendezvous: self.rendezvous,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SyncSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncSender").finish_non_exhaustive()
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.inner.recv()
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.inner.try_recv()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.inner.recv_timeout(timeout)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sending on a closed channel")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(..) => f.write_str("Full(..)"),
+            TrySendError::Disconnected(..) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(..) => f.write_str("sending on a full channel"),
+            TrySendError::Disconnected(..) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    fn from(err: SendError<T>) -> Self {
+        TrySendError::Disconnected(err.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    // The examples below are ported from the std::sync::mpsc docs.
+
+    #[test]
+    fn test_mpsc_channel() {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            sender.send(53).unwrap();
+        });
+        assert_eq!(53, receiver.recv().unwrap());
+    }
+
+    #[test]
+    fn test_mpsc_shared_sender() {
+        let (tx, rx) = channel();
+        for i in 0..10 {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                tx.send(i).unwrap();
+            });
+        }
+        drop(tx);
+        let mut received = rx.iter().collect::<Vec<_>>();
+        received.sort();
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_mpsc_send_disconnected() {
+        let (tx, rx) = channel();
+        assert!(tx.send(1).is_ok());
+        drop(rx);
+        assert_eq!(tx.send(1), Err(SendError(1)));
+        assert_eq!(
+            tx.send(2).unwrap_err().to_string(),
+            "sending on a closed channel"
+        );
+    }
+
+    #[test]
+    fn test_mpsc_sync_channel() {
+        let (sender, receiver) = sync_channel(1);
+        sender.send(1).unwrap();
+        thread::spawn(move || {
+            // blocks until the first message is received
+            sender.send(2).unwrap();
+        });
+        assert_eq!(receiver.recv().unwrap(), 1);
+        assert_eq!(receiver.recv().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_mpsc_try_send() {
+        let (sync_sender, receiver) = sync_channel(1);
+        let sync_sender2 = sync_sender.clone();
+        thread::spawn(move || {
+            sync_sender.send(1).unwrap();
+            sync_sender.send(2).unwrap();
+        });
+        thread::spawn(move || {
+            // this may or may not fit, depending on who got there first
+            let _ = sync_sender2.try_send(3);
+        });
+        let mut msgs = receiver.iter().collect::<Vec<_>>();
+        msgs.retain(|&msg| msg != 3);
+        assert_eq!(msgs, vec![1, 2]);
+
+        let (tx, rx) = sync_channel(1);
+        assert_eq!(tx.try_send(1), Ok(()));
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+        drop(rx);
+        assert_eq!(tx.try_send(3), Err(TrySendError::Disconnected(3)));
+    }
+
+    #[test]
+    fn test_mpsc_sync_channel_rendezvous() {
+        let (tx, rx) = sync_channel(0);
+        let taken = Arc::new(AtomicBool::new(false));
+        let receiver = thread::spawn({
+            let taken = Arc::clone(&taken);
+            move || {
+                thread::sleep(Duration::from_millis(100));
+                taken.store(true, Ordering::SeqCst);
+                rx.recv()
+            }
+        });
+        tx.send(1).unwrap();
+        // send returned only once the receiver had the value
+        assert!(taken.load(Ordering::SeqCst));
+        assert_eq!(receiver.join().unwrap(), Ok(1));
+        assert_eq!(tx.send(2), Err(SendError(2)));
+    }
+
+    #[test]
+    fn test_mpsc_try_recv() {
+        let (_, receiver) = channel::<i32>();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (sender, receiver) = channel();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        sender.send(1).unwrap();
+        assert_eq!(receiver.try_recv(), Ok(1));
+    }
+
+    #[test]
+    fn test_mpsc_recv_timeout() {
+        let (send, recv) = channel();
+        thread::spawn(move || {
+            send.send('a').unwrap();
+        });
+        assert_eq!(recv.recv_timeout(Duration::from_millis(400)), Ok('a'));
+
+        let (send, recv) = channel();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(800));
+            send.send('a').unwrap();
+        });
+        assert_eq!(
+            recv.recv_timeout(Duration::from_millis(400)),
+            Err(RecvTimeoutError::Timeout)
+        );
+    }
+
+    #[test]
+    fn test_mpsc_try_iter() {
+        let (sender, receiver) = channel();
+        assert_eq!(receiver.try_iter().next(), None);
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_mpsc_into_iter() {
+        let (send, recv) = channel();
+        thread::spawn(move || {
+            send.send(1u8).unwrap();
+            send.send(2u8).unwrap();
+            send.send(3u8).unwrap();
+        });
+        let mut total = 0;
+        for x in &recv {
+            total += x;
+        }
+        assert_eq!(total, 6);
+
+        let (send, recv) = channel();
+        send.send(1).unwrap();
+        drop(send);
+        assert_eq!(recv.into_iter().collect::<Vec<_>>(), vec![1]);
+    }
+}
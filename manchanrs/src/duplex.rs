@@ -0,0 +1,94 @@
+use crate::{new_channel, Receiver, RecvError, SendError, Sender};
+
+// One side of a conversation made by duplex: sends S to the peer and receives R from it.
+pub struct Endpoint<S, R> {
+    tx: Sender<S>,
+    rx: Receiver<R>,
+}
+
+// Two channels cross-connected, one per direction. Dropping either endpoint closes both:
+// the peer receives what was already sent and then Err(RecvError), and its sends fail.
+pub fn duplex<A, B>() -> (Endpoint<A, B>, Endpoint<B, A>) {
+    let (a_tx, a_rx) = new_channel();
+    let (b_tx, b_rx) = new_channel();
+    let left = Endpoint { tx: a_tx, rx: b_rx };
+    (left, Endpoint { tx: b_tx, rx: a_rx })
+}
+
+impl<S, R> Endpoint<S, R> {
+    pub fn send(&self, msg: S) -> Result<(), SendError<S>> {
+        self.tx.send(msg)
+    }
+
+    // Err(RecvError) once the peer is gone and everything it sent has been received.
+    pub fn recv(&self) -> Result<R, RecvError> {
+        self.rx.recv()
+    }
+
+    // The two directions as separate halves; dropping one closes only its own direction.
+    pub fn split(self) -> (Sender<S>, Receiver<R>) {
+        (self.tx, self.rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_duplex_ping_pong() {
+        let (client, server) = duplex::<u32, String>();
+        let server = thread::spawn(move || {
+            let mut n_served = 0;
+            while let Ok(n) = server.recv() {
+                server.send(format!("pong {n}")).unwrap();
+                n_served += 1;
+            }
+            n_served
+        });
+        for n in 0..100 {
+            client.send(n).unwrap();
+            assert_eq!(client.recv(), Ok(format!("pong {n}")));
+        }
+        drop(client);
+        assert_eq!(server.join().unwrap(), 100);
+    }
+
+    #[test]
+    fn test_duplex_peer_drop_closes_both_ways() {
+        let (left, right) = duplex();
+        left.send(1).unwrap();
+        right.send('a').unwrap();
+        right.send('b').unwrap();
+        drop(right);
+        // what the peer sent before leaving is still received
+        assert_eq!(left.recv(), Ok('a'));
+        assert_eq!(left.recv(), Ok('b'));
+        assert_eq!(left.recv(), Err(RecvError));
+        assert_eq!(left.send(2), Err(SendError::Disconnected(2)));
+
+        let (left, right) = duplex::<u8, u8>();
+        let peer = thread::spawn(move || {
+            assert_eq!(right.recv(), Ok(1));
+            // leaves mid-conversation
+        });
+        left.send(1).unwrap();
+        peer.join().unwrap();
+        assert_eq!(left.recv(), Err(RecvError));
+        assert!(left.send(2).is_err());
+    }
+
+    #[test]
+    fn test_duplex_split_half_close() {
+        let (left, right) = duplex::<u8, _>();
+        let (left_tx, left_rx) = left.split();
+        drop(left_tx);
+        assert_eq!(right.recv(), Err(RecvError));
+        // the other direction stays open
+        right.send("still here").unwrap();
+        assert_eq!(left_rx.recv(), Ok("still here"));
+        drop(left_rx);
+        assert!(right.send("gone").is_err());
+    }
+}
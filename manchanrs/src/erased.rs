@@ -0,0 +1,246 @@
+use std::any::{Any, TypeId};
+use std::marker::PhantomData;
+
+use crate::{
+    new_channel, AnyRecvError, AnySendError, Receiver, RecvError, SendError, Sender, TryRecvError,
+};
+
+type AnyMsg = Box<dyn Any + Send>;
+
+// Sends values of any type, checked against the channel behind it; see Sender::erase.
+pub struct AnySender {
+    tx: Box<dyn ErasedSender>,
+}
+
+// Receives values of any type, downcast by the caller.
+pub struct AnyReceiver {
+    rx: Receiver<AnyMsg>,
+}
+
+// An AnyReceiver that expects every message to be a T; see AnyReceiver::typed.
+pub struct TypedReceiver<T> {
+    rx: AnyReceiver,
+    _msg: PhantomData<fn() -> T>,
+}
+
+trait ErasedSender: Send + Sync {
+    fn send_any(&self, msg: AnyMsg) -> Result<(), AnySendError<AnyMsg>>;
+    fn clone_box(&self) -> Box<dyn ErasedSender>;
+    fn is_disconnected(&self) -> bool;
+}
+
+// A channel of Box<dyn Any + Send>, which carries values of whatever type is sent.
+pub fn any_channel() -> (AnySender, AnyReceiver) {
+    let (tx, rx) = new_channel::<AnyMsg>();
+    (tx.erase(), rx.erase())
+}
+
+impl<T: Send + 'static> Sender<T> {
+    // Sends into this same channel through an AnySender, which rejects values that are
+    // not a T. Erasing a Sender<Box<dyn Any + Send>> accepts anything.
+    pub fn erase(self) -> AnySender {
+        AnySender { tx: Box::new(self) }
+    }
+}
+
+impl Receiver<AnyMsg> {
+    pub fn erase(self) -> AnyReceiver {
+        AnyReceiver { rx: self }
+    }
+}
+
+impl AnySender {
+    pub fn send_value<T: Send + 'static>(&self, value: T) -> Result<(), AnySendError<T>> {
+        self.send_any(into_any(value))
+            .map_err(|err| err.map(|msg| from_any(msg).unwrap_or_else(|_| unreachable!())))
+    }
+
+    // Sends an already boxed value as is, rather than boxing it again.
+    pub fn send_any(
+        &self,
+        msg: Box<dyn Any + Send>,
+    ) -> Result<(), AnySendError<Box<dyn Any + Send>>> {
+        self.tx.send_any(msg)
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.tx.is_disconnected()
+    }
+}
+
+impl Clone for AnySender {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone_box(),
+        }
+    }
+}
+
+impl<T: Send + 'static> ErasedSender for Sender<T> {
+    fn send_any(&self, msg: AnyMsg) -> Result<(), AnySendError<AnyMsg>> {
+        let msg = from_any::<T>(msg).map_err(AnySendError::Mismatch)?;
+        self.send(msg).map_err(|err| match err {
+            SendError::Disconnected(msg) => AnySendError::Disconnected(into_any(msg)),
+            SendError::Closed(msg) => AnySendError::Closed(into_any(msg)),
+            SendError::Full(msg) => AnySendError::Full(into_any(msg)),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn ErasedSender> {
+        Box::new(self.clone())
+    }
+
+    fn is_disconnected(&self) -> bool {
+        Sender::is_disconnected(self)
+    }
+}
+
+impl AnyReceiver {
+    pub fn recv_any(&self) -> Result<Box<dyn Any + Send>, RecvError> {
+        self.rx.recv()
+    }
+
+    pub fn try_recv_any(&self) -> Result<Box<dyn Any + Send>, TryRecvError> {
+        self.rx.try_recv()
+    }
+
+    // Receives the next message and downcasts it to a T. A message of another type is
+    // handed back in the error rather than dropped.
+    pub fn recv_as<T: 'static>(&self) -> Result<T, AnyRecvError> {
+        let msg = self.recv_any()?;
+        from_any(msg).map_err(|msg| AnyRecvError::Mismatch {
+            actual: (*msg).type_id(),
+            msg,
+        })
+    }
+
+    pub fn typed<T: 'static>(self) -> TypedReceiver<T> {
+        TypedReceiver {
+            rx: self,
+            _msg: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> Receiver<Box<dyn Any + Send>> {
+        self.rx
+    }
+}
+
+impl Clone for AnyReceiver {
+    fn clone(&self) -> Self {
+        Self {
+            rx: self.rx.clone(),
+        }
+    }
+}
+
+impl<T: 'static> TypedReceiver<T> {
+    pub fn recv(&self) -> Result<T, AnyRecvError> {
+        self.rx.recv_as()
+    }
+
+    pub fn into_any(self) -> AnyReceiver {
+        self.rx
+    }
+}
+
+// A Box<dyn Any + Send> is passed through rather than boxed again, so that erasing a
+// channel of them carries the values inside.
+fn into_any<T: Send + 'static>(msg: T) -> AnyMsg {
+    let mut slot = Some(msg);
+    match (&mut slot as &mut dyn Any).downcast_mut::<Option<AnyMsg>>() {
+        Some(any) => any.take().unwrap(),
+        None => Box::new(slot.unwrap()),
+    }
+}
+
+fn from_any<T: 'static>(msg: AnyMsg) -> Result<T, AnyMsg> {
+    if TypeId::of::<T>() == TypeId::of::<AnyMsg>() {
+        let mut slot = Some(msg);
+        let typed = (&mut slot as &mut dyn Any).downcast_mut::<Option<T>>();
+        return Ok(typed.unwrap().take().unwrap());
+    }
+    msg.downcast().map(|msg| *msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_any_channel_dispatch() {
+        let (tx, rx) = any_channel();
+        let producer = thread::spawn(move || {
+            tx.send_value(1u32).unwrap();
+            tx.send_value(String::from("two")).unwrap();
+            tx.send_value(3u32).unwrap();
+            tx.send_value(4.0f64).unwrap();
+        });
+        producer.join().unwrap();
+
+        let (mut numbers, mut strings, mut unknown) = (vec![], vec![], vec![]);
+        while let Ok(msg) = rx.try_recv_any() {
+            let id = (*msg).type_id();
+            if id == TypeId::of::<u32>() {
+                numbers.push(*msg.downcast::<u32>().unwrap());
+            } else if id == TypeId::of::<String>() {
+                strings.push(*msg.downcast::<String>().unwrap());
+            } else {
+                unknown.push(msg);
+            }
+        }
+        assert_eq!(numbers, [1, 3]);
+        assert_eq!(strings, ["two"]);
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].downcast_ref::<f64>(), Some(&4.0));
+    }
+
+    #[test]
+    fn test_any_recv_as_mismatch() {
+        let (tx, rx) = any_channel();
+        tx.send_value("text").unwrap();
+        tx.send_value(5u8).unwrap();
+        match rx.recv_as::<u8>() {
+            Err(AnyRecvError::Mismatch { actual, msg }) => {
+                assert_eq!(actual, TypeId::of::<&str>());
+                assert_eq!(msg.downcast_ref::<&str>(), Some(&"text"));
+            }
+            other => panic!("expected a mismatch, got {other:?}"),
+        }
+        assert_eq!(rx.recv_as::<u8>().unwrap(), 5);
+
+        // an already boxed value is sent as is
+        tx.send_any(Box::new(6u8)).unwrap();
+        assert_eq!(rx.recv_as::<u8>().unwrap(), 6);
+        drop(tx);
+        assert!(matches!(
+            rx.recv_as::<u8>(),
+            Err(AnyRecvError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn test_any_erase_and_typed() {
+        let (tx, rx) = new_channel::<u32>();
+        let any_tx = tx.erase();
+        any_tx.send_value(7u32).unwrap();
+        assert_eq!(any_tx.send_value(8i64), Err(AnySendError::Mismatch(8i64)));
+        assert_eq!(rx.try_recv(), Ok(7));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(rx);
+        assert!(any_tx.is_disconnected());
+        assert_eq!(
+            any_tx.clone().send_value(9u32),
+            Err(AnySendError::Disconnected(9))
+        );
+
+        let (tx, rx) = any_channel();
+        let rx = rx.typed::<String>();
+        tx.send_value(String::from("a")).unwrap();
+        tx.send_value('b').unwrap();
+        assert_eq!(rx.recv().unwrap(), "a");
+        assert!(matches!(rx.recv(), Err(AnyRecvError::Mismatch { .. })));
+        assert_eq!(rx.into_any().into_inner().len(), 0);
+    }
+}
@@ -0,0 +1,624 @@
+use std::any::{Any, TypeId};
+use std::error::Error;
+use std::fmt;
+
+// Disconnected when no receivers remain, Closed after Receiver::close, Full when a full
+// channel with OverflowPolicy::Reject turned the message away; all carry the unsent message.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SendError<T> {
+    Disconnected(T),
+    Closed(T),
+    Full(T),
+}
+
+// Every variant carries the unsent message.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SendTimeoutError<T> {
+    Timeout(T),
+    Disconnected(T),
+    Closed(T),
+}
+
+// Every variant carries the unsent message.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    Full(T),
+    Disconnected(T),
+    Closed(T),
+}
+
+// Every sender was dropped, or the channel closed, and nothing is left to receive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    Timeout,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvCancelError {
+    Cancelled,
+    Disconnected,
+}
+
+// Lagged means the receiver fell so far behind that the oldest messages it had not seen
+// were overwritten; it carries how many were missed, and receiving resumes at the oldest
+// one still held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastRecvError {
+    Lagged(u64),
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastTryRecvError {
+    Empty,
+    Lagged(u64),
+    Disconnected,
+}
+
+// Mismatch carries the received message, which was not of the requested type, along
+// with the TypeId of what it was.
+pub enum AnyRecvError {
+    Mismatch {
+        actual: TypeId,
+        msg: Box<dyn Any + Send>,
+    },
+    Disconnected,
+}
+
+// Every variant carries the unsent message; Mismatch means the sender was erased from a
+// channel of another type.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AnySendError<T> {
+    Mismatch(T),
+    Disconnected(T),
+    Closed(T),
+    Full(T),
+}
+
+// The tracked message was discarded without any receiver taking it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeliveryError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryTimeoutError {
+    Timeout,
+    Dropped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryReserveError {
+    Full,
+    Disconnected,
+    Closed,
+}
+
+// Every receiver was dropped, or the channel closed, before a slot could be reserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReserveError;
+
+// Every receiver was dropped, so pending messages will never be received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushError;
+
+// The channel was closed, or lost its last sender with no KeepAlive guard, so
+// receivers may already have seen the end of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelClosed;
+
+// A sender, or a permit holding one, is still alive, so the channel cannot be reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushTimeoutError {
+    Timeout,
+    Disconnected,
+}
+
+// ChannelBuilder::build was given options that cannot work together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    ZeroCapacity,
+    ZeroByteBudget,
+    OverflowWithoutBound,
+    WatermarksReversed,
+    WatermarkAboveCapacity,
+}
+
+// Returned when the channel closes with fewer messages than requested; carries the leftovers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecvExactError<T>(pub Vec<T>);
+
+// Returned by send_iter when no receivers remain; carries the iterator with the items
+// not yet sent.
+pub struct SendIterError<I> {
+    pub n_sent: usize,
+    pub iter: I,
+}
+
+impl<T> SendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            SendError::Disconnected(msg) | SendError::Closed(msg) | SendError::Full(msg) => msg,
+        }
+    }
+
+    // For sends without a deadline, which never time out; were one to, the channel was
+    // still full.
+    pub(crate) fn from_untimed(err: SendTimeoutError<T>) -> Self {
+        match err {
+            SendTimeoutError::Closed(msg) => SendError::Closed(msg),
+            SendTimeoutError::Disconnected(msg) => SendError::Disconnected(msg),
+            SendTimeoutError::Timeout(msg) => SendError::Full(msg),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Disconnected(..) => f.write_str("Disconnected(..)"),
+            SendError::Closed(..) => f.write_str("Closed(..)"),
+            SendError::Full(..) => f.write_str("Full(..)"),
+        }
+    }
+}
+
+impl<T> SendTimeoutError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            SendTimeoutError::Timeout(msg)
+            | SendTimeoutError::Disconnected(msg)
+            | SendTimeoutError::Closed(msg) => msg,
+        }
+    }
+
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> SendTimeoutError<U> {
+        match self {
+            SendTimeoutError::Timeout(msg) => SendTimeoutError::Timeout(f(msg)),
+            SendTimeoutError::Disconnected(msg) => SendTimeoutError::Disconnected(f(msg)),
+            SendTimeoutError::Closed(msg) => SendTimeoutError::Closed(f(msg)),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(..) => f.write_str("Timeout(..)"),
+            SendTimeoutError::Disconnected(..) => f.write_str("Disconnected(..)"),
+            SendTimeoutError::Closed(..) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+impl<T> TrySendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            TrySendError::Full(msg)
+            | TrySendError::Disconnected(msg)
+            | TrySendError::Closed(msg) => msg,
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(..) => f.write_str("Full(..)"),
+            TrySendError::Disconnected(..) => f.write_str("Disconnected(..)"),
+            TrySendError::Closed(..) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+impl<T> AnySendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            AnySendError::Mismatch(msg)
+            | AnySendError::Disconnected(msg)
+            | AnySendError::Closed(msg)
+            | AnySendError::Full(msg) => msg,
+        }
+    }
+
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> AnySendError<U> {
+        match self {
+            AnySendError::Mismatch(msg) => AnySendError::Mismatch(f(msg)),
+            AnySendError::Disconnected(msg) => AnySendError::Disconnected(f(msg)),
+            AnySendError::Closed(msg) => AnySendError::Closed(f(msg)),
+            AnySendError::Full(msg) => AnySendError::Full(f(msg)),
+        }
+    }
+}
+
+impl<T> fmt::Debug for AnySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnySendError::Mismatch(..) => f.write_str("Mismatch(..)"),
+            AnySendError::Disconnected(..) => f.write_str("Disconnected(..)"),
+            AnySendError::Closed(..) => f.write_str("Closed(..)"),
+            AnySendError::Full(..) => f.write_str("Full(..)"),
+        }
+    }
+}
+
+impl fmt::Debug for AnyRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyRecvError::Mismatch { actual, .. } => f
+                .debug_struct("Mismatch")
+                .field("actual", actual)
+                .finish_non_exhaustive(),
+            AnyRecvError::Disconnected => f.write_str("Disconnected"),
+        }
+    }
+}
+
+impl<I> SendIterError<I> {
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+}
+
+impl<I> fmt::Debug for SendIterError<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendIterError")
+            .field("n_sent", &self.n_sent)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<RecvError> for TryRecvError {
+    fn from(_: RecvError) -> Self {
+        TryRecvError::Disconnected
+    }
+}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(_: RecvError) -> Self {
+        RecvTimeoutError::Disconnected
+    }
+}
+
+impl From<RecvError> for RecvCancelError {
+    fn from(_: RecvError) -> Self {
+        RecvCancelError::Disconnected
+    }
+}
+
+impl From<RecvError> for AnyRecvError {
+    fn from(_: RecvError) -> Self {
+        AnyRecvError::Disconnected
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Disconnected(..) => f.write_str("sending on a disconnected channel"),
+            SendError::Closed(..) => f.write_str("sending on a closed channel"),
+            SendError::Full(..) => f.write_str("sending on a full channel"),
+        }
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(..) => f.write_str("timed out waiting on send operation"),
+            SendTimeoutError::Disconnected(..) => f.write_str("sending on a disconnected channel"),
+            SendTimeoutError::Closed(..) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for SendTimeoutError<T> {}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(..) => f.write_str("sending on a full channel"),
+            TrySendError::Disconnected(..) => f.write_str("sending on a disconnected channel"),
+            TrySendError::Closed(..) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("receiving on a closed channel")
+    }
+}
+
+impl Error for RecvError {}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => f.write_str("receiving on an empty channel"),
+            TryRecvError::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvTimeoutError::Timeout => f.write_str("timed out waiting on receive operation"),
+            RecvTimeoutError::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for RecvTimeoutError {}
+
+impl fmt::Display for RecvCancelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvCancelError::Cancelled => f.write_str("receive operation was cancelled"),
+            RecvCancelError::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for RecvCancelError {}
+
+impl fmt::Display for BroadcastRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BroadcastRecvError::Lagged(n) => write!(f, "receiver lagged behind by {n} messages"),
+            BroadcastRecvError::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for BroadcastRecvError {}
+
+impl fmt::Display for BroadcastTryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BroadcastTryRecvError::Empty => f.write_str("receiving on an empty channel"),
+            BroadcastTryRecvError::Lagged(n) => {
+                write!(f, "receiver lagged behind by {n} messages")
+            }
+            BroadcastTryRecvError::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for BroadcastTryRecvError {}
+
+impl fmt::Display for AnyRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyRecvError::Mismatch { .. } => f.write_str("received a message of another type"),
+            AnyRecvError::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for AnyRecvError {}
+
+impl<T> fmt::Display for AnySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnySendError::Mismatch(..) => f.write_str("sending a message of another type"),
+            AnySendError::Disconnected(..) => f.write_str("sending on a disconnected channel"),
+            AnySendError::Closed(..) => f.write_str("sending on a closed channel"),
+            AnySendError::Full(..) => f.write_str("sending on a full channel"),
+        }
+    }
+}
+
+impl<T> Error for AnySendError<T> {}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("message was dropped without being received")
+    }
+}
+
+impl Error for DeliveryError {}
+
+impl fmt::Display for DeliveryTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryTimeoutError::Timeout => f.write_str("timed out waiting on delivery"),
+            DeliveryTimeoutError::Dropped => {
+                f.write_str("message was dropped without being received")
+            }
+        }
+    }
+}
+
+impl Error for DeliveryTimeoutError {}
+
+impl fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryReserveError::Full => f.write_str("reserving on a full channel"),
+            TryReserveError::Disconnected => f.write_str("reserving on a disconnected channel"),
+            TryReserveError::Closed => f.write_str("reserving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryReserveError {}
+
+impl fmt::Display for ReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("reserving on a disconnected or closed channel")
+    }
+}
+
+impl Error for ReserveError {}
+
+impl fmt::Display for FlushError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("flushing a disconnected channel")
+    }
+}
+
+impl Error for FlushError {}
+
+impl fmt::Display for ChannelClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("channel is closed")
+    }
+}
+
+impl Error for ChannelClosed {}
+
+impl fmt::Display for ResetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("resetting a channel that still has senders")
+    }
+}
+
+impl Error for ResetError {}
+
+impl fmt::Display for FlushTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlushTimeoutError::Timeout => f.write_str("timed out waiting on flush"),
+            FlushTimeoutError::Disconnected => f.write_str("flushing a disconnected channel"),
+        }
+    }
+}
+
+impl Error for FlushTimeoutError {}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BuildError::ZeroCapacity => "bounded channel capacity must be nonzero",
+            BuildError::ZeroByteBudget => "byte budget must be nonzero",
+            BuildError::OverflowWithoutBound => {
+                "overflow policy needs a capacity or byte budget to take effect"
+            }
+            BuildError::WatermarksReversed => "low watermark must not exceed the high one",
+            BuildError::WatermarkAboveCapacity => {
+                "high watermark exceeds the capacity, so it can never be reached"
+            }
+        })
+    }
+}
+
+impl Error for BuildError {}
+
+impl<T> fmt::Display for RecvExactError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "channel closed after {} of the requested messages",
+            self.0.len()
+        )
+    }
+}
+
+impl<T: fmt::Debug> Error for RecvExactError<T> {}
+
+impl<I> fmt::Display for SendIterError<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "channel disconnected after sending {} items",
+            self.n_sent
+        )
+    }
+}
+
+impl<I> Error for SendIterError<I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+        // None of these need the message to implement Debug or Display.
+        struct Opaque;
+        assert_eq!(
+            SendError::Disconnected(Opaque).to_string(),
+            "sending on a disconnected channel"
+        );
+        assert_eq!(
+            SendError::Closed(Opaque).to_string(),
+            "sending on a closed channel"
+        );
+        assert_eq!(
+            SendError::Full(Opaque).to_string(),
+            "sending on a full channel"
+        );
+        assert_eq!(
+            TrySendError::Full(Opaque).to_string(),
+            "sending on a full channel"
+        );
+        assert_eq!(
+            SendTimeoutError::Timeout(Opaque).to_string(),
+            "timed out waiting on send operation"
+        );
+        assert_eq!(RecvError.to_string(), "receiving on a closed channel");
+        assert_eq!(
+            TryRecvError::Empty.to_string(),
+            "receiving on an empty channel"
+        );
+        assert_eq!(
+            TryRecvError::Disconnected.to_string(),
+            "receiving on a closed channel"
+        );
+        assert_eq!(
+            RecvTimeoutError::Timeout.to_string(),
+            "timed out waiting on receive operation"
+        );
+        assert_eq!(
+            BroadcastRecvError::Lagged(3).to_string(),
+            "receiver lagged behind by 3 messages"
+        );
+        assert_eq!(
+            RecvExactError(vec![Opaque, Opaque]).to_string(),
+            "channel closed after 2 of the requested messages"
+        );
+    }
+
+    #[test]
+    fn test_error_into_inner() {
+        assert_eq!(SendError::Closed("a").into_inner(), "a");
+        assert_eq!(TrySendError::Full("b").into_inner(), "b");
+        assert_eq!(TrySendError::Disconnected("c").into_inner(), "c");
+        assert_eq!(SendTimeoutError::Timeout("d").into_inner(), "d");
+    }
+
+    #[test]
+    fn test_error_source() {
+        fn check(err: &dyn Error) {
+            assert!(err.source().is_none());
+        }
+        check(&SendError::Disconnected(0));
+        check(&TrySendError::Full(0));
+        check(&RecvError);
+        check(&TryRecvError::Disconnected);
+        check(&RecvTimeoutError::Timeout);
+
+        let boxed: Box<dyn Error + Send + Sync> = Box::new(TryRecvError::Empty);
+        assert_eq!(boxed.to_string(), "receiving on an empty channel");
+        assert_eq!(boxed.downcast_ref(), Some(&TryRecvError::Empty));
+    }
+}
@@ -0,0 +1,490 @@
+// Futures for using a channel from async code, with no runtime dependency. Async and
+// blocking endpoints can share a channel: wherever a send or receive signals the
+// condvars, it wakes the parked tasks too.
+use std::collections::VecDeque;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Instant;
+
+use crate::{
+    OverflowPolicy, Permit, Receiver, Recover, RecvError, ReserveError, SendError, Sender, Shared,
+    TryRecvError, TrySendError,
+};
+
+#[derive(Default)]
+pub(crate) struct Wakers {
+    // parked receiving tasks by slot key, longest parked first
+    recv: VecDeque<(u64, Waker)>,
+    send: Vec<Waker>,
+    next_key: u64,
+}
+
+// Where one poller parks its task, keeping a single entry however often it polls.
+#[derive(Default)]
+pub(crate) struct WakerSlot {
+    // zero until first parked
+    key: AtomicU64,
+    // set from parking until Ready; with the entry gone, a wakeup was spent on this slot
+    parked: AtomicBool,
+    // when the timer thread started for a delayed message or tick fires
+    timer: Mutex<Option<Instant>>,
+}
+
+pub struct RecvFuture<'a, T> {
+    rx: &'a Receiver<T>,
+    slot: WakerSlot,
+}
+
+pub struct SendFuture<'a, T> {
+    tx: &'a Sender<T>,
+    msg: Option<T>,
+}
+
+impl<T> Receiver<T> {
+    // Resolves as recv returns. A task parked on it is woken by sends and by the channel
+    // ending; for a delayed message or tick, a short-lived thread wakes it when due.
+    pub fn recv_async(&self) -> RecvFuture<'_, T> {
+        RecvFuture {
+            rx: self,
+            slot: WakerSlot::default(),
+        }
+    }
+
+    // The step under recv_async: takes a message if there is one, or parks the task with
+    // `cx`'s waker and returns Pending. Polling again through the same handle replaces
+    // the waker rather than adding one, and each message sent wakes one parked task, so a
+    // woken task must poll again, or drop this handle, which passes the wakeup on.
+    // Otherwise the message may wait for the next send to wake someone else.
+    pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        self.poll_recv_in(&self.slot, cx)
+    }
+
+    fn poll_recv_in(&self, slot: &WakerSlot, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        if let Poll::Ready(result) = self.poll_try_recv() {
+            self.shared.unpark_receiver(slot);
+            return Poll::Ready(result);
+        }
+        // checked again once parked, so a send in between still wakes the task
+        self.shared.park_receiver(slot, cx.waker());
+        if let Poll::Ready(result) = self.poll_try_recv() {
+            self.shared.unpark_receiver(slot);
+            return Poll::Ready(result);
+        }
+        let due = self.shared.inner.lock().recover().next_maturity();
+        let mut timer = slot.timer.lock().recover();
+        if let Some(due) = due.filter(|&due| *timer != Some(due)) {
+            *timer = Some(due);
+            wake_at(due, cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    fn poll_try_recv(&self) -> Poll<Result<T, RecvError>> {
+        match self.try_recv() {
+            Ok(msg) => Poll::Ready(Ok(msg)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(Err(RecvError)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    // Resolves as send returns. Only a bounded channel with the Block policy can make it
+    // wait; under other policies it completes on the first poll, as send would.
+    pub fn send_async(&self, msg: T) -> SendFuture<'_, T> {
+        SendFuture {
+            tx: self,
+            msg: Some(msg),
+        }
+    }
+
+    // Like reserve, but parks the task with `cx`'s waker instead of blocking. Every
+    // parked sender is woken whenever room is made, and those finding none park again.
+    pub fn poll_reserve(&self, cx: &mut Context<'_>) -> Poll<Result<Permit<'_, T>, ReserveError>> {
+        if let Poll::Ready(result) = self.poll_try_reserve() {
+            return Poll::Ready(result);
+        }
+        self.shared.park_sender(cx.waker());
+        self.poll_try_reserve()
+    }
+
+    fn poll_try_reserve(&self) -> Poll<Result<Permit<'_, T>, ReserveError>> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        if inner_guard.check_accepting().is_err() {
+            return Poll::Ready(Err(ReserveError));
+        }
+        if !inner_guard.can_send_now(0) {
+            return Poll::Pending;
+        }
+        inner_guard.n_reserved += 1;
+        Poll::Ready(Ok(Permit { tx: self }))
+    }
+}
+
+impl<T> Shared<T> {
+    pub(crate) fn wake_async_receiver(&self) {
+        let parked = self.wakers.lock().recover().recv.pop_front();
+        if let Some((_, waker)) = parked {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn wake_async_receivers(&self) {
+        let parked = mem::take(&mut self.wakers.lock().recover().recv);
+        for (_, waker) in parked {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn wake_async_senders(&self) {
+        let parked = mem::take(&mut self.wakers.lock().recover().send);
+        for waker in parked {
+            waker.wake();
+        }
+    }
+
+    fn park_receiver(&self, slot: &WakerSlot, waker: &Waker) {
+        let mut wakers = self.wakers.lock().recover();
+        let mut key = slot.key.load(Ordering::Relaxed);
+        if key == 0 {
+            wakers.next_key += 1;
+            key = wakers.next_key;
+            slot.key.store(key, Ordering::Relaxed);
+        }
+        match wakers
+            .recv
+            .iter_mut()
+            .find(|(parked_key, _)| *parked_key == key)
+        {
+            Some((_, parked)) => parked.clone_from(waker),
+            None => wakers.recv.push_back((key, waker.clone())),
+        }
+        slot.parked.store(true, Ordering::Relaxed);
+    }
+
+    // Returns whether the slot was still parked, i.e. not yet woken.
+    fn remove_receiver(&self, slot: &WakerSlot) -> bool {
+        let key = slot.key.load(Ordering::Relaxed);
+        let mut wakers = self.wakers.lock().recover();
+        let idx = wakers
+            .recv
+            .iter()
+            .position(|(parked_key, _)| *parked_key == key);
+        idx.and_then(|idx| wakers.recv.remove(idx)).is_some()
+    }
+
+    fn unpark_receiver(&self, slot: &WakerSlot) {
+        if slot.parked.swap(false, Ordering::Relaxed) {
+            self.remove_receiver(slot);
+        }
+    }
+
+    // For a poller going away: a wakeup it was given but never acted on goes to the next
+    // parked task.
+    pub(crate) fn leave_slot(&self, slot: &WakerSlot) {
+        if slot.parked.swap(false, Ordering::Relaxed) && !self.remove_receiver(slot) {
+            self.wake_async_receiver();
+        }
+    }
+
+    fn park_sender(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock().recover();
+        if !wakers.send.iter().any(|parked| parked.will_wake(waker)) {
+            wakers.send.push(waker.clone());
+        }
+    }
+}
+
+impl<T> Future for RecvFuture<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.rx.poll_recv_in(&self.slot, cx)
+    }
+}
+
+impl<T> Drop for RecvFuture<'_, T> {
+    fn drop(&mut self) {
+        self.rx.shared.leave_slot(&self.slot);
+    }
+}
+
+// The message is never pinned.
+impl<T> Unpin for SendFuture<'_, T> {}
+
+impl<T> Future for SendFuture<'_, T> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let msg = self.msg.take().expect("SendFuture polled after completion");
+        if self.tx.shared.inner.lock().recover().policy != OverflowPolicy::Block {
+            return Poll::Ready(self.tx.send(msg));
+        }
+        let msg = match self.tx.try_send(msg) {
+            Err(TrySendError::Full(msg)) => msg,
+            result => return Poll::Ready(result.map_err(into_send_error)),
+        };
+        // tried again once parked, so a slot freed in between still wakes the task
+        self.tx.shared.park_sender(cx.waker());
+        match self.tx.try_send(msg) {
+            Err(TrySendError::Full(msg)) => {
+                self.msg = Some(msg);
+                Poll::Pending
+            }
+            result => Poll::Ready(result.map_err(into_send_error)),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn wake_at(due: Instant, waker: Waker) {
+    thread::spawn(move || {
+        thread::sleep(due.saturating_duration_since(Instant::now()));
+        waker.wake();
+    });
+}
+
+// wasm32-unknown-unknown cannot spawn threads, so there a task waiting on a delayed
+// message or tick is only woken by the next send, or by being polled again.
+#[cfg(target_arch = "wasm32")]
+fn wake_at(_due: Instant, _waker: Waker) {}
+
+fn into_send_error<T>(err: TrySendError<T>) -> SendError<T> {
+    match err {
+        TrySendError::Closed(msg) => SendError::Closed(msg),
+        TrySendError::Disconnected(msg) => SendError::Disconnected(msg),
+        TrySendError::Full(msg) => SendError::Full(msg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_bounded, new_channel, tick};
+    use std::pin::pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Wake;
+    use std::thread::Thread;
+    use std::time::Duration;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_future_recv_from_sync_sender() {
+        let (tx, rx) = new_channel();
+        let producer = thread::spawn(move || {
+            for i in 0..100 {
+                tx.send(i).unwrap();
+                if i % 10 == 0 {
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+        });
+        let received = block_on(async {
+            let mut received = vec![];
+            while let Ok(msg) = rx.recv_async().await {
+                received.push(msg);
+            }
+            received
+        });
+        producer.join().unwrap();
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_future_send_to_sync_receiver() {
+        let (tx, rx) = new_bounded(2);
+        let consumer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            rx.iter().collect::<Vec<_>>()
+        });
+        block_on(async {
+            for i in 0..10 {
+                tx.send_async(i).await.unwrap();
+            }
+        });
+        drop(tx);
+        assert_eq!(consumer.join().unwrap(), (0..10).collect::<Vec<_>>());
+
+        let (tx, rx) = new_bounded(1);
+        tx.send(1).unwrap();
+        let closer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            rx.close();
+            rx
+        });
+        assert_eq!(block_on(tx.send_async(2)), Err(SendError::Closed(2)));
+        drop(closer.join().unwrap());
+        assert_eq!(block_on(tx.send_async(3)), Err(SendError::Disconnected(3)));
+    }
+
+    #[test]
+    fn test_future_wakes_parked_task() {
+        let (tx, rx) = new_channel();
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(Arc::clone(&counter));
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = rx.recv_async();
+        assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+        assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+        tx.send(1).unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Ready(Ok(1)));
+
+        let mut fut = rx.recv_async();
+        assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+        drop(tx);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            Pin::new(&mut fut).poll(&mut cx),
+            Poll::Ready(Err(RecvError))
+        );
+    }
+
+    fn counting_context() -> (Arc<CountingWaker>, Waker) {
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(Arc::clone(&counter));
+        (counter, waker)
+    }
+
+    fn n_wakes(counter: &CountingWaker) -> usize {
+        counter.0.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_future_poll_recv_wakes_one_per_message() {
+        let (tx, rx) = new_channel();
+        let rx2 = rx.clone();
+        let (counter, waker) = counting_context();
+        let (counter2, waker2) = counting_context();
+        assert!(rx.poll_recv(&mut Context::from_waker(&waker)).is_pending());
+        assert!(rx2
+            .poll_recv(&mut Context::from_waker(&waker2))
+            .is_pending());
+        tx.send(1).unwrap();
+        assert_eq!((n_wakes(&counter), n_wakes(&counter2)), (1, 0));
+        tx.send(2).unwrap();
+        assert_eq!((n_wakes(&counter), n_wakes(&counter2)), (1, 1));
+        tx.send(3).unwrap();
+        assert_eq!((n_wakes(&counter), n_wakes(&counter2)), (1, 1));
+
+        let mut cx = Context::from_waker(&waker);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Ok(1)));
+        assert_eq!(rx2.poll_recv(&mut cx), Poll::Ready(Ok(2)));
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Ok(3)));
+        drop(tx);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Err(RecvError)));
+    }
+
+    #[test]
+    fn test_future_poll_recv_replaces_waker() {
+        let (tx, rx) = new_channel();
+        let (counter, waker) = counting_context();
+        let (counter2, waker2) = counting_context();
+        for _ in 0..3 {
+            assert!(rx.poll_recv(&mut Context::from_waker(&waker)).is_pending());
+        }
+        assert!(rx.poll_recv(&mut Context::from_waker(&waker2)).is_pending());
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!((n_wakes(&counter), n_wakes(&counter2)), (0, 1));
+    }
+
+    #[test]
+    fn test_future_dropped_wakeup_is_passed_on() {
+        let (tx, rx) = new_channel();
+        let (counter, waker) = counting_context();
+        let (counter2, waker2) = counting_context();
+        let mut fut = rx.recv_async();
+        assert!(Pin::new(&mut fut)
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+        let mut fut2 = rx.recv_async();
+        assert!(Pin::new(&mut fut2)
+            .poll(&mut Context::from_waker(&waker2))
+            .is_pending());
+        tx.send(1).unwrap();
+        assert_eq!((n_wakes(&counter), n_wakes(&counter2)), (1, 0));
+        drop(fut);
+        assert_eq!((n_wakes(&counter), n_wakes(&counter2)), (1, 1));
+        assert_eq!(
+            Pin::new(&mut fut2).poll(&mut Context::from_waker(&waker2)),
+            Poll::Ready(Ok(1))
+        );
+    }
+
+    #[test]
+    fn test_future_poll_reserve() {
+        let (tx, rx) = new_bounded(1);
+        let (counter, waker) = counting_context();
+        let mut cx = Context::from_waker(&waker);
+        match tx.poll_reserve(&mut cx) {
+            Poll::Ready(Ok(permit)) => permit.send(1),
+            _ => panic!("expected a permit"),
+        }
+        assert!(tx.poll_reserve(&mut cx).is_pending());
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(n_wakes(&counter), 1);
+        match tx.poll_reserve(&mut cx) {
+            Poll::Ready(Ok(permit)) => permit.send(2),
+            _ => panic!("expected a permit"),
+        }
+        assert_eq!(rx.recv(), Ok(2));
+        drop(rx);
+        assert!(matches!(
+            tx.poll_reserve(&mut cx),
+            Poll::Ready(Err(ReserveError))
+        ));
+    }
+
+    #[test]
+    fn test_future_delayed_and_tick() {
+        let (tx, rx) = new_channel();
+        let started_at = Instant::now();
+        tx.send_after(5, Duration::from_millis(30)).unwrap();
+        assert_eq!(block_on(rx.recv_async()), Ok(5));
+        assert!(started_at.elapsed() >= Duration::from_millis(30));
+
+        let ticker = tick(Duration::from_millis(10));
+        let started_at = Instant::now();
+        block_on(async {
+            for _ in 0..3 {
+                ticker.recv_async().await.unwrap();
+            }
+        });
+        assert!(started_at.elapsed() >= Duration::from_millis(30));
+    }
+}
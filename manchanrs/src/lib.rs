@@ -1,14 +1,266 @@
-use std::collections::VecDeque;
-use std::sync::{Arc, Condvar, Mutex};
+use std::any::Any;
+use std::cmp;
+use std::collections::{BinaryHeap, VecDeque};
+use std::convert;
+use std::fmt;
+use std::iter::{FusedIterator, Peekable};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(all(unix, feature = "os-poll"))]
+use std::sync::OnceLock;
+use std::sync::{Arc, Condvar, LockResult, Mutex, MutexGuard, PoisonError, Weak};
+use std::time::{Duration, Instant};
+
+mod adapters;
+mod array;
+mod broadcast;
+mod builder;
+mod coalescing;
+pub mod compat;
+mod duplex;
+mod erased;
+mod errors;
+#[cfg(feature = "async")]
+mod future;
+mod oneshot;
+#[cfg(all(unix, feature = "os-poll"))]
+mod os_poll;
+mod priority;
+mod pump;
+mod reason;
+#[cfg(all(unix, feature = "signals"))]
+mod signals;
+mod topics;
+mod watch;
+
+use builder::{OnDrop, OnSend};
+
+pub use adapters::{
+    merge, zip, ChunkTimeout, Chunks, Debounced, Dedup, FilteredReceiver, GroupBy, MappedReceiver,
+    MergedReceiver, PartReceiver, RecvSource, Sampled, ZipReceiver,
+};
+pub use array::{new_static_channel, ArrayReceiver, ArraySender, StaticChannel};
+pub use broadcast::{broadcast, BroadcastBuilder, BroadcastReceiver, BroadcastSender};
+pub use builder::ChannelBuilder;
+pub use coalescing::{coalescing, CoalescingReceiver, CoalescingSender};
+pub use duplex::{duplex, Endpoint};
+pub use erased::{any_channel, AnyReceiver, AnySender, TypedReceiver};
+pub use errors::{
+    AnyRecvError, AnySendError, BroadcastRecvError, BroadcastTryRecvError, BuildError,
+    ChannelClosed, DeliveryError, DeliveryTimeoutError, FlushError, FlushTimeoutError,
+    RecvCancelError, RecvError, RecvExactError, RecvTimeoutError, ReserveError, ResetError,
+    SendError, SendIterError, SendTimeoutError, TryRecvError, TryReserveError, TrySendError,
+};
+#[cfg(feature = "async")]
+pub use future::{RecvFuture, SendFuture};
+pub use oneshot::{oneshot, OneshotReceiver, OneshotSender};
+pub use priority::{priority_channel, priority_channel_by_key, PriorityReceiver};
+pub use pump::{PumpHandle, PumpReport, PumpStop};
+pub use reason::{new_channel_with_reason, Closed, ReasonReceiver, ReasonSender};
+#[cfg(all(unix, feature = "signals"))]
+pub use signals::{signals, SignalKind};
+pub use topics::{TopicSender, Topics};
+pub use watch::{watch, WatchReceiver, WatchSender};
 
 struct Inner<T> {
-    queue: VecDeque<T>,
+    queue: MsgQueue<T>,
+    // messages from send_after, invisible until their deadline; see promote_matured
+    delayed: BinaryHeap<Delayed<T>>,
+    n_delayed: u64,
     n_senders: usize,
+    n_receivers: usize,
+    // live KeepAlive guards; while any exist, running out of senders does not end recv
+    n_keep_alive: usize,
+    capacity: Option<usize>,
+    // budget for the summed MessageSize of queued and delayed messages
+    max_bytes: Option<usize>,
+    n_delayed_bytes: usize,
+    policy: OverflowPolicy,
+    // messages discarded by a DropNewest or DropOldest policy
+    n_dropped: u64,
+    // slots held by outstanding permits
+    n_reserved: usize,
+    // set by Receiver::close: sends fail, but what is queued can still be received
+    closed: bool,
+    // from close_with on a channel made by new_channel_with_reason
+    close_reason: Option<Box<dyn Any + Send>>,
+    // tickets of senders parked for room, admitted strictly in this order
+    send_line: VecDeque<u64>,
+    next_ticket: u64,
+    // bumped by Receiver::reset, so weak senders from an earlier round stay dead
+    generation: u64,
+    // set by tick; fires in promote_matured
+    ticker: Option<Ticker<T>>,
+    // from Receiver::set_ready_callback
+    on_ready: Option<ReadyFn>,
+}
+
+struct Ticker<T> {
+    period: Duration,
+    next: Instant,
+    make: fn(Instant) -> T,
+}
+
+type SizeFn<T> = fn(&T) -> usize;
+
+type ReadyFn = Arc<dyn Fn() + Send + Sync>;
+
+type OrderFn<T> = Box<dyn Fn(&T, &T) -> cmp::Ordering + Send + Sync>;
+
+// A panic can only unwind through the channel lock from user code (a callback, predicate,
+// or T's Clone or Drop), which always runs with the queue and its counters in agreement,
+// so a poisoned lock is taken over rather than failing every other endpoint too.
+trait Recover<G> {
+    fn recover(self) -> G;
+}
+
+impl<G> Recover<G> for LockResult<G> {
+    fn recover(self) -> G {
+        self.unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// Condvar waits, taking over a poisoned lock like Recover. Every blocking wait goes
+// through here or check_can_block, so a target that cannot block fails in one place.
+trait Wait {
+    fn wait_on<'a, G>(&self, guard: MutexGuard<'a, G>) -> MutexGuard<'a, G>;
+    fn wait_on_timeout<'a, G>(
+        &self,
+        guard: MutexGuard<'a, G>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, G>;
+}
+
+impl Wait for Condvar {
+    fn wait_on<'a, G>(&self, guard: MutexGuard<'a, G>) -> MutexGuard<'a, G> {
+        check_can_block();
+        self.wait(guard).recover()
+    }
+
+    fn wait_on_timeout<'a, G>(
+        &self,
+        guard: MutexGuard<'a, G>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, G> {
+        check_can_block();
+        self.wait_timeout(guard, timeout).recover().0
+    }
+}
+
+// wasm32 without the atomics feature has no way to block, so blocking calls panic there;
+// try_recv, try_iter and recv_async still work. With atomics, std's Condvar waits with
+// memory.atomic.wait, which is allowed in workers but not on a browser's main thread.
+#[cfg(all(target_arch = "wasm32", not(target_feature = "atomics")))]
+fn check_can_block() {
+    panic!("blocking channel operations need wasm atomics; use try_recv or recv_async");
+}
+
+#[cfg(not(all(target_arch = "wasm32", not(target_feature = "atomics"))))]
+fn check_can_block() {}
+
+// The channel lock together with a value handed back through it.
+type LockedWith<'a, T, M> = (MutexGuard<'a, Inner<T>>, M);
+
+struct Delayed<T> {
+    // None for a delay too long to represent, which never matures
+    deadline: Option<Instant>,
+    seq: u64,
+    msg: Envelope<T>,
+}
+
+// FIFO queue with a High lane that overtakes the Low lane. Both lanes share one
+// VecDeque: the first `n_high` entries are High, so a plain pop_front drains High first.
+// On a ranked queue the Low lane lives in `ranked` instead, and `entries` is all High.
+struct MsgQueue<T> {
+    entries: VecDeque<Envelope<T>>,
+    n_high: usize,
+    n_bytes: usize,
+    watermarks: Option<Watermarks>,
+    // set when a push finds the queue empty; taken by Inner::take_ready
+    refilled: bool,
+    // from priority_channel
+    ranked: Option<RankedLane<T>>,
+    // from Receiver::readiness_fd
+    #[cfg(all(unix, feature = "os-poll"))]
+    ready_signal: Option<os_poll::ReadySignal>,
+}
+
+// A binary heap ordered by `order`, ties going to the earlier send. Hand-rolled rather
+// than a BinaryHeap, whose ordering could not reach the comparator.
+struct RankedLane<T> {
+    order: OrderFn<T>,
+    heap: Vec<Ranked<T>>,
+    n_pushed: u64,
+}
+
+struct Ranked<T> {
+    seq: u64,
+    envelope: Envelope<T>,
+}
+
+// Level tracking for new_channel_with_watermarks. `is_high` is set once the queue reaches
+// `high` and cleared only when it falls below `low`.
+struct Watermarks {
+    high: usize,
+    low: usize,
+    is_high: bool,
+    on_watermark: Box<dyn FnMut(Watermark) + Send>,
+}
+
+// A queued message, plus the delivery tracker when it came from send_tracked. `size` is
+// fixed at send time so the byte count stays consistent even if the message is mutated.
+struct Envelope<T> {
+    msg: T,
+    tracker: Option<DeliveryTracker>,
+    size: usize,
+}
+
+// Reports Dropped to the SendHandle if the envelope is discarded before a receiver takes it.
+struct DeliveryTracker(Arc<Tracked>);
+
+struct Tracked {
+    delivery: Mutex<Delivery>,
+    done: Condvar,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    Pending,
+    Delivered,
+    Dropped,
 }
 
 struct Shared<T> {
     inner: Mutex<Inner<T>>,
     available: Condvar,
+    // receivers that may decline a message wait here instead, so they never
+    // swallow a notify_one meant for a plain receiver
+    selective: Condvar,
+    // senders parked on a full bounded channel
+    not_full: Condvar,
+    // senders in flush, waiting for the queue to empty
+    drained: Condvar,
+    // senders in wait_below_high
+    below_low: Condvar,
+    // senders in closed, waiting for the last receiver to drop
+    no_receivers: Condvar,
+    // MessageSize::size for byte-budget channels
+    size_of: Option<SizeFn<T>>,
+    id: ChannelId,
+    // from new_channel_named, for diagnostics
+    name: Option<Arc<str>>,
+    // from ChannelBuilder::on_drop
+    on_drop: Option<OnDrop<T>>,
+    // from ChannelBuilder::on_send, in registration order
+    on_send: Vec<OnSend<T>>,
+    // tasks parked in recv_async and send_async
+    #[cfg(feature = "async")]
+    wakers: Mutex<future::Wakers>,
+    // made by the first Receiver::readiness_fd; the queue holds the other handle
+    #[cfg(all(unix, feature = "os-poll"))]
+    ready_pipe: OnceLock<Arc<os_poll::ReadyPipe>>,
 }
 
 pub struct Sender<T> {
@@ -17,340 +269,5761 @@ pub struct Sender<T> {
 
 pub struct Receiver<T> {
     shared: Arc<Shared<T>>,
+    // where poll_recv parks this handle's task
+    #[cfg(feature = "async")]
+    slot: future::WakerSlot,
 }
 
-pub fn new_channel<T>() -> (Sender<T>, Receiver<T>) {
-    let inner = Inner {
-        queue: VecDeque::<T>::new(),
-        n_senders: 1,
-    };
-    let shared = Shared {
-        inner: Mutex::new(inner),
-        available: Condvar::new(),
-    };
-    let arc_shared = Arc::new(shared);
-    let tx = Sender {
-        shared: arc_shared.clone(),
-    };
-    let rx = Receiver {
-        shared: arc_shared.clone(),
+// A sender that does not keep the channel open; see Sender::downgrade.
+pub struct WeakSender<T> {
+    shared: Weak<Shared<T>>,
+    generation: u64,
+}
+
+// A receiver that does not keep the channel connected; see Receiver::downgrade.
+pub struct WeakReceiver<T> {
+    shared: Weak<Shared<T>>,
+}
+
+// Keeps receivers waiting when the last sender drops; see Receiver::keep_alive.
+pub struct KeepAlive<T> {
+    shared: Arc<Shared<T>>,
+}
+
+// Identifies a channel, e.g. as a map key; ids are never reused within a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChannelId(u64);
+
+static NEXT_CHANNEL_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    High,
+    Low,
+}
+
+// What send, send_priority, send_front, send_after and send_tracked do when a bounded
+// channel is full. try_send, send_timeout, send_all and reservations always wait or report
+// Full instead. DropOldest evicts the front of the queue, so High messages go first; if
+// only delayed messages or permits fill the channel there is nothing to evict and the send
+// waits. Reject fails with SendError::Full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    Block,
+    DropNewest,
+    DropOldest,
+    Reject,
+}
+
+// Where a channel is in its lifecycle; see Sender::state. `pending` counts queued and
+// delayed messages that receivers can still take. Closed wins over the other states, and
+// ReceiversGone over SendersGone; running out of senders while a KeepAlive guard exists
+// still counts as Open. A close reason is read through ReasonReceiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChannelState {
+    Open,
+    SendersGone { pending: usize },
+    ReceiversGone,
+    Closed { pending: usize },
+}
+
+// What a ChannelBuilder::on_send hook wants done with a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendDecision {
+    Accept,
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watermark {
+    High,
+    Low,
+}
+
+// What a message costs against the budget of new_bounded_bytes. The default is the
+// inline size, which ignores heap data; containers should report their payload, e.g.
+//
+//     impl MessageSize for Packet {
+//         fn size(&self) -> usize {
+//             self.header.len() + self.body.len()
+//         }
+//     }
+pub trait MessageSize {
+    fn size(&self) -> usize
+    where
+        Self: Sized,
+    {
+        mem::size_of::<Self>()
+    }
+}
+
+// No blanket impl, since it would rule out the container impls below; other types opt in
+// to the default with an empty impl.
+macro_rules! impl_inline_message_size {
+    ($($t:ty),*) => {
+        $(impl MessageSize for $t {})*
     };
-    (tx, rx)
 }
 
-impl<T> Sender<T> {
-    pub fn send(&mut self, msg: T) {
-        let mut inner_guard = self.shared.inner.lock().unwrap();
-        inner_guard.queue.push_back(msg);
-        self.shared.available.notify_one();
+impl_inline_message_size!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64
+);
+
+impl<T> MessageSize for Vec<T> {
+    fn size(&self) -> usize {
+        self.len() * mem::size_of::<T>()
     }
 }
 
-impl<T> Clone for Sender<T> {
-    fn clone(&self) -> Self {
-        let mut inner_guard = self.shared.inner.lock().unwrap();
-        inner_guard.n_senders += 1;
-        drop(inner_guard);
-        Self {
-            shared: Arc::clone(&self.shared),
+impl MessageSize for String {
+    fn size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: ?Sized> MessageSize for Box<T> {
+    fn size(&self) -> usize {
+        mem::size_of_val(&**self)
+    }
+}
+
+// Waits for a message from send_tracked to be taken by a receiver.
+pub struct SendHandle {
+    tracked: Arc<Tracked>,
+}
+
+// A reserved slot in the channel; sending through it never blocks or fails, and dropping
+// it unused gives the slot back. On a channel bounded by bytes the slot holds none, as the
+// message's size isn't known yet: reserving waits only until the budget is not used up,
+// and the message then counts in full, so messages sent through several permits can take
+// the queue past max_bytes.
+pub struct Permit<'a, T> {
+    tx: &'a Sender<T>,
+}
+
+// A group of reserved slots whose messages are enqueued back to back. Slots left unused
+// are given back.
+pub struct PermitBatch<'a, T> {
+    tx: &'a Sender<T>,
+    n: usize,
+}
+
+// Like Permit, but holds the sender itself so it can be moved to another thread; the
+// channel stays open while it is alive.
+pub struct OwnedPermit<T> {
+    tx: Option<Sender<T>>,
+}
+
+// Requeues the message at the front of the channel if dropped without `commit`. It goes
+// back as a High message, ahead of any sent since. If the receivers are gone or the
+// channel was closed by then, it is discarded instead, through on_drop if set.
+pub struct RecvGuard<T> {
+    shared: Arc<Shared<T>>,
+    msg: Option<T>,
+}
+
+// Yields messages like recv, ending once the channel is closed and drained.
+pub struct Iter<'a, T> {
+    rx: &'a Receiver<T>,
+    done: bool,
+}
+
+// Like Iter, but owns the receiver; `for msg in rx` uses it.
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+    done: bool,
+}
+
+// See Receiver::into_exact_iter.
+pub struct ExactIter<T> {
+    msgs: std::vec::IntoIter<T>,
+}
+
+// Yields messages until the queue is momentarily empty, never blocking.
+pub struct TryIter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+// Yields messages until an overall deadline passes.
+pub struct IterFor<'a, T> {
+    rx: &'a Receiver<T>,
+    deadline: Option<Instant>,
+    done: bool,
+}
+
+// Yields messages until none has arrived for the idle duration.
+pub struct IterWithIdleTimeout<'a, T> {
+    rx: &'a Receiver<T>,
+    idle: Duration,
+    done: bool,
+}
+
+// What close_and_drain did with the backlog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrainReport {
+    pub n_processed: usize,
+    pub n_discarded: usize,
+}
+
+impl<T> MsgQueue<T> {
+    fn len(&self) -> usize {
+        self.entries.len() + self.ranked.as_ref().map_or(0, |lane| lane.heap.len())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn front(&self) -> Option<&Envelope<T>> {
+        let top = || Some(&self.ranked.as_ref()?.heap.first()?.envelope);
+        self.entries.front().or_else(top)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.entries.reserve(additional);
+    }
+
+    fn shrink_to(&mut self, min_capacity: usize) {
+        self.entries.shrink_to(min_capacity);
+        if let Some(lane) = &mut self.ranked {
+            lane.heap.shrink_to(min_capacity);
+        }
+    }
+
+    // Low lane; on a ranked queue, after everything ranking at least as high. The byte
+    // count is settled before any comparison, so a panicking comparator leaves it right.
+    fn push_back(&mut self, envelope: Envelope<T>) {
+        self.n_bytes += envelope.size;
+        match &mut self.ranked {
+            Some(lane) => lane.push(envelope),
+            None => self.entries.push_back(envelope),
+        }
+        let len = self.len();
+        self.refilled |= len == 1;
+        self.sync_ready_fd(len);
+        if let Some(lane) = &mut self.ranked {
+            lane.settle(lane.heap.len() - 1);
+        }
+        level_changed(&mut self.watermarks, len);
+    }
+
+    fn push_high(&mut self, envelope: Envelope<T>) {
+        self.n_bytes += envelope.size;
+        self.entries.insert(self.n_high, envelope);
+        self.n_high += 1;
+        let len = self.len();
+        self.refilled |= len == 1;
+        self.sync_ready_fd(len);
+        level_changed(&mut self.watermarks, len);
+    }
+
+    // Head of the High lane, ahead of everything else.
+    fn push_front(&mut self, envelope: Envelope<T>) {
+        self.n_bytes += envelope.size;
+        self.entries.push_front(envelope);
+        self.n_high += 1;
+        let len = self.len();
+        self.refilled |= len == 1;
+        self.sync_ready_fd(len);
+        level_changed(&mut self.watermarks, len);
+    }
+
+    fn pop_front(&mut self) -> Option<Envelope<T>> {
+        let envelope = match self.entries.pop_front() {
+            Some(envelope) => {
+                self.n_high = self.n_high.saturating_sub(1);
+                self.n_bytes -= envelope.size;
+                envelope
+            }
+            None => self.remove_ranked(RankedLane::top)?,
+        };
+        let len = self.len();
+        self.sync_ready_fd(len);
+        level_changed(&mut self.watermarks, len);
+        Some(envelope)
+    }
+
+    // The message that would be received last.
+    fn pop_back(&mut self) -> Option<Envelope<T>> {
+        let envelope = match self.remove_ranked(RankedLane::lowest) {
+            Some(envelope) => envelope,
+            None => {
+                let envelope = self.entries.pop_back()?;
+                self.n_high = self.n_high.min(self.entries.len());
+                self.n_bytes -= envelope.size;
+                envelope
+            }
+        };
+        let len = self.len();
+        self.sync_ready_fd(len);
+        level_changed(&mut self.watermarks, len);
+        Some(envelope)
+    }
+
+    // The first message, in receive order, that `pred` accepts.
+    fn remove_first(&mut self, mut pred: impl FnMut(&T) -> bool) -> Option<Envelope<T>> {
+        let envelope = match self.entries.iter().position(|e| pred(&e.msg)) {
+            Some(idx) => {
+                let envelope = self.entries.remove(idx)?;
+                if idx < self.n_high {
+                    self.n_high -= 1;
+                }
+                self.n_bytes -= envelope.size;
+                envelope
+            }
+            None => self.remove_ranked(|lane| lane.best_match(pred))?,
+        };
+        let len = self.len();
+        self.sync_ready_fd(len);
+        level_changed(&mut self.watermarks, len);
+        Some(envelope)
+    }
+
+    // Takes the ranked entry `pick` chooses, if any, fixing up the byte count before the
+    // heap, as push_back does.
+    fn remove_ranked(
+        &mut self,
+        pick: impl FnOnce(&RankedLane<T>) -> Option<usize>,
+    ) -> Option<Envelope<T>> {
+        let lane = self.ranked.as_mut()?;
+        let idx = pick(lane)?;
+        let envelope = lane.heap.swap_remove(idx).envelope;
+        self.n_bytes -= envelope.size;
+        if idx < lane.heap.len() {
+            lane.settle(idx);
+        }
+        Some(envelope)
+    }
+
+    // Receives up to `n` messages into `buf`, in receive order.
+    fn drain_into(&mut self, n: usize, buf: &mut Vec<T>) {
+        let n_entries = n.min(self.entries.len());
+        self.n_high = self.n_high.saturating_sub(n_entries);
+        for envelope in self.entries.drain(..n_entries) {
+            self.n_bytes -= envelope.size;
+            buf.push(envelope.into_msg());
+        }
+        for _ in n_entries..n {
+            let Some(envelope) = self.remove_ranked(RankedLane::top) else {
+                break;
+            };
+            buf.push(envelope.into_msg());
+        }
+        let len = self.len();
+        self.sync_ready_fd(len);
+        level_changed(&mut self.watermarks, len);
+    }
+
+    // Empties the queue but keeps its configuration, handing the entries back, in no
+    // particular order, so they can be dropped outside the lock.
+    fn take_entries(&mut self) -> VecDeque<Envelope<T>> {
+        self.n_high = 0;
+        self.n_bytes = 0;
+        let mut entries = mem::take(&mut self.entries);
+        if let Some(lane) = &mut self.ranked {
+            entries.extend(lane.heap.drain(..).map(|ranked| ranked.envelope));
+        }
+        self.sync_ready_fd(0);
+        level_changed(&mut self.watermarks, 0);
+        entries
+    }
+
+    fn is_above_low(&self) -> bool {
+        self.watermarks.as_ref().is_some_and(|w| w.is_high)
+    }
+
+    // Keeps the pipe behind readiness_fd, if any, readable exactly while messages are
+    // queued. Called with the new length after every queue change, before level_changed.
+    #[cfg(all(unix, feature = "os-poll"))]
+    fn sync_ready_fd(&mut self, len: usize) {
+        if let Some(signal) = &mut self.ready_signal {
+            signal.update(len > 0);
         }
     }
+
+    #[cfg(not(all(unix, feature = "os-poll")))]
+    fn sync_ready_fd(&mut self, _len: usize) {}
 }
 
-impl<T> Drop for Sender<T> {
-    fn drop(&mut self) {
-        let mut inner_guard = self.shared.inner.lock().unwrap();
-        inner_guard.n_senders -= 1;
-        let is_channel_close = inner_guard.n_senders == 0;
-        drop(inner_guard);
-        if is_channel_close {
-            self.shared.available.notify_all();
+impl<T> Default for MsgQueue<T> {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            n_high: 0,
+            n_bytes: 0,
+            watermarks: None,
+            refilled: false,
+            ranked: None,
+            #[cfg(all(unix, feature = "os-poll"))]
+            ready_signal: None,
         }
     }
 }
 
-impl<T> Receiver<T> {
-    pub fn recv(&mut self) -> Option<T> {
-        let mut inner_guard = self.shared.inner.lock().unwrap();
+impl<T> RankedLane<T> {
+    fn new(order: OrderFn<T>) -> Self {
+        Self {
+            order,
+            heap: Vec::new(),
+            n_pushed: 0,
+        }
+    }
+
+    // Appends without restoring the heap; the caller settles the new entry.
+    fn push(&mut self, envelope: Envelope<T>) {
+        let seq = self.n_pushed;
+        self.n_pushed += 1;
+        self.heap.push(Ranked { seq, envelope });
+    }
+
+    fn top(&self) -> Option<usize> {
+        (!self.heap.is_empty()).then_some(0)
+    }
+
+    fn lowest(&self) -> Option<usize> {
+        (0..self.heap.len()).reduce(|a, b| if self.outranks(a, b) { b } else { a })
+    }
+
+    fn best_match(&self, mut pred: impl FnMut(&T) -> bool) -> Option<usize> {
+        (0..self.heap.len())
+            .filter(|&idx| pred(&self.heap[idx].envelope.msg))
+            .reduce(|a, b| if self.outranks(b, a) { b } else { a })
+    }
+
+    fn outranks(&self, a: usize, b: usize) -> bool {
+        let (a, b) = (&self.heap[a], &self.heap[b]);
+        match (self.order)(&a.envelope.msg, &b.envelope.msg) {
+            cmp::Ordering::Equal => a.seq < b.seq,
+            ordering => ordering == cmp::Ordering::Greater,
+        }
+    }
+
+    // Moves the entry at `idx` up or down until the heap is ordered again.
+    fn settle(&mut self, mut idx: usize) {
+        while idx > 0 {
+            let parent = (idx - 1) / 2;
+            if !self.outranks(idx, parent) {
+                break;
+            }
+            self.heap.swap(idx, parent);
+            idx = parent;
+        }
         loop {
-            if let Some(val) = inner_guard.queue.pop_front() {
-                return Some(val);
+            let mut top = idx;
+            for child in [2 * idx + 1, 2 * idx + 2] {
+                if child < self.heap.len() && self.outranks(child, top) {
+                    top = child;
+                }
             }
-            if inner_guard.n_senders == 0 {
-                return None; // channel is closed
+            if top == idx {
+                break;
             }
-            inner_guard = self.shared.available.wait(inner_guard).unwrap();
+            self.heap.swap(idx, top);
+            idx = top;
         }
     }
 }
 
-impl<T> Clone for Receiver<T> {
-    fn clone(&self) -> Self {
+// Called with the new length after every queue change, so each crossing is seen exactly
+// once and in order. The callback runs under the channel lock, and last, so a panic in it
+// leaves the queue consistent.
+fn level_changed(watermarks: &mut Option<Watermarks>, len: usize) {
+    let Some(watermarks) = watermarks else {
+        return;
+    };
+    if !watermarks.is_high && len >= watermarks.high {
+        watermarks.is_high = true;
+        (watermarks.on_watermark)(Watermark::High);
+    } else if watermarks.is_high && len < watermarks.low {
+        watermarks.is_high = false;
+        (watermarks.on_watermark)(Watermark::Low);
+    }
+}
+
+// Calls a callback from take_ready, without the channel lock. A panic in it is
+// swallowed, as the message that triggered it was already sent.
+fn call_ready(ready: Option<ReadyFn>) {
+    if let Some(on_ready) = ready {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| on_ready()));
+    }
+}
+
+impl<T> Envelope<T> {
+    fn new(msg: T, size: usize) -> Self {
         Self {
-            shared: Arc::clone(&self.shared),
+            msg,
+            tracker: None,
+            size,
         }
     }
-}
 
-impl<T> Iterator for Receiver<T> {
-    type Item = T;
-    fn next(&mut self) -> Option<Self::Item> {
-        self.recv()
+    // Hands the message to a receiver, completing its SendHandle if tracked.
+    fn into_msg(self) -> T {
+        if let Some(tracker) = self.tracker {
+            tracker.finish(Delivery::Delivered);
+        }
+        self.msg
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::thread::{self, sleep};
-    use std::time::Duration;
+impl DeliveryTracker {
+    fn finish(&self, delivery: Delivery) {
+        let mut delivery_guard = self.0.delivery.lock().recover();
+        if *delivery_guard == Delivery::Pending {
+            *delivery_guard = delivery;
+            self.0.done.notify_all();
+        }
+    }
+}
 
-    #[test]
-    fn test_channel_pingpong() {
-        let (mut tx, mut rx) = new_channel();
-        tx.send("hello".to_string());
-        tx.send("world".to_string());
-        assert_eq!(rx.recv(), Some("hello".to_string()));
-        assert_eq!(rx.recv(), Some("world".to_string()));
+impl Drop for DeliveryTracker {
+    fn drop(&mut self) {
+        self.finish(Delivery::Dropped);
     }
+}
 
-    #[test]
-    fn test_channel_iterator() {
-        let (mut tx, rx) = new_channel();
-        for i in 0..5 {
-            tx.send(i);
+impl<T> Inner<T> {
+    // Moves delayed messages whose deadline has passed onto the queue, earliest first.
+    fn promote_matured(&mut self) {
+        if self.ticker.is_some() {
+            self.fire_ticker();
         }
-        drop(tx);
+        if self.delayed.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        while self
+            .delayed
+            .peek()
+            .is_some_and(|d| d.deadline.is_some_and(|deadline| deadline <= now))
+        {
+            let d = self.delayed.pop().unwrap();
+            self.n_delayed_bytes -= d.msg.size;
+            self.queue.push_back(d.msg);
+        }
+    }
 
-        for (i, val) in rx.enumerate() {
-            match i {
-                0 => assert_eq!(val, 0),
-                1 => assert_eq!(val, 1),
-                2 => assert_eq!(val, 2),
-                3 => assert_eq!(val, 3),
-                4 => assert_eq!(val, 4),
-                _ => panic!("bad length"),
-            }
+    // The ready callback, if the queue went from empty to non-empty since last asked.
+    // Senders ask under the lock and call it once the lock is released.
+    fn take_ready(&mut self) -> Option<ReadyFn> {
+        if !mem::take(&mut self.queue.refilled) {
+            return None;
         }
+        self.on_ready.clone()
     }
 
-    #[test]
-    fn test_channel_concurrent() {
-        let (mut tx, mut rx) = new_channel();
+    // When the next delayed message or tick is due, if any.
+    fn next_maturity(&self) -> Option<Instant> {
+        let next_tick = self.ticker.as_ref().map(|ticker| ticker.next);
+        let next_delayed = self.delayed.peek().and_then(|d| d.deadline);
+        match (next_tick, next_delayed) {
+            (Some(a), Some(b)) => Some(cmp::min(a, b)),
+            (a, b) => a.or(b),
+        }
+    }
 
-        let f = move || {
-            for i in 0..5 {
-                tx.send(format!("hello {}", i));
-                sleep(Duration::new(0, 10000000));
-            }
+    // Queues the tick that came due, unless one is still queued, and schedules the next
+    // one period on; ticks missed meanwhile are skipped rather than piling up. A next tick
+    // past the range of Instant is never due, so the ticker stops.
+    fn fire_ticker(&mut self) {
+        let now = Instant::now();
+        let is_idle = self.queue.is_empty() && !self.closed;
+        let Some(ticker) = &mut self.ticker else {
+            return;
         };
-        thread::spawn(f);
-
-        for i in 0..5 {
-            assert_eq!(rx.recv(), Some(format!("hello {}", i)));
+        if ticker.next > now {
+            return;
+        }
+        let due = ticker.next;
+        let n_missed = (now - due).as_nanos() / ticker.period.as_nanos();
+        let n_periods = u32::try_from(n_missed + 1).unwrap_or(u32::MAX);
+        let next = ticker
+            .period
+            .checked_mul(n_periods)
+            .and_then(|skip| due.checked_add(skip));
+        let msg = is_idle.then(|| (ticker.make)(due));
+        match next {
+            Some(next) => ticker.next = next,
+            None => self.ticker = None,
+        }
+        if let Some(msg) = msg {
+            self.queue.push_back(Envelope::new(msg, 0));
         }
     }
 
-    #[test]
-    fn test_channel_mpsc() {
-        let (tx, mut rx) = new_channel();
-        let mut tx1 = tx.clone();
-        let mut tx2 = tx.clone();
-        let mut tx3 = tx.clone();
-        drop(tx);
+    fn is_bounded(&self) -> bool {
+        self.capacity.is_some() || self.max_bytes.is_some()
+    }
 
-        thread::spawn(move || {
-            for i in 0..5 {
-                tx1.send(format!("hello {} from {}", i, 1));
-                sleep(Duration::new(0, 10000000));
-            }
-        });
-        thread::spawn(move || {
-            for i in 0..5 {
-                tx2.send(format!("hello {} from {}", i, 2));
-                sleep(Duration::new(0, 20000000));
-            }
-        });
-        thread::spawn(move || {
-            for i in 0..5 {
-                tx3.send(format!("hello {} from {}", i, 3));
-                sleep(Duration::new(0, 30000000));
-            }
+    // No room for even the smallest message. Delayed messages and reserved slots count
+    // against the capacity too.
+    fn is_full(&self) -> bool {
+        let slots_full = self.capacity.is_some_and(|capacity| {
+            self.queue.len() + self.delayed.len() + self.n_reserved >= capacity
         });
+        let bytes_full = self
+            .max_bytes
+            .is_some_and(|max_bytes| self.queued_bytes() >= max_bytes);
+        slots_full || bytes_full
+    }
 
-        for _ in 0..15 {
-            assert!(rx.recv().is_some());
+    // A message larger than the whole byte budget still fits into an empty channel, or
+    // it could never be sent.
+    fn has_room_for(&self, size: usize) -> bool {
+        if self.is_full() {
+            return false;
         }
-        assert!(rx.recv().is_none());
+        self.max_bytes.is_none_or(|max_bytes| {
+            let n_bytes = self.queued_bytes();
+            n_bytes == 0 || n_bytes + size <= max_bytes
+        })
     }
 
-    #[test]
-    fn test_channel_spmc() {
-        let (mut tx, rx) = new_channel();
-        let mut rx1 = rx.clone();
-        let mut rx2 = rx.clone();
-        let mut rx3 = rx.clone();
-        drop(rx);
+    // Room alone isn't enough while others are parked: a new arrival goes behind them.
+    fn can_send_now(&self, size: usize) -> bool {
+        self.send_line.is_empty() && self.has_room_for(size)
+    }
+
+    fn queued_bytes(&self) -> usize {
+        self.queue.n_bytes + self.n_delayed_bytes
+    }
+
+    fn is_drained(&self) -> bool {
+        self.queue.is_empty() && self.delayed.is_empty()
+    }
+
+    // No sender can add to the queue and nothing delayed is left to mature.
+    fn no_more_arrivals(&self) -> bool {
+        let is_abandoned = self.n_senders == 0 && self.n_keep_alive == 0;
+        (is_abandoned || self.closed) && self.delayed.is_empty()
+    }
+
+    fn state(&self) -> ChannelState {
+        let pending = self.queue.len() + self.delayed.len();
+        if self.closed {
+            ChannelState::Closed { pending }
+        } else if self.n_receivers == 0 {
+            ChannelState::ReceiversGone
+        } else if self.n_senders == 0 && self.n_keep_alive == 0 {
+            ChannelState::SendersGone { pending }
+        } else {
+            ChannelState::Open
+        }
+    }
+
+    // Sends fail once every receiver is gone or one closed the channel.
+    fn check_accepting(&self) -> Result<(), SendTimeoutError<()>> {
+        if self.n_receivers == 0 {
+            Err(SendTimeoutError::Disconnected(()))
+        } else if self.closed {
+            Err(SendTimeoutError::Closed(()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T> Shared<T> {
+    fn size_of(&self, msg: &T) -> usize {
+        self.size_of.map_or(0, |size_of| size_of(msg))
+    }
+
+    // Passes a message the overflow policy evicted or an on_send hook dropped to the
+    // on_drop hook. Call without the channel lock.
+    fn discard(&self, msg: T) {
+        match &self.on_drop {
+            Some(on_drop) => on_drop(msg),
+            None => drop(msg),
+        }
+    }
+
+    // Runs the on_send hooks in order, stopping at the first that drops the message; a
+    // hook that panics drops it too. A dropped message is counted and discarded. Call
+    // without the channel lock.
+    fn admit(&self, msg: T) -> Option<T> {
+        if self.accepts(&msg) {
+            return Some(msg);
+        }
+        self.reject(msg);
+        None
+    }
+
+    // The on_send hooks' verdict on `msg`, as in admit.
+    fn accepts(&self, msg: &T) -> bool {
+        self.on_send.iter().all(|on_send| {
+            let decision = panic::catch_unwind(AssertUnwindSafe(|| on_send(msg)));
+            decision.unwrap_or(SendDecision::Drop) == SendDecision::Accept
+        })
+    }
+
+    // Counts and discards a message an on_send hook dropped. Call without the channel lock.
+    fn reject(&self, msg: T) {
+        self.inner.lock().recover().n_dropped += 1;
+        self.discard(msg);
+    }
+
+    fn envelope(&self, msg: T) -> Envelope<T> {
+        let size = self.size_of(&msg);
+        Envelope::new(msg, size)
+    }
+
+    fn lock_recv(&self) -> MutexGuard<'_, Inner<T>> {
+        let mut inner_guard = self.inner.lock().recover();
+        inner_guard.promote_matured();
+        inner_guard
+    }
+
+    // Closes the channel to sends, waking everyone parked. Only the first reason given is
+    // kept; returns it if it is an `R`.
+    fn close<R: Clone + Send + 'static>(&self, reason: Option<R>) -> Option<R> {
+        let mut inner_guard = self.inner.lock().recover();
+        if inner_guard.close_reason.is_none() {
+            inner_guard.close_reason = reason.map(|r| Box::new(r) as Box<dyn Any + Send>);
+        }
+        let reason = inner_guard
+            .close_reason
+            .as_ref()
+            .and_then(|r| r.downcast_ref::<R>())
+            .cloned();
+        if !inner_guard.closed {
+            inner_guard.closed = true;
+            drop(inner_guard);
+            self.notify_not_full();
+            self.below_low.notify_all();
+            // receivers parked on an empty queue can now return Err(RecvError)
+            self.notify_available_all();
+        }
+        reason
+    }
+
+    // Spends a slot taken by a permit. If every receiver has gone since the reservation,
+    // the message is dropped.
+    fn send_reserved(&self, msg: T) {
+        let Some(msg) = self.admit(msg) else {
+            self.release_reserved(1);
+            return;
+        };
+        let mut inner_guard = self.inner.lock().recover();
+        inner_guard.n_reserved -= 1;
+        if inner_guard.check_accepting().is_ok() {
+            inner_guard.queue.push_back(self.envelope(msg));
+            self.notify_available();
+        }
+        let ready = inner_guard.take_ready();
+        drop(inner_guard);
+        call_ready(ready);
+    }
+
+    fn release_reserved(&self, n: usize) {
+        let mut inner_guard = self.inner.lock().recover();
+        inner_guard.n_reserved -= n;
+        self.notify_popped(&inner_guard);
+    }
+
+    fn shrink_to(&self, min_capacity: usize) {
+        self.inner.lock().recover().queue.shrink_to(min_capacity);
+    }
+
+    // Discards every queued message, leaving delayed ones to mature, and returns how many
+    // went. They are dropped outside the lock.
+    fn purge(&self) -> usize {
+        let mut inner_guard = self.inner.lock().recover();
+        let queue = inner_guard.queue.take_entries();
+        self.notify_popped(&inner_guard);
+        drop(inner_guard);
+        queue.len()
+    }
+
+    fn wake_all(&self) {
+        // taking the lock orders this wakeup after any waiter's flag check
+        let inner_guard = self.inner.lock().recover();
+        self.notify_available_all();
+        drop(inner_guard);
+    }
+
+    // A message was queued: one plain receiver can take it, but every selective one has
+    // to look.
+    fn notify_available(&self) {
+        self.available.notify_one();
+        self.selective.notify_all();
+        #[cfg(feature = "async")]
+        self.wake_async_receiver();
+    }
+
+    // Every receiver has to look, e.g. because several messages were queued or the
+    // channel ended.
+    fn notify_available_all(&self) {
+        self.available.notify_all();
+        self.selective.notify_all();
+        #[cfg(feature = "async")]
+        self.wake_async_receivers();
+    }
+
+    fn notify_not_full(&self) {
+        self.not_full.notify_all();
+        #[cfg(feature = "async")]
+        self.wake_async_senders();
+    }
+
+    // Called after messages leave the queue, to admit senders parked on a full channel
+    // and release flushers once nothing is left.
+    fn notify_popped(&self, inner_guard: &Inner<T>) {
+        if inner_guard.is_bounded() {
+            self.notify_not_full();
+        }
+        if inner_guard.is_drained() {
+            self.drained.notify_all();
+        }
+        if inner_guard.queue.watermarks.is_some() && !inner_guard.queue.is_above_low() {
+            self.below_low.notify_all();
+        }
+    }
+
+    // Uses try_lock, since the caller may be formatting from code that runs under the
+    // channel lock, such as a recv_matching predicate.
+    fn fmt_state(&self, kind: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Ok(inner_guard) = self.inner.try_lock() else {
+            return match &self.name {
+                Some(name) => write!(f, "{kind} {{ name: {name:?}, <locked> }}"),
+                None => write!(f, "{kind} {{ <locked> }}"),
+            };
+        };
+        let mut debug_struct = f.debug_struct(kind);
+        if let Some(name) = &self.name {
+            debug_struct.field("name", name);
+        }
+        debug_struct
+            .field("len", &inner_guard.queue.len())
+            .field("n_senders", &inner_guard.n_senders)
+            .field("n_receivers", &inner_guard.n_receivers)
+            .field("capacity", &inner_guard.capacity)
+            .field("closed", &inner_guard.closed)
+            .finish()
+    }
+
+    // Waits on `condvar`, waking no later than `deadline` or the next delayed message.
+    fn wait<'a>(
+        &self,
+        condvar: &Condvar,
+        inner_guard: MutexGuard<'a, Inner<T>>,
+        deadline: Option<Instant>,
+    ) -> MutexGuard<'a, Inner<T>> {
+        let wake_at = match (deadline, inner_guard.next_maturity()) {
+            (Some(a), Some(b)) => Some(cmp::min(a, b)),
+            (a, b) => a.or(b),
+        };
+        let mut inner_guard = match wake_at {
+            Some(wake_at) => {
+                let timeout = wake_at.saturating_duration_since(Instant::now());
+                condvar.wait_on_timeout(inner_guard, timeout)
+            }
+            None => condvar.wait_on(inner_guard),
+        };
+        inner_guard.promote_matured();
+        inner_guard
+    }
+}
+
+impl<T> Delayed<T> {
+    // Sorts deadlines that never come after every real one.
+    fn key(&self) -> (bool, Option<Instant>, u64) {
+        (self.deadline.is_none(), self.deadline, self.seq)
+    }
+}
+
+// Ordered so the BinaryHeap (a max-heap) pops the earliest deadline, then the earliest send.
+impl<T> Ord for Delayed<T> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        other.key().cmp(&self.key())
+    }
+}
+
+impl<T> PartialOrd for Delayed<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Delayed<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<T> Eq for Delayed<T> {}
+
+pub fn new_channel<T>() -> (Sender<T>, Receiver<T>) {
+    ChannelBuilder::new().finish()
+}
+
+// Unbounded, labeled with `name` in the Debug output of its endpoints and by their name
+// accessors.
+pub fn new_channel_named<T>(name: impl Into<Arc<str>>) -> (Sender<T>, Receiver<T>) {
+    ChannelBuilder::new().name(name).finish()
+}
+
+// Unbounded, with room for `n` messages allocated up front so a burst doesn't reallocate
+// under the lock.
+pub fn new_channel_with_capacity<T>(n: usize) -> (Sender<T>, Receiver<T>) {
+    let (tx, rx) = new_channel();
+    tx.shared.inner.lock().recover().queue.reserve(n);
+    (tx, rx)
+}
+
+// Unbounded, but tracks the queue length against `high` and `low` for backpressure: once
+// `high` messages are queued, `on_watermark` gets High and wait_below_high blocks, until
+// the queue drains below `low` and it gets Low. Delayed messages count once they mature.
+// `on_watermark` runs under the channel lock, so it must not use the channel. Panics
+// unless `low <= high`.
+pub fn new_channel_with_watermarks<T>(
+    high: usize,
+    low: usize,
+    on_watermark: impl FnMut(Watermark) + Send + 'static,
+) -> (Sender<T>, Receiver<T>) {
+    assert!(low <= high, "low watermark must not exceed the high one");
+    ChannelBuilder::new()
+        .watermarks(high, low, on_watermark)
+        .finish()
+}
+
+// Sends block while `capacity` messages are queued. Panics if `capacity` is zero; there is
+// no rendezvous mode.
+pub fn new_bounded<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    new_bounded_with(capacity, OverflowPolicy::Block)
+}
+
+// Like new_bounded, but a full channel is handled per `policy`.
+pub fn new_bounded_with<T>(capacity: usize, policy: OverflowPolicy) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "bounded channel capacity must be nonzero");
+    ChannelBuilder::new()
+        .capacity(capacity)
+        .overflow(policy)
+        .finish()
+}
+
+// Sends block while the summed MessageSize of queued and delayed messages would exceed
+// `max_bytes`; any number of messages may be queued otherwise. Panics if `max_bytes` is
+// zero.
+pub fn new_bounded_bytes<T: MessageSize>(max_bytes: usize) -> (Sender<T>, Receiver<T>) {
+    new_bounded_bytes_with(max_bytes, OverflowPolicy::Block)
+}
+
+pub fn new_bounded_bytes_with<T: MessageSize>(
+    max_bytes: usize,
+    policy: OverflowPolicy,
+) -> (Sender<T>, Receiver<T>) {
+    assert!(max_bytes > 0, "byte budget must be nonzero");
+    ChannelBuilder::new()
+        .max_bytes(max_bytes)
+        .overflow(policy)
+        .finish()
+}
+
+// A receiver that never gets a message and never ends, e.g. to stand in for an absent
+// input to merge: recv blocks forever and recv_timeout times out. It has no senders, but
+// is held open as by a KeepAlive that is never dropped; only close ends it.
+pub fn never<T>() -> Receiver<T> {
+    let (tx, rx) = new_channel();
+    rx.shared.inner.lock().recover().n_keep_alive += 1;
+    drop(tx);
+    rx
+}
+
+// A receiver that gets the current time once every `period`, starting one period from
+// now. Nothing runs in the background; a tick is produced when a receive finds it due.
+// Ticks a slow receiver missed are dropped, bar the one it finds queued, which carries
+// its original time. Ends only when closed. Panics if `period` is zero; a period too long
+// to represent never ticks.
+pub fn tick(period: Duration) -> Receiver<Instant> {
+    assert!(!period.is_zero(), "tick period must be nonzero");
+    let rx = never();
+    if let Some(next) = Instant::now().checked_add(period) {
+        rx.shared.inner.lock().recover().ticker = Some(Ticker {
+            period,
+            next,
+            make: convert::identity,
+        });
+    }
+    rx
+}
+
+// A receiver that gets the time `timeout` from now once that time comes, and then ends.
+// Of several clones, one gets it and the rest see the end. A timeout too long to
+// represent never comes, as with never.
+pub fn after(timeout: Duration) -> Receiver<Instant> {
+    let Some(deadline) = Instant::now().checked_add(timeout) else {
+        return never();
+    };
+    let (tx, rx) = new_channel();
+    tx.send_at(deadline, Some(deadline)).unwrap();
+    rx
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        self.send_with(msg, MsgQueue::push_back)
+    }
+
+    // High messages are received before any Low ones, FIFO within each lane. Plain `send`
+    // is Low, so a steady stream of High messages starves it.
+    pub fn send_priority(&self, msg: T, prio: Priority) -> Result<(), SendError<T>> {
+        let push = match prio {
+            Priority::High => MsgQueue::push_high,
+            Priority::Low => MsgQueue::push_back,
+        };
+        self.send_with(msg, push)
+    }
+
+    // Jumps the queue, even High messages; of several front-sent messages, the latest is
+    // received first.
+    pub fn send_front(&self, msg: T) -> Result<(), SendError<T>> {
+        self.send_with(msg, MsgQueue::push_front)
+    }
+
+    // Never blocks; only a bounded channel can be `Full`.
+    pub fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        self.send_until(msg, Some(Instant::now()))
+            .map_err(|err| match err {
+                SendTimeoutError::Timeout(msg) => TrySendError::Full(msg),
+                SendTimeoutError::Disconnected(msg) => TrySendError::Disconnected(msg),
+                SendTimeoutError::Closed(msg) => TrySendError::Closed(msg),
+            })
+    }
+
+    // Moves messages from the front of `msgs` until the channel is full, never blocking.
+    // Unsent messages stay in `msgs` in their original order.
+    pub fn try_send_many(&self, msgs: &mut VecDeque<T>) -> usize {
+        if !self.shared.on_send.is_empty() {
+            // the hooks must run unlocked, so this takes one message at a time
+            let mut n_sent = 0;
+            while let Some(msg) = msgs.pop_front() {
+                if let Err(err) = self.try_send(msg) {
+                    msgs.push_front(err.into_inner());
+                    break;
+                }
+                n_sent += 1;
+            }
+            return n_sent;
+        }
+        let mut inner_guard = self.shared.inner.lock().recover();
+        if inner_guard.check_accepting().is_err() || !inner_guard.send_line.is_empty() {
+            return 0;
+        }
+        let mut n_sent = 0;
+        while let Some(msg) = msgs.front() {
+            let size = self.shared.size_of(msg);
+            if !inner_guard.can_send_now(size) {
+                break;
+            }
+            let msg = msgs.pop_front().unwrap();
+            inner_guard.queue.push_back(Envelope::new(msg, size));
+            n_sent += 1;
+        }
+        let ready = inner_guard.take_ready();
+        drop(inner_guard);
+        if n_sent > 0 {
+            self.shared.notify_available_all();
+        }
+        call_ready(ready);
+        n_sent
+    }
+
+    // Only a bounded channel can make this wait; unbounded sends never time out.
+    pub fn send_timeout(&self, msg: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        let deadline = Instant::now().checked_add(timeout);
+        self.send_until(msg, deadline)
+    }
+
+    // The message stays invisible to receivers until `delay` has passed; delayed messages
+    // are delivered in deadline order and keep the channel open until consumed. A delay
+    // too long to represent, such as Duration::MAX, never passes.
+    pub fn send_after(&self, msg: T, delay: Duration) -> Result<(), SendError<T>> {
+        self.send_at(msg, Instant::now().checked_add(delay))
+    }
+
+    fn send_at(&self, msg: T, deadline: Option<Instant>) -> Result<(), SendError<T>> {
+        let Some(msg) = self.shared.admit(msg) else {
+            return Ok(());
+        };
+        let size = self.shared.size_of(&msg);
+        let Some((mut inner_guard, msg)) = self.lock_for_send(msg, size)? else {
+            return Ok(());
+        };
+        let seq = inner_guard.n_delayed;
+        inner_guard.n_delayed += 1;
+        let delayed = Delayed {
+            deadline,
+            seq,
+            msg: Envelope::new(msg, size),
+        };
+        let is_earliest = inner_guard
+            .delayed
+            .peek()
+            .is_none_or(|d| delayed.key() < d.key());
+        inner_guard.n_delayed_bytes += size;
+        inner_guard.delayed.push(delayed);
+        if is_earliest {
+            // parked receivers must recompute how long to sleep
+            self.shared.notify_available_all();
+        }
+        Ok(())
+    }
+
+    // The handle's wait returns once a receiver takes the message, or errors if it is
+    // discarded first, e.g. because every receiver was dropped.
+    pub fn send_tracked(&self, msg: T) -> Result<SendHandle, SendError<T>> {
+        let tracked = Arc::new(Tracked {
+            delivery: Mutex::new(Delivery::Pending),
+            done: Condvar::new(),
+        });
+        let Some(msg) = self.shared.admit(msg) else {
+            *tracked.delivery.lock().recover() = Delivery::Dropped;
+            return Ok(SendHandle { tracked });
+        };
+        let size = self.shared.size_of(&msg);
+        let Some((mut inner_guard, msg)) = self.lock_for_send(msg, size)? else {
+            // discarded by DropNewest, which the handle reports as Dropped
+            *tracked.delivery.lock().recover() = Delivery::Dropped;
+            return Ok(SendHandle { tracked });
+        };
+        inner_guard.queue.push_back(Envelope {
+            msg,
+            tracker: Some(DeliveryTracker(Arc::clone(&tracked))),
+            size,
+        });
+        self.shared.notify_available();
+        let ready = inner_guard.take_ready();
+        drop(inner_guard);
+        call_ready(ready);
+        Ok(SendHandle { tracked })
+    }
+
+    fn send_with(
+        &self,
+        msg: T,
+        push: fn(&mut MsgQueue<T>, Envelope<T>),
+    ) -> Result<(), SendError<T>> {
+        let Some(msg) = self.shared.admit(msg) else {
+            return Ok(());
+        };
+        let size = self.shared.size_of(&msg);
+        if let Some((mut inner_guard, msg)) = self.lock_for_send(msg, size)? {
+            push(&mut inner_guard.queue, Envelope::new(msg, size));
+            self.shared.notify_available();
+            let ready = inner_guard.take_ready();
+            drop(inner_guard);
+            call_ready(ready);
+        }
+        Ok(())
+    }
+
+    fn send_until(&self, msg: T, deadline: Option<Instant>) -> Result<(), SendTimeoutError<T>> {
+        let Some(msg) = self.shared.admit(msg) else {
+            return Ok(());
+        };
+        let size = self.shared.size_of(&msg);
+        let (mut inner_guard, msg) = self.lock_with_room(msg, size, deadline)?;
+        inner_guard.queue.push_back(Envelope::new(msg, size));
+        self.shared.notify_available();
+        let ready = inner_guard.take_ready();
+        drop(inner_guard);
+        call_ready(ready);
+        Ok(())
+    }
+
+    // Like lock_with_room without a deadline, but a full channel is handled per the
+    // overflow policy. Ok(None) means DropNewest discarded the message.
+    fn lock_for_send(
+        &self,
+        msg: T,
+        size: usize,
+    ) -> Result<Option<LockedWith<'_, T, T>>, SendError<T>> {
+        loop {
+            let mut inner_guard = self.shared.inner.lock().recover();
+            if let Err(err) = inner_guard.check_accepting() {
+                return Err(SendError::from_untimed(err.map(|()| msg)));
+            }
+            if inner_guard.can_send_now(size) {
+                return Ok(Some((inner_guard, msg)));
+            }
+            match inner_guard.policy {
+                OverflowPolicy::Block => {}
+                OverflowPolicy::Reject => return Err(SendError::Full(msg)),
+                OverflowPolicy::DropNewest => {
+                    inner_guard.n_dropped += 1;
+                    drop(inner_guard);
+                    self.shared.discard(msg);
+                    return Ok(None);
+                }
+                // with room to spare, evicting wouldn't get ahead of those parked
+                OverflowPolicy::DropOldest if !inner_guard.has_room_for(size) => {
+                    if let Some(oldest) = inner_guard.queue.pop_front() {
+                        inner_guard.n_dropped += 1;
+                        // dropped outside the lock; another sender may take the freed
+                        // slot meanwhile, in which case the next oldest goes too
+                        drop(inner_guard);
+                        let Envelope {
+                            msg: oldest,
+                            tracker,
+                            ..
+                        } = oldest;
+                        drop(tracker);
+                        self.shared.discard(oldest);
+                        continue;
+                    }
+                }
+                OverflowPolicy::DropOldest => {}
+            }
+            let (inner_guard, result) = self.wait_for_room(inner_guard, size, None);
+            return match result {
+                Ok(()) => Ok(Some((inner_guard, msg))),
+                Err(err) => Err(SendError::from_untimed(err.map(|()| msg))),
+            };
+        }
+    }
+
+    // Locks the channel once it has room for one more message of `size` bytes, handing
+    // `msg` back with the guard. Gives up when no receivers remain or `deadline` passes.
+    fn lock_with_room<M>(
+        &self,
+        msg: M,
+        size: usize,
+        deadline: Option<Instant>,
+    ) -> Result<LockedWith<'_, T, M>, SendTimeoutError<M>> {
+        let inner_guard = self.shared.inner.lock().recover();
+        let (inner_guard, result) = self.wait_for_room(inner_guard, size, deadline);
+        match result {
+            Ok(()) => Ok((inner_guard, msg)),
+            Err(err) => Err(err.map(|()| msg)),
+        }
+    }
+
+    // Parks in the send line until it is this sender's turn and there is room, so senders
+    // are admitted in arrival order however the wakeups fall. A deadline already passed
+    // fails fast without joining the line. The guard comes back either way.
+    fn wait_for_room<'a>(
+        &self,
+        mut inner_guard: MutexGuard<'a, Inner<T>>,
+        size: usize,
+        deadline: Option<Instant>,
+    ) -> LockedWith<'a, T, Result<(), SendTimeoutError<()>>> {
+        let mut ticket = None;
+        let result = loop {
+            if let Err(err) = inner_guard.check_accepting() {
+                break Err(err);
+            }
+            let is_turn = inner_guard.send_line.front() == ticket.as_ref();
+            if is_turn && inner_guard.has_room_for(size) {
+                break Ok(());
+            }
+            let now = Instant::now();
+            if deadline.is_some_and(|deadline| now >= deadline) {
+                break Err(SendTimeoutError::Timeout(()));
+            }
+            if ticket.is_none() {
+                let next_ticket = inner_guard.next_ticket;
+                inner_guard.next_ticket += 1;
+                inner_guard.send_line.push_back(next_ticket);
+                ticket = Some(next_ticket);
+            }
+            inner_guard = match deadline {
+                Some(deadline) => self
+                    .shared
+                    .not_full
+                    .wait_on_timeout(inner_guard, deadline - now),
+                None => self.shared.not_full.wait_on(inner_guard),
+            };
+        };
+        if let Some(ticket) = ticket {
+            let idx = inner_guard.send_line.iter().position(|&t| t == ticket);
+            inner_guard.send_line.remove(idx.unwrap());
+            // the next in line may be admitted too, or be first now that this one gave up
+            self.shared.notify_not_full();
+        }
+        (inner_guard, result)
+    }
+
+    // Blocks like `send` until there is room, so the message need only be built once a
+    // slot is guaranteed.
+    pub fn reserve(&self) -> Result<Permit<'_, T>, ReserveError> {
+        let (mut inner_guard, ()) = self.lock_with_room((), 0, None).map_err(|_| ReserveError)?;
+        inner_guard.n_reserved += 1;
+        Ok(Permit { tx: self })
+    }
+
+    // Claims all `n` slots at once or none. Never blocks; a group larger than the capacity
+    // can never fit and always fails with Full, as does any group while the byte budget is
+    // used up.
+    pub fn try_reserve_many(&self, n: usize) -> Result<PermitBatch<'_, T>, TryReserveError> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        match inner_guard.check_accepting() {
+            Ok(()) => {}
+            Err(SendTimeoutError::Closed(())) => return Err(TryReserveError::Closed),
+            Err(_) => return Err(TryReserveError::Disconnected),
+        }
+        let is_out_of_bytes = inner_guard
+            .max_bytes
+            .is_some_and(|max_bytes| inner_guard.queued_bytes() >= max_bytes);
+        if !inner_guard.send_line.is_empty() || is_out_of_bytes {
+            return Err(TryReserveError::Full);
+        }
+        if let Some(capacity) = inner_guard.capacity {
+            let occupied = inner_guard.queue.len() + inner_guard.delayed.len();
+            if occupied + inner_guard.n_reserved + n > capacity {
+                return Err(TryReserveError::Full);
+            }
+        }
+        inner_guard.n_reserved += n;
+        Ok(PermitBatch { tx: self, n })
+    }
+
+    // Like `reserve`, but the permit owns the sender and hands it back once used.
+    pub fn reserve_owned(self) -> Result<OwnedPermit<T>, ReserveError> {
+        let (mut inner_guard, ()) = self.lock_with_room((), 0, None).map_err(|_| ReserveError)?;
+        inner_guard.n_reserved += 1;
+        drop(inner_guard);
+        Ok(OwnedPermit { tx: Some(self) })
+    }
+
+    // Messages discarded so far by a DropNewest or DropOldest policy or an on_send hook.
+    pub fn dropped_count(&self) -> u64 {
+        self.shared.inner.lock().recover().n_dropped
+    }
+
+    // Like dropped_count, but resets the count, so successive calls give deltas.
+    pub fn take_dropped_count(&self) -> u64 {
+        mem::take(&mut self.shared.inner.lock().recover().n_dropped)
+    }
+
+    // The bound given at construction, or None if unbounded. Reserved slots are not
+    // subtracted; is_full accounts for them.
+    pub fn capacity(&self) -> Option<usize> {
+        self.shared.inner.lock().recover().capacity
+    }
+
+    // Messages ready to be received; delayed messages are not counted until they mature.
+    pub fn len(&self) -> usize {
+        self.shared.inner.lock().recover().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.inner.lock().recover().queue.is_empty()
+    }
+
+    pub fn same_channel(&self, other: &Sender<T>) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    pub fn is_paired_with(&self, rx: &Receiver<T>) -> bool {
+        Arc::ptr_eq(&self.shared, &rx.shared)
+    }
+
+    pub fn channel_id(&self) -> ChannelId {
+        self.shared.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.shared.name.as_deref()
+    }
+
+    // A new receiver competing with the existing ones for each message. If every receiver
+    // was already dropped, this reconnects the channel: what was queued then is gone, and
+    // sends work again from this call on.
+    pub fn subscribe(&self) -> Receiver<T> {
+        self.shared.inner.lock().recover().n_receivers += 1;
+        Receiver::from_shared(Arc::clone(&self.shared))
+    }
+
+    // A handle that does not count as a sender: once every Sender is dropped, receivers
+    // see the channel as disconnected even while weak senders remain.
+    pub fn downgrade(&self) -> WeakSender<T> {
+        let generation = self.shared.inner.lock().recover().generation;
+        WeakSender {
+            shared: Arc::downgrade(&self.shared),
+            generation,
+        }
+    }
+
+    // Every receiver is gone, so nothing sent from now on can be received.
+    pub fn is_disconnected(&self) -> bool {
+        self.shared.inner.lock().recover().n_receivers == 0
+    }
+
+    // A snapshot, like sender_count.
+    pub fn state(&self) -> ChannelState {
+        self.shared.inner.lock().recover().state()
+    }
+
+    // Live senders, including owned permits; a snapshot that other threads may change
+    // right away.
+    pub fn sender_count(&self) -> usize {
+        self.shared.inner.lock().recover().n_senders
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.shared.inner.lock().recover().n_receivers
+    }
+
+    // Queued and delayed messages plus outstanding permits fill the channel. Never true
+    // when unbounded.
+    pub fn is_full(&self) -> bool {
+        self.shared.inner.lock().recover().is_full()
+    }
+
+    // Summed MessageSize of queued and delayed messages; always 0 unless the channel was
+    // made by new_bounded_bytes.
+    pub fn queued_bytes(&self) -> usize {
+        self.shared.inner.lock().recover().queued_bytes()
+    }
+
+    // Releases queue memory left over from a burst; queued messages are untouched.
+    pub fn shrink_to_fit(&self) {
+        self.shared.shrink_to(0);
+    }
+
+    // Throws away the backlog and returns its length, without closing the channel.
+    // Delayed messages are kept.
+    pub fn purge(&self) -> usize {
+        self.shared.purge()
+    }
+
+    // Like shrink_to_fit, but keeps room for at least `min_capacity` messages.
+    pub fn shrink_to(&self, min_capacity: usize) {
+        self.shared.shrink_to(min_capacity);
+    }
+
+    // Blocks while the queue is above its low watermark after reaching the high one.
+    // Returns at once on a channel without watermarks or without receivers.
+    pub fn wait_below_high(&self) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        while inner_guard.check_accepting().is_ok() && inner_guard.queue.is_above_low() {
+            inner_guard = self.shared.below_low.wait_on(inner_guard);
+        }
+    }
+
+    // Blocks until every message sent by anyone, delayed ones included, has been received.
+    pub fn flush(&self) -> Result<(), FlushError> {
+        self.flush_until(None).map_err(|_| FlushError)
+    }
+
+    pub fn flush_timeout(&self, timeout: Duration) -> Result<(), FlushTimeoutError> {
+        self.flush_until(Instant::now().checked_add(timeout))
+    }
+
+    fn flush_until(&self, deadline: Option<Instant>) -> Result<(), FlushTimeoutError> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        loop {
+            // checked first: the last receiver's drop empties the queue without receiving
+            if inner_guard.n_receivers == 0 {
+                return Err(FlushTimeoutError::Disconnected);
+            }
+            if inner_guard.is_drained() {
+                return Ok(());
+            }
+            inner_guard = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(FlushTimeoutError::Timeout);
+                    }
+                    let timeout = deadline - now;
+                    self.shared.drained.wait_on_timeout(inner_guard, timeout)
+                }
+                None => self.shared.drained.wait_on(inner_guard),
+            };
+        }
+    }
+
+    // Blocks until every receiver has been dropped. Receiver::close does not count.
+    pub fn closed(&self) {
+        self.closed_until(None);
+    }
+
+    // Returns whether every receiver was dropped before `timeout` passed.
+    pub fn closed_timeout(&self, timeout: Duration) -> bool {
+        self.closed_until(Instant::now().checked_add(timeout))
+    }
+
+    fn closed_until(&self, deadline: Option<Instant>) -> bool {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        while inner_guard.n_receivers > 0 {
+            inner_guard = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    let timeout = deadline - now;
+                    self.shared
+                        .no_receivers
+                        .wait_on_timeout(inner_guard, timeout)
+                }
+                None => self.shared.no_receivers.wait_on(inner_guard),
+            };
+        }
+        true
+    }
+
+    // Enqueues the batch under one lock, except that it is released while a bounded
+    // channel waits for room and while on_send hooks run. Returns how many were enqueued, stopping early if no receivers
+    // remain. If `iter` panics, the items it already yielded stay enqueued.
+    pub fn send_all<I: IntoIterator<Item = T>>(&self, iter: I) -> usize {
+        match self.send_iter(iter) {
+            Ok(n_sent) => n_sent,
+            Err(err) => err.n_sent,
+        }
+    }
+
+    // Like `send_all`, but if the receivers go away the remaining items are handed back
+    // rather than left in a dropped iterator.
+    pub fn send_iter<I: IntoIterator<Item = T>>(
+        &self,
+        iter: I,
+    ) -> Result<usize, SendIterError<Peekable<I::IntoIter>>> {
+        let mut iter = iter.into_iter().peekable();
+        let mut inner_guard = self.shared.inner.lock().recover();
+        let mut n_sent = 0;
+        let mut is_disconnected = false;
+        // whether the on_send hooks have passed the peeked item
+        let mut is_admitted = false;
+        let result = loop {
+            if inner_guard.check_accepting().is_err() {
+                is_disconnected = true;
+                break Ok(());
+            }
+            // pulled before waiting for room, so the wait is for this item's size; catching
+            // the panic here keeps the guard from being dropped while unwinding, which would
+            // poison the mutex for every other endpoint
+            match panic::catch_unwind(AssertUnwindSafe(|| iter.peek().is_some())) {
+                Ok(true) => {}
+                Ok(false) => break Ok(()),
+                Err(payload) => break Err(payload),
+            }
+            if !is_admitted && !self.shared.on_send.is_empty() {
+                // the hooks must run unlocked; the item stays peeked, so if the receivers
+                // leave meanwhile it is handed back with the rest
+                drop(inner_guard);
+                is_admitted = self.shared.accepts(iter.peek().unwrap());
+                if !is_admitted {
+                    self.shared.reject(iter.next().unwrap());
+                }
+                inner_guard = self.shared.inner.lock().recover();
+                continue;
+            }
+            let size = self.shared.size_of(iter.peek().unwrap());
+            if !inner_guard.can_send_now(size) {
+                // let receivers at what is queued so far before parking
+                self.shared.notify_available_all();
+                let result;
+                (inner_guard, result) = self.wait_for_room(inner_guard, size, None);
+                if result.is_err() {
+                    is_disconnected = true;
+                    break Ok(());
+                }
+            }
+            let msg = iter.next().unwrap();
+            inner_guard.queue.push_back(Envelope::new(msg, size));
+            n_sent += 1;
+            is_admitted = false;
+        };
+        let ready = inner_guard.take_ready();
+        drop(inner_guard);
+        if n_sent > 0 {
+            self.shared.notify_available_all();
+        }
+        call_ready(ready);
+        match result {
+            Ok(()) if is_disconnected => Err(SendIterError { n_sent, iter }),
+            Ok(()) => Ok(n_sent),
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+impl<T> Extend<T> for Sender<T> {
+    // Items are silently dropped if no receivers remain; use send_iter to get them back.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.send_all(iter);
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_senders += 1;
+        drop(inner_guard);
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.shared.fmt_state("Sender", f)
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_senders -= 1;
+        let is_channel_close = inner_guard.n_senders == 0;
+        drop(inner_guard);
+        if is_channel_close {
+            self.shared.notify_available_all();
+        }
+    }
+}
+
+impl<T> WeakSender<T> {
+    // Fails once every Sender has been dropped, even if the channel is later reset.
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        let shared = self.shared.upgrade()?;
+        let mut inner_guard = shared.inner.lock().recover();
+        if inner_guard.n_senders == 0 || inner_guard.generation != self.generation {
+            return None;
+        }
+        inner_guard.n_senders += 1;
+        drop(inner_guard);
+        Some(Sender { shared })
+    }
+}
+
+impl<T> Clone for WeakSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Weak::clone(&self.shared),
+            generation: self.generation,
+        }
+    }
+}
+
+impl<T> WeakReceiver<T> {
+    // Fails while no Receiver is alive, since the last one to drop discarded the queue.
+    pub fn upgrade(&self) -> Option<Receiver<T>> {
+        let shared = self.shared.upgrade()?;
+        let mut inner_guard = shared.inner.lock().recover();
+        if inner_guard.n_receivers == 0 {
+            return None;
+        }
+        inner_guard.n_receivers += 1;
+        drop(inner_guard);
+        Some(Receiver::from_shared(shared))
+    }
+}
+
+impl<T> Clone for WeakReceiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Weak::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Clone for KeepAlive<T> {
+    fn clone(&self) -> Self {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_keep_alive += 1;
+        drop(inner_guard);
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for KeepAlive<T> {
+    fn drop(&mut self) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_keep_alive -= 1;
+        let is_channel_close = inner_guard.n_keep_alive == 0 && inner_guard.n_senders == 0;
+        drop(inner_guard);
+        if is_channel_close {
+            self.shared.notify_available_all();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    // Callers account for the new handle in n_receivers.
+    fn from_shared(shared: Arc<Shared<T>>) -> Self {
+        Receiver {
+            shared,
+            #[cfg(feature = "async")]
+            slot: future::WakerSlot::default(),
+        }
+    }
+
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.recv_with(pop_front)
+    }
+
+    #[deprecated(note = "recv now returns Result; use recv().ok()")]
+    pub fn recv_opt(&self) -> Option<T> {
+        self.recv().ok()
+    }
+
+    pub fn recv_back(&self) -> Result<T, RecvError> {
+        self.recv_with(pop_back)
+    }
+
+    // The count is how many older messages were discarded.
+    pub fn recv_latest(&self) -> Result<(T, usize), RecvError> {
+        let (val, discarded) = self.recv_with(take_latest)?;
+        Ok((val, discarded.len()))
+    }
+
+    fn recv_with<R>(&self, pop: fn(&mut MsgQueue<T>) -> Option<R>) -> Result<R, RecvError> {
+        let mut inner_guard = self.shared.lock_recv();
+        loop {
+            if let Some(val) = pop(&mut inner_guard.queue) {
+                self.shared.notify_popped(&inner_guard);
+                return Ok(val);
+            }
+            if inner_guard.no_more_arrivals() {
+                return Err(RecvError); // channel is closed
+            }
+            inner_guard = self.shared.wait(&self.shared.available, inner_guard, None);
+        }
+    }
+
+    pub fn recv_guarded(&self) -> Result<RecvGuard<T>, RecvError> {
+        let msg = self.recv()?;
+        Ok(RecvGuard {
+            shared: Arc::clone(&self.shared),
+            msg: Some(msg),
+        })
+    }
+
+    // The flag is true when the channel was closed and the value is `T::default()`.
+    pub fn recv_or_default(&self) -> (T, bool)
+    where
+        T: Default,
+    {
+        match self.recv() {
+            Ok(val) => (val, false),
+            Err(RecvError) => (T::default(), true),
+        }
+    }
+
+    // A `max` of zero is treated as one, so a returned batch is never empty.
+    pub fn recv_many(&self, max: usize) -> Result<Vec<T>, RecvError> {
+        let mut inner_guard = self.shared.lock_recv();
+        loop {
+            if !inner_guard.queue.is_empty() {
+                let n = max.max(1).min(inner_guard.queue.len());
+                let mut batch = Vec::with_capacity(n);
+                inner_guard.queue.drain_into(n, &mut batch);
+                if !inner_guard.queue.is_empty() {
+                    // leftovers may have been signalled to us alone; pass the wakeup on
+                    self.shared.available.notify_one();
+                }
+                self.shared.notify_popped(&inner_guard);
+                return Ok(batch);
+            }
+            if inner_guard.no_more_arrivals() {
+                return Err(RecvError); // channel is closed
+            }
+            inner_guard = self.shared.wait(&self.shared.available, inner_guard, None);
+        }
+    }
+
+    pub fn recv_matching(&self, mut pred: impl FnMut(&T) -> bool) -> Result<T, RecvError> {
+        let mut inner_guard = self.shared.lock_recv();
+        loop {
+            if let Some(envelope) = inner_guard.queue.remove_first(&mut pred) {
+                let val = envelope.into_msg();
+                self.shared.notify_popped(&inner_guard);
+                return Ok(val);
+            }
+            if inner_guard.no_more_arrivals() {
+                return Err(RecvError); // channel is closed
+            }
+            inner_guard = self.shared.wait(&self.shared.selective, inner_guard, None);
+        }
+    }
+
+    pub fn recv_exact(&self, n: usize) -> Result<Vec<T>, RecvExactError<T>> {
+        let mut inner_guard = self.shared.lock_recv();
+        loop {
+            if inner_guard.queue.len() >= n {
+                let mut batch = Vec::with_capacity(n);
+                inner_guard.queue.drain_into(n, &mut batch);
+                self.shared.notify_popped(&inner_guard);
+                return Ok(batch);
+            }
+            if inner_guard.no_more_arrivals() {
+                let mut leftovers = Vec::new();
+                let len = inner_guard.queue.len();
+                inner_guard.queue.drain_into(len, &mut leftovers);
+                self.shared.notify_popped(&inner_guard);
+                return Err(RecvExactError(leftovers));
+            }
+            // each send only notify_one's `available`, which could be spent on a partial group
+            inner_guard = self.shared.wait(&self.shared.selective, inner_guard, None);
+        }
+    }
+
+    // Checked before popping, so a cancelled receiver leaves messages for the others.
+    // Whoever flips `cancel` should then call `wake_all` to rouse a parked receiver.
+    pub fn recv_cancellable(&self, cancel: &AtomicBool) -> Result<T, RecvCancelError> {
+        let mut inner_guard = self.shared.lock_recv();
+        loop {
+            if cancel.load(Ordering::Acquire) {
+                return Err(RecvCancelError::Cancelled);
+            }
+            if let Some(val) = pop_front(&mut inner_guard.queue) {
+                self.shared.notify_popped(&inner_guard);
+                return Ok(val);
+            }
+            if inner_guard.no_more_arrivals() {
+                return Err(RecvCancelError::Disconnected);
+            }
+            inner_guard = self.shared.wait(&self.shared.available, inner_guard, None);
+        }
+    }
+
+    // Refuses further sends with Closed, including sends parked on a full channel, while
+    // what is already queued or delayed can still be received; then recv returns
+    // Err(RecvError).
+    // Closing again does nothing.
+    pub fn close(&self) {
+        self.shared.close::<()>(None);
+    }
+
+    // Closes the channel, then hands queued messages to `f` until none are left or
+    // `timeout` passes. Whatever remains, delayed messages included, is dropped and
+    // counted as discarded. `f` runs without the channel locked.
+    pub fn close_and_drain(&self, timeout: Duration, mut f: impl FnMut(T)) -> DrainReport {
+        self.close();
+        let deadline = Instant::now().checked_add(timeout);
+        let mut n_processed = 0;
+        while deadline.is_none_or(|deadline| Instant::now() < deadline) {
+            let Ok(msg) = self.try_recv() else {
+                break;
+            };
+            f(msg);
+            n_processed += 1;
+        }
+        let mut inner_guard = self.shared.lock_recv();
+        let queue = inner_guard.queue.take_entries();
+        let delayed = mem::take(&mut inner_guard.delayed);
+        inner_guard.n_delayed_bytes = 0;
+        self.shared.notify_popped(&inner_guard);
+        drop(inner_guard);
+        let n_discarded = queue.len() + delayed.len();
+        drop(queue);
+        drop(delayed);
+        DrainReport {
+            n_processed,
+            n_discarded,
+        }
+    }
+
+    pub fn same_channel(&self, other: &Receiver<T>) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    pub fn channel_id(&self) -> ChannelId {
+        self.shared.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.shared.name.as_deref()
+    }
+
+    // A new sender for a channel that is still open. Once the last sender is gone, recv
+    // may already have returned Err(RecvError), so this fails rather than revive the channel;
+    // hold a KeepAlive guard to hand out senders across such gaps.
+    pub fn new_sender(&self) -> Result<Sender<T>, ChannelClosed> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        if inner_guard.closed || (inner_guard.n_senders == 0 && inner_guard.n_keep_alive == 0) {
+            return Err(ChannelClosed);
+        }
+        inner_guard.n_senders += 1;
+        drop(inner_guard);
+        Ok(Sender {
+            shared: Arc::clone(&self.shared),
+        })
+    }
+
+    // While the guard or a clone of it is alive, receivers keep waiting on an empty
+    // channel even with no senders left, so senders can come and go. Closing the channel
+    // still ends recv as usual.
+    pub fn keep_alive(&self) -> KeepAlive<T> {
+        self.shared.inner.lock().recover().n_keep_alive += 1;
+        KeepAlive {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    // A handle that does not count as a receiver: once every Receiver is dropped, sends
+    // fail with Disconnected even while weak receivers remain.
+    pub fn downgrade(&self) -> WeakReceiver<T> {
+        WeakReceiver {
+            shared: Arc::downgrade(&self.shared),
+        }
+    }
+
+    // Readies a channel whose senders are all gone for another round: reopens it if
+    // closed, discards leftover and delayed messages, zeroes the dropped count, and
+    // returns a new first Sender. Fails while any sender is alive, so messages from a
+    // previous round can never show up in the next one.
+    pub fn reset(&self) -> Result<Sender<T>, ResetError> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        if inner_guard.n_senders > 0 {
+            return Err(ResetError);
+        }
+        let queue = inner_guard.queue.take_entries();
+        let delayed = mem::take(&mut inner_guard.delayed);
+        inner_guard.n_delayed_bytes = 0;
+        inner_guard.n_dropped = 0;
+        inner_guard.closed = false;
+        let close_reason = inner_guard.close_reason.take();
+        inner_guard.generation += 1;
+        inner_guard.n_senders = 1;
+        drop(inner_guard);
+        drop(queue);
+        drop(delayed);
+        drop(close_reason);
+        Ok(Sender {
+            shared: Arc::clone(&self.shared),
+        })
+    }
+
+    pub fn dropped_count(&self) -> u64 {
+        self.shared.inner.lock().recover().n_dropped
+    }
+
+    pub fn take_dropped_count(&self) -> u64 {
+        mem::take(&mut self.shared.inner.lock().recover().n_dropped)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.inner.lock().recover().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.inner.lock().recover().queue.is_empty()
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.shared.inner.lock().recover().capacity
+    }
+
+    // Nothing more can be sent, because every sender and KeepAlive is gone or the channel
+    // was closed. Messages sent before may still be queued or delayed; see
+    // is_closed_and_empty.
+    pub fn is_disconnected(&self) -> bool {
+        let inner_guard = self.shared.inner.lock().recover();
+        let is_abandoned = inner_guard.n_senders == 0 && inner_guard.n_keep_alive == 0;
+        is_abandoned || inner_guard.closed
+    }
+
+    pub fn state(&self) -> ChannelState {
+        self.shared.inner.lock().recover().state()
+    }
+
+    // Like Sender::sender_count, only a snapshot.
+    pub fn sender_count(&self) -> usize {
+        self.shared.inner.lock().recover().n_senders
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.shared.inner.lock().recover().n_receivers
+    }
+
+    // Nothing is left to receive and nothing more can arrive, because every sender is
+    // gone or the channel was closed; recv would return Err(RecvError).
+    pub fn is_closed_and_empty(&self) -> bool {
+        let inner_guard = self.shared.inner.lock().recover();
+        inner_guard.no_more_arrivals() && inner_guard.queue.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.shared.inner.lock().recover().is_full()
+    }
+
+    pub fn queued_bytes(&self) -> usize {
+        self.shared.inner.lock().recover().queued_bytes()
+    }
+
+    pub fn shrink_to_fit(&self) {
+        self.shared.shrink_to(0);
+    }
+
+    pub fn purge(&self) -> usize {
+        self.shared.purge()
+    }
+
+    pub fn shrink_to(&self, min_capacity: usize) {
+        self.shared.shrink_to(min_capacity);
+    }
+
+    pub fn wake_all(&self) {
+        self.shared.wake_all();
+    }
+
+    // For event loops that cannot block: the callback runs on the sending thread, after
+    // the lock is released, whenever a send finds the queue empty. It is not called again
+    // until the queue has been emptied, so it should drain, e.g. with try_iter, which it
+    // may do itself. Messages that mature from send_after or tick count at the next send.
+    // There is one callback per channel; setting it replaces the previous one.
+    pub fn set_ready_callback(&self, on_ready: impl Fn() + Send + Sync + 'static) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        let old = inner_guard.on_ready.replace(Arc::new(on_ready));
+        drop(inner_guard);
+        drop(old);
+    }
+
+    pub fn clear_ready_callback(&self) {
+        let old = self.shared.inner.lock().recover().on_ready.take();
+        drop(old);
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.try_recv_with(pop_front)
+    }
+
+    pub fn try_recv_back(&self) -> Result<T, TryRecvError> {
+        self.try_recv_with(pop_back)
+    }
+
+    pub fn latest(&self) -> Option<(T, usize)> {
+        let (val, discarded) = self.try_recv_with(take_latest).ok()?;
+        Some((val, discarded.len()))
+    }
+
+    fn try_recv_with<R>(&self, pop: fn(&mut MsgQueue<T>) -> Option<R>) -> Result<R, TryRecvError> {
+        let mut inner_guard = self.shared.lock_recv();
+        if let Some(val) = pop(&mut inner_guard.queue) {
+            self.shared.notify_popped(&inner_guard);
+            return Ok(val);
+        }
+        if inner_guard.no_more_arrivals() {
+            return Err(TryRecvError::Disconnected);
+        }
+        Err(TryRecvError::Empty)
+    }
+
+    pub fn peek(&self) -> Result<T, TryRecvError>
+    where
+        T: Clone,
+    {
+        self.peek_with(T::clone)
+    }
+
+    // `f` runs with the channel lock held, so it must not touch this channel.
+    pub fn peek_with<R>(&self, f: impl FnOnce(&T) -> R) -> Result<R, TryRecvError> {
+        let inner_guard = self.shared.lock_recv();
+        if let Some(e) = inner_guard.queue.front() {
+            return Ok(f(&e.msg));
+        }
+        if inner_guard.no_more_arrivals() {
+            return Err(TryRecvError::Disconnected);
+        }
+        Err(TryRecvError::Empty)
+    }
+
+    pub fn drain_into(&self, buf: &mut Vec<T>) -> usize {
+        let mut inner_guard = self.shared.lock_recv();
+        let n = inner_guard.queue.len();
+        inner_guard.queue.drain_into(n, buf);
+        self.shared.notify_popped(&inner_guard);
+        n
+    }
+
+    pub fn drain(&self) -> Vec<T> {
+        let mut buf = Vec::new();
+        self.drain_into(&mut buf);
+        buf
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            rx: self,
+            done: false,
+        }
+    }
+
+    // Other receivers can take queued messages, so the lower bound holds only for a sole
+    // receiver, and only a closed channel has an upper one.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let inner_guard = self.shared.inner.lock().recover();
+        let len = inner_guard.queue.len();
+        let lower = if inner_guard.n_receivers == 1 { len } else { 0 };
+        let upper = inner_guard.no_more_arrivals().then_some(len);
+        (lower, upper)
+    }
+
+    // The backlog of a closed channel as an exact-size iterator. Fails, handing the
+    // receiver back, while messages can still arrive. The backlog is taken up front, so
+    // other receivers cannot shrink it afterwards.
+    pub fn into_exact_iter(self) -> Result<ExactIter<T>, Self> {
+        let mut inner_guard = self.shared.lock_recv();
+        if !inner_guard.no_more_arrivals() {
+            drop(inner_guard);
+            return Err(self);
+        }
+        let mut msgs = Vec::new();
+        let len = inner_guard.queue.len();
+        inner_guard.queue.drain_into(len, &mut msgs);
+        self.shared.notify_popped(&inner_guard);
+        Ok(ExactIter {
+            msgs: msgs.into_iter(),
+        })
+    }
+
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+
+    pub fn iter_for(&self, duration: Duration) -> IterFor<'_, T> {
+        IterFor {
+            deadline: Instant::now().checked_add(duration),
+            rx: self,
+            done: false,
+        }
+    }
+
+    pub fn iter_with_idle_timeout(&self, idle: Duration) -> IterWithIdleTimeout<'_, T> {
+        IterWithIdleTimeout {
+            rx: self,
+            idle,
+            done: false,
+        }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_deadline(deadline),
+            None => Ok(self.recv()?),
+        }
+    }
+
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        let mut inner_guard = self.shared.lock_recv();
+        loop {
+            if let Some(val) = pop_front(&mut inner_guard.queue) {
+                self.shared.notify_popped(&inner_guard);
+                return Ok(val);
+            }
+            if inner_guard.no_more_arrivals() {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            // recompute against the fixed deadline so spurious wakeups never extend the wait
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            inner_guard = self
+                .shared
+                .wait(&self.shared.available, inner_guard, Some(deadline));
+        }
+    }
+}
+
+fn pop_front<T>(queue: &mut MsgQueue<T>) -> Option<T> {
+    queue.pop_front().map(Envelope::into_msg)
+}
+
+fn pop_back<T>(queue: &mut MsgQueue<T>) -> Option<T> {
+    queue.pop_back().map(Envelope::into_msg)
+}
+
+// The older messages are handed back so the caller can drop them once the lock is released.
+fn take_latest<T>(queue: &mut MsgQueue<T>) -> Option<(T, VecDeque<Envelope<T>>)> {
+    let val = pop_back(queue)?;
+    Some((val, queue.take_entries()))
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_receivers += 1;
+        drop(inner_guard);
+        Self::from_shared(Arc::clone(&self.shared))
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.shared.fmt_state("Receiver", f)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "async")]
+        self.shared.leave_slot(&self.slot);
+        let mut inner_guard = self.shared.inner.lock().recover();
+        inner_guard.n_receivers -= 1;
+        if inner_guard.n_receivers == 0 {
+            // nobody can take these anymore; release them (and fail their SendHandles)
+            // outside the lock
+            let queue = inner_guard.queue.take_entries();
+            let delayed = mem::take(&mut inner_guard.delayed);
+            inner_guard.n_delayed_bytes = 0;
+            // blocked senders must wake up to report the disconnect
+            self.shared.notify_not_full();
+            self.shared.drained.notify_all();
+            self.shared.below_low.notify_all();
+            self.shared.no_receivers.notify_all();
+            drop(inner_guard);
+            drop(queue);
+            drop(delayed);
+        }
+    }
+}
+
+// Ends at the same point recv starts returning Err, so `for msg in rx` behaves as it did
+// when recv returned Option. A reset can reopen the channel, so it stays done once recv
+// fails.
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let val = self.rx.recv().ok();
+        self.done = val.is_none();
+        val
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.done {
+            true => (0, Some(0)),
+            false => self.rx.size_hint(),
+        }
+    }
+}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+// Ends, and stays done, as Iter does.
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let val = self.rx.recv().ok();
+        self.done = val.is_none();
+        val
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.done {
+            true => (0, Some(0)),
+            false => self.rx.size_hint(),
+        }
+    }
+}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> Iterator for ExactIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.msgs.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.msgs.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for ExactIter<T> {}
+
+impl<T> FusedIterator for ExactIter<T> {}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter {
+            rx: self,
+            done: false,
+        }
+    }
+}
+
+impl<T> IntoIter<T> {
+    pub fn into_inner(self) -> Receiver<T> {
+        self.rx
+    }
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl<T> Iterator for IterFor<'_, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let val = match self.deadline {
+            Some(deadline) => self.rx.recv_deadline(deadline).ok(),
+            None => self.rx.recv().ok(),
+        };
+        self.done = val.is_none();
+        val
+    }
+}
+
+impl<T> FusedIterator for IterFor<'_, T> {}
+
+impl<T> Iterator for IterWithIdleTimeout<'_, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let val = self.rx.recv_timeout(self.idle).ok();
+        self.done = val.is_none();
+        val
+    }
+}
+
+impl<T> FusedIterator for IterWithIdleTimeout<'_, T> {}
+
+impl SendHandle {
+    pub fn wait(self) -> Result<(), DeliveryError> {
+        let mut delivery_guard = self.tracked.delivery.lock().recover();
+        while *delivery_guard == Delivery::Pending {
+            delivery_guard = self.tracked.done.wait_on(delivery_guard);
+        }
+        match *delivery_guard {
+            Delivery::Delivered => Ok(()),
+            _ => Err(DeliveryError),
+        }
+    }
+
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<(), DeliveryTimeoutError> {
+        check_can_block();
+        let (delivery_guard, _) = self
+            .tracked
+            .done
+            .wait_timeout_while(
+                self.tracked.delivery.lock().recover(),
+                timeout,
+                |delivery| *delivery == Delivery::Pending,
+            )
+            .recover();
+        match *delivery_guard {
+            Delivery::Pending => Err(DeliveryTimeoutError::Timeout),
+            Delivery::Delivered => Ok(()),
+            Delivery::Dropped => Err(DeliveryTimeoutError::Dropped),
+        }
+    }
+
+    pub fn is_delivered(&self) -> bool {
+        *self.tracked.delivery.lock().recover() == Delivery::Delivered
+    }
+}
+
+impl<T> Permit<'_, T> {
+    pub fn send(self, msg: T) {
+        self.tx.shared.send_reserved(msg);
+        mem::forget(self);
+    }
+}
+
+impl<T> Drop for Permit<'_, T> {
+    fn drop(&mut self) {
+        self.tx.shared.release_reserved(1);
+    }
+}
+
+impl<T> PermitBatch<'_, T> {
+    // Enqueues up to the reserved number of messages under one lock, so no other sender
+    // can interleave. Returns how many were enqueued; any extra items are not consumed.
+    pub fn send_all<I: IntoIterator<Item = T>>(mut self, iter: I) -> usize {
+        // collected first so a panicking iterator never runs under the lock
+        let msgs: Vec<T> = iter.into_iter().take(self.n).collect();
+        let n_sent = msgs.len();
+        let shared = &self.tx.shared;
+        let msgs: Vec<T> = msgs
+            .into_iter()
+            .filter_map(|msg| shared.admit(msg))
+            .collect();
+        let mut inner_guard = shared.inner.lock().recover();
+        inner_guard.n_reserved -= n_sent;
+        self.n -= n_sent;
+        if msgs.len() < n_sent {
+            // slots of dropped messages are free again
+            shared.notify_popped(&inner_guard);
+        }
+        if inner_guard.check_accepting().is_ok() && !msgs.is_empty() {
+            for msg in msgs {
+                inner_guard.queue.push_back(shared.envelope(msg));
+            }
+            shared.notify_available_all();
+        }
+        let ready = inner_guard.take_ready();
+        drop(inner_guard);
+        call_ready(ready);
+        n_sent
+    }
+}
+
+impl<T> Drop for PermitBatch<'_, T> {
+    fn drop(&mut self) {
+        if self.n > 0 {
+            self.tx.shared.release_reserved(self.n);
+        }
+    }
+}
+
+impl<T> OwnedPermit<T> {
+    pub fn send(mut self, msg: T) -> Sender<T> {
+        let tx = self.tx.take().unwrap();
+        tx.shared.send_reserved(msg);
+        tx
+    }
+
+    // Gives the slot back without sending.
+    pub fn release(mut self) -> Sender<T> {
+        let tx = self.tx.take().unwrap();
+        tx.shared.release_reserved(1);
+        tx
+    }
+}
+
+impl<T> Drop for OwnedPermit<T> {
+    fn drop(&mut self) {
+        if let Some(tx) = self.tx.take() {
+            tx.shared.release_reserved(1);
+        }
+    }
+}
+
+impl<T> RecvGuard<T> {
+    pub fn commit(mut self) -> T {
+        self.msg.take().unwrap()
+    }
+}
+
+impl<T> Deref for RecvGuard<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        self.msg.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for RecvGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.msg.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for RecvGuard<T> {
+    fn drop(&mut self) {
+        if let Some(msg) = self.msg.take() {
+            let mut inner_guard = self.shared.inner.lock().recover();
+            if inner_guard.check_accepting().is_err() {
+                drop(inner_guard);
+                self.shared.discard(msg);
+                return;
+            }
+            inner_guard.queue.push_front(self.shared.envelope(msg));
+            self.shared.notify_available();
+            let ready = inner_guard.take_ready();
+            drop(inner_guard);
+            call_ready(ready);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::thread::{self, sleep};
+
+    #[test]
+    fn test_channel_pingpong() {
+        let (tx, rx) = new_channel();
+        tx.send("hello".to_string()).unwrap();
+        tx.send("world".to_string()).unwrap();
+        assert_eq!(rx.recv(), Ok("hello".to_string()));
+        assert_eq!(rx.recv(), Ok("world".to_string()));
+    }
+
+    #[test]
+    fn test_channel_iterator() {
+        let (tx, rx) = new_channel();
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+
+        for (i, val) in rx.into_iter().enumerate() {
+            match i {
+                0 => assert_eq!(val, 0),
+                1 => assert_eq!(val, 1),
+                2 => assert_eq!(val, 2),
+                3 => assert_eq!(val, 3),
+                4 => assert_eq!(val, 4),
+                _ => panic!("bad length"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_channel_concurrent() {
+        let (tx, rx) = new_channel();
+
+        let f = move || {
+            for i in 0..5 {
+                tx.send(format!("hello {}", i)).unwrap();
+                sleep(Duration::new(0, 10000000));
+            }
+        };
+        thread::spawn(f);
+
+        for i in 0..5 {
+            assert_eq!(rx.recv(), Ok(format!("hello {}", i)));
+        }
+    }
+
+    #[test]
+    fn test_channel_mpsc() {
+        let (tx, rx) = new_channel();
+        let tx1 = tx.clone();
+        let tx2 = tx.clone();
+        let tx3 = tx.clone();
+        drop(tx);
+
+        thread::spawn(move || {
+            for i in 0..5 {
+                tx1.send(format!("hello {} from {}", i, 1)).unwrap();
+                sleep(Duration::new(0, 10000000));
+            }
+        });
+        thread::spawn(move || {
+            for i in 0..5 {
+                tx2.send(format!("hello {} from {}", i, 2)).unwrap();
+                sleep(Duration::new(0, 20000000));
+            }
+        });
+        thread::spawn(move || {
+            for i in 0..5 {
+                tx3.send(format!("hello {} from {}", i, 3)).unwrap();
+                sleep(Duration::new(0, 30000000));
+            }
+        });
+
+        for _ in 0..15 {
+            assert!(rx.recv().is_ok());
+        }
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn test_channel_spmc() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+        let rx2 = rx.clone();
+        let rx3 = rx.clone();
+        drop(rx);
+
+        let rx1_handle = thread::spawn(move || {
+            let mut rx1_results: Vec<Option<String>> = vec![];
+            for _ in 0..5 {
+                rx1_results.push(rx1.recv().ok());
+                sleep(Duration::new(0, 10000000));
+            }
+            rx1_results
+        });
+        let rx2_handle = thread::spawn(move || {
+            let mut rx2_results: Vec<Option<String>> = vec![];
+            for _ in 0..5 {
+                rx2_results.push(rx2.recv().ok());
+                sleep(Duration::new(0, 10000000));
+            }
+            rx2_results
+        });
+        let rx3_handle = thread::spawn(move || {
+            let mut rx3_results: Vec<Option<String>> = vec![];
+            for _ in 0..5 {
+                rx3_results.push(rx3.recv().ok());
+                sleep(Duration::new(0, 10000000));
+            }
+            rx3_results
+        });
+
+        for i in 0..15 {
+            tx.send(format!("hello #{:02}", i)).unwrap();
+        }
+        let mut rx1_results = rx1_handle.join().unwrap();
+        let mut rx2_results = rx2_handle.join().unwrap();
+        let mut rx3_results = rx3_handle.join().unwrap();
+
+        let mut results: Vec<Option<String>> = vec![];
+        results.append(&mut rx1_results);
+        results.append(&mut rx2_results);
+        results.append(&mut rx3_results);
+        let mut sorted_results = results
+            .into_iter()
+            .map(|item| item.unwrap())
+            .collect::<Vec<_>>();
+        sorted_results.sort();
+
+        assert_eq!(
+            sorted_results,
+            vec![
+                "hello #00".to_string(),
+                "hello #01".to_string(),
+                "hello #02".to_string(),
+                "hello #03".to_string(),
+                "hello #04".to_string(),
+                "hello #05".to_string(),
+                "hello #06".to_string(),
+                "hello #07".to_string(),
+                "hello #08".to_string(),
+                "hello #09".to_string(),
+                "hello #10".to_string(),
+                "hello #11".to_string(),
+                "hello #12".to_string(),
+                "hello #13".to_string(),
+                "hello #14".to_string(),
+            ]
+        )
+    }
+
+    #[test]
+    fn test_channel_mpmc() {
+        let (tx, rx) = new_channel();
+        let tx1 = tx.clone();
+        let tx2 = tx.clone();
+        let tx3 = tx.clone();
+        drop(tx);
+
+        let rx1 = rx.clone();
+        let rx2 = rx.clone();
+        let rx3 = rx.clone();
 
         let rx1_handle = thread::spawn(move || {
             let mut rx1_results: Vec<Option<String>> = vec![];
             for _ in 0..5 {
-                rx1_results.push(rx1.recv());
+                rx1_results.push(rx1.recv().ok());
+                sleep(Duration::new(0, 10000000));
+            }
+            (rx1, rx1_results)
+        });
+        let rx2_handle = thread::spawn(move || {
+            let mut rx2_results: Vec<Option<String>> = vec![];
+            for _ in 0..5 {
+                rx2_results.push(rx2.recv().ok());
+                sleep(Duration::new(0, 10000000));
+            }
+            (rx2, rx2_results)
+        });
+        let rx3_handle = thread::spawn(move || {
+            let mut rx3_results: Vec<Option<String>> = vec![];
+            for _ in 0..5 {
+                rx3_results.push(rx3.recv().ok());
                 sleep(Duration::new(0, 10000000));
             }
-            rx1_results
+            (rx3, rx3_results)
+        });
+
+        let tx1_handle = thread::spawn(move || {
+            for i in 0..5 {
+                tx1.send(format!("hello #{} from tx1", i)).unwrap();
+                sleep(Duration::new(0, 11000000));
+            }
+        });
+        let tx2_handle = thread::spawn(move || {
+            for i in 0..5 {
+                tx2.send(format!("hello #{} from tx2", i)).unwrap();
+                sleep(Duration::new(0, 13000000));
+            }
+        });
+        let tx3_handle = thread::spawn(move || {
+            for i in 0..5 {
+                tx3.send(format!("hello #{} from tx3", i)).unwrap();
+                sleep(Duration::new(0, 15000000));
+            }
+        });
+        tx1_handle.join().unwrap();
+        tx2_handle.join().unwrap();
+        tx3_handle.join().unwrap();
+        let (rx1, mut rx1_results) = rx1_handle.join().unwrap();
+        let (rx2, mut rx2_results) = rx2_handle.join().unwrap();
+        let (rx3, mut rx3_results) = rx3_handle.join().unwrap();
+
+        let mut results: Vec<Option<String>> = vec![];
+        results.append(&mut rx1_results);
+        results.append(&mut rx2_results);
+        results.append(&mut rx3_results);
+        let mut sorted_results = results
+            .into_iter()
+            .map(|item| item.unwrap())
+            .collect::<Vec<_>>();
+        sorted_results.sort();
+
+        assert_eq!(
+            sorted_results,
+            vec![
+                "hello #0 from tx1".to_string(),
+                "hello #0 from tx2".to_string(),
+                "hello #0 from tx3".to_string(),
+                "hello #1 from tx1".to_string(),
+                "hello #1 from tx2".to_string(),
+                "hello #1 from tx3".to_string(),
+                "hello #2 from tx1".to_string(),
+                "hello #2 from tx2".to_string(),
+                "hello #2 from tx3".to_string(),
+                "hello #3 from tx1".to_string(),
+                "hello #3 from tx2".to_string(),
+                "hello #3 from tx3".to_string(),
+                "hello #4 from tx1".to_string(),
+                "hello #4 from tx2".to_string(),
+                "hello #4 from tx3".to_string(),
+            ]
+        );
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(rx1.recv(), Err(RecvError));
+        assert_eq!(rx2.recv(), Err(RecvError));
+        assert_eq!(rx3.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_try_recv_empty_open() {
+        let (tx, rx) = new_channel::<i32>();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_channel_try_recv_closed_empty() {
+        let (tx, rx) = new_channel::<i32>();
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_try_recv_closed_pending() {
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        drop(tx);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_try_recv_concurrent_with_recv() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+
+        let rx1_handle = thread::spawn(move || {
+            let mut rx1_results = vec![];
+            while let Ok(val) = rx1.recv() {
+                rx1_results.push(val);
+            }
+            rx1_results
+        });
+        let tx_handle = thread::spawn(move || {
+            for i in 0..1000 {
+                tx.send(i).unwrap();
+            }
+        });
+
+        let mut results = vec![];
+        loop {
+            match rx.try_recv() {
+                Ok(val) => results.push(val),
+                Err(TryRecvError::Empty) => thread::yield_now(),
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        tx_handle.join().unwrap();
+        results.append(&mut rx1_handle.join().unwrap());
+        results.sort();
+        assert_eq!(results, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_channel_recv_timeout_zero() {
+        let (tx, rx) = new_channel();
+        assert_eq!(
+            rx.recv_timeout(Duration::ZERO),
+            Err(RecvTimeoutError::Timeout)
+        );
+        tx.send(1).unwrap();
+        assert_eq!(rx.recv_timeout(Duration::ZERO), Ok(1));
+        drop(tx);
+        assert_eq!(
+            rx.recv_timeout(Duration::ZERO),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_channel_recv_timeout_sender_after_timeout() {
+        let (tx, rx) = new_channel();
+        let tx_handle = thread::spawn(move || {
+            sleep(Duration::from_millis(200));
+            tx.send(1).unwrap();
+            tx
+        });
+        let start = Instant::now();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(100)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        let _tx = tx_handle.join().unwrap();
+        assert_eq!(rx.recv_timeout(Duration::ZERO), Ok(1));
+    }
+
+    #[test]
+    fn test_channel_recv_timeout_sender_before_timeout() {
+        let (tx, rx) = new_channel();
+        let tx_handle = thread::spawn(move || {
+            sleep(Duration::from_millis(50));
+            tx.send(1).unwrap();
+            tx
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_millis(500)), Ok(1));
+        let _tx = tx_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_channel_recv_timeout_disconnect_while_waiting() {
+        let (tx, rx) = new_channel::<i32>();
+        thread::spawn(move || {
+            sleep(Duration::from_millis(50));
+            drop(tx);
+        });
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_channel_recv_deadline_past() {
+        let (tx, rx) = new_channel();
+        let past = Instant::now();
+        sleep(Duration::from_millis(1));
+        assert_eq!(rx.recv_deadline(past), Err(RecvTimeoutError::Timeout));
+        tx.send(1).unwrap();
+        assert_eq!(rx.recv_deadline(past), Ok(1));
+        drop(tx);
+        assert_eq!(rx.recv_deadline(past), Err(RecvTimeoutError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_recv_deadline_wait() {
+        let (tx, rx) = new_channel();
+        let deadline = Instant::now() + Duration::from_millis(100);
+        assert_eq!(rx.recv_deadline(deadline), Err(RecvTimeoutError::Timeout));
+        assert!(Instant::now() >= deadline);
+
+        let tx_handle = thread::spawn(move || {
+            sleep(Duration::from_millis(50));
+            tx.send(1).unwrap();
+            tx
+        });
+        let deadline = Instant::now() + Duration::from_millis(500);
+        assert_eq!(rx.recv_deadline(deadline), Ok(1));
+        let _tx = tx_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_channel_recv_many() {
+        let (tx, rx) = new_channel();
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.recv_many(3), Ok(vec![0, 1, 2]));
+        assert_eq!(rx.recv_many(3), Ok(vec![3, 4]));
+        tx.send(5).unwrap();
+        assert_eq!(rx.recv_many(0), Ok(vec![5]));
+        drop(tx);
+        assert_eq!(rx.recv_many(3), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_recv_many_does_not_wait_for_full_batch() {
+        let (tx, rx) = new_channel();
+        let tx_handle = thread::spawn(move || {
+            sleep(Duration::from_millis(50));
+            tx.send(1).unwrap();
+            sleep(Duration::from_millis(500));
+            tx.send(2).unwrap();
+        });
+        let start = Instant::now();
+        assert_eq!(rx.recv_many(10), Ok(vec![1]));
+        assert!(start.elapsed() < Duration::from_millis(500));
+        tx_handle.join().unwrap();
+        assert_eq!(rx.recv_many(10), Ok(vec![2]));
+        assert_eq!(rx.recv_many(10), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_recv_many_competing_receivers() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+        let rx2 = rx.clone();
+        drop(rx);
+
+        let rx1_handle = thread::spawn(move || {
+            let mut rx1_results = vec![];
+            while let Ok(mut batch) = rx1.recv_many(7) {
+                assert!(!batch.is_empty() && batch.len() <= 7);
+                rx1_results.append(&mut batch);
+            }
+            rx1_results
+        });
+        let rx2_handle = thread::spawn(move || {
+            let mut rx2_results = vec![];
+            while let Ok(mut batch) = rx2.recv_many(3) {
+                assert!(!batch.is_empty() && batch.len() <= 3);
+                rx2_results.append(&mut batch);
+            }
+            rx2_results
+        });
+
+        for i in 0..1000 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+
+        let mut results = rx1_handle.join().unwrap();
+        results.append(&mut rx2_handle.join().unwrap());
+        results.sort();
+        assert_eq!(results, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_channel_drain_into() {
+        let (tx, rx) = new_channel();
+        let mut buf = Vec::with_capacity(8);
+        assert_eq!(rx.drain_into(&mut buf), 0);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.drain_into(&mut buf), 5);
+        assert_eq!(buf, vec![0, 1, 2, 3, 4]);
+
+        buf.clear();
+        tx.send(5).unwrap();
+        assert_eq!(rx.drain_into(&mut buf), 1);
+        assert_eq!(buf, vec![5]);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        drop(tx);
+        assert_eq!(rx.drain_into(&mut buf), 0);
+        assert_eq!(buf, vec![5]);
+    }
+
+    #[test]
+    fn test_channel_drain() {
+        let (tx, rx) = new_channel();
+        assert_eq!(rx.drain(), Vec::<i32>::new());
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        drop(tx);
+        assert_eq!(rx.drain(), vec![1, 2]);
+        assert_eq!(rx.drain(), Vec::<i32>::new());
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_recv_matching() {
+        let (tx, rx) = new_channel();
+        for i in 0..10 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.recv_matching(|val| val % 3 == 2), Ok(2));
+        assert_eq!(rx.recv_matching(|val| val % 3 == 2), Ok(5));
+        assert_eq!(rx.recv(), Ok(0));
+        assert_eq!(rx.recv_matching(|val| val % 3 == 2), Ok(8));
+        drop(tx);
+        assert_eq!(rx.recv_matching(|val| val % 3 == 2), Err(RecvError));
+        assert_eq!(rx.drain(), vec![1, 3, 4, 6, 7, 9]);
+    }
+
+    #[test]
+    fn test_channel_recv_matching_blocks_for_match() {
+        let (tx, rx) = new_channel();
+        let tx_handle = thread::spawn(move || {
+            for i in 0..5 {
+                tx.send(format!("data {}", i)).unwrap();
+                sleep(Duration::from_millis(10));
+            }
+            tx.send("reply".to_string()).unwrap();
+            tx.send("data 5".to_string()).unwrap();
+        });
+        assert_eq!(
+            rx.recv_matching(|msg| msg.starts_with("reply")),
+            Ok("reply".to_string())
+        );
+        tx_handle.join().unwrap();
+        let rest = rx.into_iter().collect::<Vec<_>>();
+        assert_eq!(
+            rest,
+            (0..6).map(|i| format!("data {}", i)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_channel_recv_matching_does_not_starve_recv() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+
+        let rx1_handle = thread::spawn(move || rx1.recv_matching(|val| *val < 0));
+        sleep(Duration::from_millis(20));
+
+        let rx_handle = thread::spawn(move || {
+            let mut rx_results = vec![];
+            for _ in 0..100 {
+                rx_results.push(rx.recv().unwrap());
+            }
+            rx_results
+        });
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx_handle.join().unwrap(), (0..100).collect::<Vec<_>>());
+        tx.send(-1).unwrap();
+        assert_eq!(rx1_handle.join().unwrap(), Ok(-1));
+    }
+
+    #[test]
+    fn test_channel_recv_or_default() {
+        let (tx, rx) = new_channel();
+        tx.send(0).unwrap();
+        tx.send(7).unwrap();
+        assert_eq!(rx.recv_or_default(), (0, false));
+        drop(tx);
+        assert_eq!(rx.recv_or_default(), (7, false));
+        assert_eq!(rx.recv_or_default(), (0, true));
+        assert_eq!(rx.recv_or_default(), (0, true));
+    }
+
+    #[test]
+    fn test_channel_recv_or_default_blocks() {
+        let (tx, rx) = new_channel::<String>();
+        thread::spawn(move || {
+            sleep(Duration::from_millis(20));
+            tx.send("hello".to_string()).unwrap();
+        });
+        assert_eq!(rx.recv_or_default(), ("hello".to_string(), false));
+        assert_eq!(rx.recv_or_default(), (String::new(), true));
+    }
+
+    #[test]
+    fn test_channel_recv_result() {
+        fn sum_two(rx: &mut Receiver<i32>) -> Result<i32, RecvTimeoutError> {
+            let first = rx.recv()?;
+            Ok(first + rx.recv_timeout(Duration::from_millis(10))?)
+        }
+        let (tx, mut rx) = new_channel();
+        for i in 1..=5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(sum_two(&mut rx), Ok(3));
+        drop(tx);
+        #[allow(deprecated)]
+        let val = rx.recv_opt();
+        assert_eq!(val, Some(3));
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(sum_two(&mut rx), Err(RecvTimeoutError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_recv_exact() {
+        let (tx, rx) = new_channel();
+        for i in 0..7 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.recv_exact(0), Ok(vec![]));
+        assert_eq!(rx.recv_exact(3), Ok(vec![0, 1, 2]));
+        assert_eq!(rx.recv_exact(3), Ok(vec![3, 4, 5]));
+        drop(tx);
+        assert_eq!(rx.recv_exact(3), Err(RecvExactError(vec![6])));
+        assert_eq!(rx.recv_exact(3), Err(RecvExactError(vec![])));
+    }
+
+    #[test]
+    fn test_channel_recv_exact_waits_for_group() {
+        let (tx, rx) = new_channel();
+        let tx_handle = thread::spawn(move || {
+            for part in ["header", "payload", "trailer"] {
+                tx.send(part).unwrap();
+                sleep(Duration::from_millis(10));
+            }
+        });
+        assert_eq!(rx.recv_exact(3), Ok(vec!["header", "payload", "trailer"]));
+        tx_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_channel_recv_exact_groups_not_torn() {
+        let (tx, rx) = new_channel();
+        let handles = (0..3)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || {
+                    let mut groups = vec![];
+                    while let Ok(group) = rx.recv_exact(3) {
+                        groups.push(group);
+                    }
+                    groups
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(rx);
+
+        for i in 0..100 {
+            tx.send(i * 3).unwrap();
+            tx.send(i * 3 + 1).unwrap();
+            tx.send(i * 3 + 2).unwrap();
+        }
+        drop(tx);
+
+        let mut n_groups = 0;
+        for handle in handles {
+            for group in handle.join().unwrap() {
+                assert_eq!(group, vec![group[0], group[0] + 1, group[0] + 2]);
+                assert_eq!(group[0] % 3, 0);
+                n_groups += 1;
+            }
+        }
+        assert_eq!(n_groups, 100);
+    }
+
+    #[test]
+    fn test_channel_recv_cancellable() {
+        let (tx, rx) = new_channel();
+        let cancel = AtomicBool::new(false);
+        tx.send(1).unwrap();
+        assert_eq!(rx.recv_cancellable(&cancel), Ok(1));
+        cancel.store(true, Ordering::Release);
+        tx.send(2).unwrap();
+        assert_eq!(
+            rx.recv_cancellable(&cancel),
+            Err(RecvCancelError::Cancelled)
+        );
+        assert_eq!(rx.recv(), Ok(2));
+        cancel.store(false, Ordering::Release);
+        drop(tx);
+        assert_eq!(
+            rx.recv_cancellable(&cancel),
+            Err(RecvCancelError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_channel_recv_cancellable_while_parked() {
+        let (_tx, rx) = new_channel::<i32>();
+        let waker = rx.clone();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel_clone = Arc::clone(&cancel);
+
+        let rx_handle = thread::spawn(move || {
+            let result = rx.recv_cancellable(&cancel_clone);
+            (result, Instant::now())
+        });
+        sleep(Duration::from_millis(50));
+        let cancelled_at = Instant::now();
+        cancel.store(true, Ordering::Release);
+        waker.wake_all();
+
+        let (result, returned_at) = rx_handle.join().unwrap();
+        assert_eq!(result, Err(RecvCancelError::Cancelled));
+        assert!(returned_at.duration_since(cancelled_at) < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_channel_recv_back() {
+        let (tx, rx) = new_channel();
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.recv_back(), Ok(4));
+        assert_eq!(rx.recv(), Ok(0));
+        assert_eq!(rx.try_recv_back(), Ok(3));
+        assert_eq!(rx.try_recv(), Ok(1));
+        drop(tx);
+        assert_eq!(rx.recv_back(), Ok(2));
+        assert_eq!(rx.recv_back(), Err(RecvError));
+        assert_eq!(rx.try_recv_back(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_try_recv_back_empty_open() {
+        let (_tx, rx) = new_channel::<i32>();
+        assert_eq!(rx.try_recv_back(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_channel_recv_back_interleaved_with_recv() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+
+        let rx1_handle = thread::spawn(move || {
+            let mut rx1_results = vec![];
+            while let Ok(val) = rx1.recv_back() {
+                rx1_results.push(val);
+            }
+            rx1_results
+        });
+        let tx_handle = thread::spawn(move || {
+            for i in 0..1000 {
+                tx.send(i).unwrap();
+            }
+        });
+
+        let mut results = vec![];
+        while let Ok(val) = rx.recv() {
+            results.push(val);
+        }
+        tx_handle.join().unwrap();
+        results.append(&mut rx1_handle.join().unwrap());
+        results.sort();
+        assert_eq!(results, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_channel_peek() {
+        let (tx, rx) = new_channel();
+        assert_eq!(rx.peek(), Err(TryRecvError::Empty));
+        tx.send("hello".to_string()).unwrap();
+        tx.send("world".to_string()).unwrap();
+        assert_eq!(rx.peek(), Ok("hello".to_string()));
+        assert_eq!(rx.peek(), Ok("hello".to_string()));
+        assert_eq!(rx.recv(), Ok("hello".to_string()));
+        drop(tx);
+        assert_eq!(rx.peek(), Ok("world".to_string()));
+        assert_eq!(rx.recv(), Ok("world".to_string()));
+        assert_eq!(rx.peek(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_peek_with() {
+        struct Routed {
+            key: u32,
+            _payload: Vec<u8>,
+        }
+        let (tx, rx) = new_channel();
+        assert_eq!(
+            rx.peek_with(|msg: &Routed| msg.key),
+            Err(TryRecvError::Empty)
+        );
+        tx.send(Routed {
+            key: 7,
+            _payload: vec![1, 2, 3],
+        })
+        .unwrap();
+        assert_eq!(rx.peek_with(|msg| msg.key), Ok(7));
+        assert_eq!(rx.recv().map(|msg| msg.key), Ok(7));
+        drop(tx);
+        assert_eq!(rx.peek_with(|msg| msg.key), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_recv_guarded_commit() {
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        let mut guard = rx.recv_guarded().unwrap();
+        assert_eq!(*guard, 1);
+        *guard += 10;
+        assert_eq!(guard.commit(), 11);
+        assert_eq!(rx.recv(), Ok(2));
+        drop(tx);
+        assert!(rx.recv_guarded().is_err());
+    }
+
+    #[test]
+    fn test_channel_recv_guarded_requeue_on_drop() {
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        drop(tx);
+        let guard = rx.recv_guarded().unwrap();
+        assert_eq!(*guard, 1);
+        drop(guard);
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_recv_guarded_requeue_on_panic() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+        tx.send("job".to_string()).unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let guard = rx.recv_guarded().unwrap();
+            assert_eq!(*guard, "job");
+            panic!("worker crashed");
+        }));
+        assert!(result.is_err());
+
+        let rx1_handle = thread::spawn(move || rx1.recv_guarded().unwrap().commit());
+        assert_eq!(rx1_handle.join().unwrap(), "job".to_string());
+    }
+
+    #[test]
+    fn test_channel_recv_guarded_requeue_wakes_waiter() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+        tx.send(1).unwrap();
+        let guard = rx.recv_guarded().unwrap();
+
+        let rx1_handle = thread::spawn(move || rx1.recv());
+        sleep(Duration::from_millis(20));
+        drop(guard);
+        assert_eq!(rx1_handle.join().unwrap(), Ok(1));
+    }
+
+    #[test]
+    fn test_channel_recv_guarded_discard_when_gone() {
+        let dropped = Arc::new(Mutex::new(vec![]));
+        let new_channel = || {
+            let dropped = Arc::clone(&dropped);
+            ChannelBuilder::new()
+                .on_drop(move |msg| dropped.lock().unwrap().push(msg))
+                .build()
+                .unwrap()
+        };
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        let guard = rx.recv_guarded().unwrap();
+        rx.close();
+        drop(guard);
+        assert_eq!(*dropped.lock().unwrap(), vec![1]);
+        assert_eq!(rx.recv(), Ok(2));
+
+        let (tx, rx) = new_channel();
+        tx.send(3).unwrap();
+        let guard = rx.recv_guarded().unwrap();
+        drop(rx);
+        drop(guard);
+        assert_eq!(tx.len(), 0);
+        assert_eq!(*dropped.lock().unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_channel_iter_for() {
+        let (tx, rx) = new_channel();
+        let tx_handle = thread::spawn(move || {
+            for i in 0.. {
+                tx.send(i).unwrap();
+                sleep(Duration::from_millis(10));
+                if i == 30 {
+                    return tx;
+                }
+            }
+            unreachable!()
+        });
+        let start = Instant::now();
+        let results = rx.iter_for(Duration::from_millis(100)).collect::<Vec<_>>();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100));
+        assert!(elapsed < Duration::from_millis(900));
+        assert!(!results.is_empty() && results.len() < 30);
+        assert_eq!(results, (0..results.len() as i32).collect::<Vec<_>>());
+        let _tx = tx_handle.join().unwrap();
+        assert_eq!(rx.recv(), Ok(results.len() as i32));
+    }
+
+    #[test]
+    fn test_channel_iter_with_idle_timeout() {
+        let (tx, rx) = new_channel();
+        let tx_handle = thread::spawn(move || {
+            for i in 0..5 {
+                tx.send(i).unwrap();
+                sleep(Duration::from_millis(10));
+            }
+            sleep(Duration::from_millis(300));
+            tx.send(5).unwrap();
+        });
+
+        let mut results = vec![];
+        for val in rx.iter_with_idle_timeout(Duration::from_millis(100)) {
+            results.push(val);
+        }
+        assert_eq!(results, vec![0, 1, 2, 3, 4]);
+        tx_handle.join().unwrap();
+        assert_eq!(rx.recv(), Ok(5));
+    }
+
+    #[test]
+    fn test_channel_timed_iters_end_on_close() {
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        drop(tx);
+        let start = Instant::now();
+        assert_eq!(
+            rx.iter_for(Duration::from_secs(10)).collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert_eq!(
+            rx.iter_with_idle_timeout(Duration::from_secs(10)).next(),
+            None
+        );
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_channel_try_iter() {
+        let (tx, rx) = new_channel();
+        assert_eq!(rx.try_iter().next(), None);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(rx.try_iter().next(), None);
+        tx.send(5).unwrap();
+        drop(tx);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![5]);
+    }
+
+    #[test]
+    fn test_channel_try_iter_concurrent_sends_not_lost() {
+        let (tx, rx) = new_channel();
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+        let tx_handle = thread::spawn(move || {
+            for i in 100..1000 {
+                tx.send(i).unwrap();
+            }
+        });
+
+        let mut results = rx.try_iter().collect::<Vec<_>>();
+        assert!(results.len() >= 100);
+        tx_handle.join().unwrap();
+        results.extend(rx.iter());
+        assert_eq!(results, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_channel_latest() {
+        let (tx, rx) = new_channel();
+        assert_eq!(rx.latest(), None);
+        tx.send(1).unwrap();
+        assert_eq!(rx.latest(), Some((1, 0)));
+        for i in 2..6 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.latest(), Some((5, 3)));
+        assert_eq!(rx.latest(), None);
+        drop(tx);
+        assert_eq!(rx.latest(), None);
+    }
+
+    #[test]
+    fn test_channel_recv_latest() {
+        let (tx, rx) = new_channel();
+        let tx_handle = thread::spawn(move || {
+            sleep(Duration::from_millis(20));
+            tx.send(1).unwrap();
+            tx
+        });
+        assert_eq!(rx.recv_latest(), Ok((1, 0)));
+        let tx = tx_handle.join().unwrap();
+        for i in 2..12 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        assert_eq!(rx.recv_latest(), Ok((11, 9)));
+        assert_eq!(rx.recv_latest(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_latest_drops_outside_lock() {
+        // counts drops that find the channel unlocked
+        struct Probe {
+            tx: Option<Sender<Probe>>,
+            n_unlocked: Arc<AtomicUsize>,
+        }
+        impl Drop for Probe {
+            fn drop(&mut self) {
+                if let Some(tx) = &self.tx {
+                    if tx.shared.inner.try_lock().is_ok() {
+                        self.n_unlocked.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            }
+        }
+        let n_unlocked = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = new_channel();
+        let probe = |tx| Probe {
+            tx,
+            n_unlocked: Arc::clone(&n_unlocked),
+        };
+        for _ in 0..3 {
+            tx.send(probe(Some(tx.clone()))).unwrap();
+        }
+        tx.send(probe(None)).unwrap();
+        let (_, n_discarded) = rx.recv_latest().unwrap();
+        assert_eq!(n_discarded, 3);
+        assert_eq!(n_unlocked.load(Ordering::SeqCst), 3);
+
+        tx.send(probe(Some(tx.clone()))).unwrap();
+        tx.send(probe(None)).unwrap();
+        assert_eq!(rx.latest().map(|(_, n)| n), Some(1));
+        assert_eq!(n_unlocked.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_channel_latest_competing_receivers() {
+        let (tx, rx) = new_channel();
+        let handles = (0..3)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || {
+                    let (mut n_received, mut n_discarded) = (0, 0);
+                    while let Ok((_, n)) = rx.recv_latest() {
+                        n_received += 1;
+                        n_discarded += n;
+                    }
+                    (n_received, n_discarded)
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(rx);
+        for i in 0..10000 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+
+        let total = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .fold(0, |acc, (n_received, n_discarded)| {
+                acc + n_received + n_discarded
+            });
+        assert_eq!(total, 10000);
+    }
+
+    #[test]
+    fn test_channel_send_without_receivers() {
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        drop(rx);
+        assert_eq!(tx.send(2), Err(SendError::Disconnected(2)));
+    }
+
+    #[test]
+    fn test_channel_send_after_last_receiver_dropped_with_sender_clone() {
+        let (tx, rx) = new_channel();
+        let tx1 = tx.clone();
+        drop(tx);
+        let rx1 = rx.clone();
+        drop(rx);
+        tx1.send("still listening").unwrap();
+        drop(rx1);
+        assert_eq!(tx1.send("gone"), Err(SendError::Disconnected("gone")));
+    }
+
+    #[test]
+    fn test_channel_clone_receiver_after_some_dropped() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+        let rx2 = rx.clone();
+        drop(rx);
+        drop(rx1);
+        let rx3 = rx2.clone();
+        drop(rx2);
+        tx.send(1).unwrap();
+        assert_eq!(rx3.recv(), Ok(1));
+        drop(rx3);
+        assert_eq!(tx.send(2), Err(SendError::Disconnected(2)));
+    }
+
+    #[test]
+    fn test_channel_send_error_debug() {
+        struct NotDebug;
+        let (tx, rx) = new_channel();
+        drop(rx);
+        let err = tx.send(NotDebug).unwrap_err();
+        assert_eq!(format!("{:?}", err), "Disconnected(..)");
+    }
+
+    #[test]
+    fn test_channel_send_all() {
+        let (tx, rx) = new_channel();
+        assert_eq!(tx.send_all(0..5), 5);
+        assert_eq!(tx.send_all(Vec::new()), 0);
+        tx.send(5).unwrap();
+        assert_eq!(tx.send_all(vec![6, 7]), 2);
+        drop(tx);
+        assert_eq!(
+            rx.into_iter().collect::<Vec<_>>(),
+            (0..8).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_channel_send_all_without_receivers() {
+        let (tx, rx) = new_channel();
+        drop(rx);
+        assert_eq!(tx.send_all(0..5), 0);
+    }
+
+    #[test]
+    fn test_channel_send_all_wakes_all_receivers() {
+        let (tx, rx) = new_channel();
+        let handles = (0..4)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.recv())
+            })
+            .collect::<Vec<_>>();
+        sleep(Duration::from_millis(20));
+        assert_eq!(tx.send_all(0..4), 4);
+        let mut results = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap().unwrap())
+            .collect::<Vec<_>>();
+        results.sort();
+        assert_eq!(results, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_channel_survives_poisoned_lock() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+        tx.send(1).unwrap();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            rx.recv_matching(|_| panic!("bad predicate"))
+        }));
+        assert!(result.is_err());
+        assert!(tx.shared.inner.is_poisoned());
+        tx.send(2).unwrap();
+        assert_eq!(rx1.recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Ok(2));
+        drop(rx);
+        drop(tx);
+        assert_eq!(rx1.recv(), Err(RecvError));
+
+        let (tx, rx) = new_channel_with_watermarks(2, 0, |mark| {
+            assert_eq!(mark, Watermark::Low, "bad callback");
+        });
+        tx.send(1).unwrap();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| tx.send(2)));
+        assert!(result.is_err());
+        assert_eq!(rx.len(), 2);
+        assert_eq!(rx.drain(), vec![1, 2]);
+        tx.send(3).unwrap();
+        assert_eq!(rx.recv(), Ok(3));
+    }
+
+    #[test]
+    fn test_channel_send_all_panicking_iterator() {
+        let (tx, rx) = new_channel();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            tx.send_all((0..10).map(|i| if i == 3 { panic!("bad item") } else { i }))
+        }));
+        assert!(result.is_err());
+        tx.send(10).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![0, 1, 2, 10]);
+    }
+
+    #[test]
+    fn test_channel_send_front() {
+        let (tx, rx) = new_channel();
+        tx.send_all(0..3);
+        tx.send_front(10).unwrap();
+        tx.send_front(11).unwrap();
+        drop(tx);
+        assert_eq!(rx.into_iter().collect::<Vec<_>>(), vec![11, 10, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_channel_send_front_without_receivers() {
+        let (tx, rx) = new_channel();
+        drop(rx);
+        assert_eq!(tx.send_front(1), Err(SendError::Disconnected(1)));
+    }
+
+    #[test]
+    fn test_channel_send_front_mid_iteration() {
+        let (tx, rx) = new_channel();
+        tx.send_all(0..5);
+        let mut urgent_tx = Some(tx);
+
+        let mut results = vec![];
+        for val in rx {
+            if val == 1 {
+                // dropping the only sender afterwards lets the loop end once drained
+                let tx = urgent_tx.take().unwrap();
+                tx.send_front(100).unwrap();
+            }
+            results.push(val);
+        }
+        assert_eq!(results, vec![0, 1, 100, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_channel_send_after() {
+        let (tx, rx) = new_channel();
+        tx.send_after(1, Duration::from_millis(100)).unwrap();
+        tx.send(0).unwrap();
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        let start = Instant::now();
+        assert_eq!(rx.recv(), Ok(1));
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn test_channel_send_after_max_delay() {
+        let (tx, rx) = new_channel();
+        tx.send_after(1, Duration::MAX).unwrap();
+        tx.send_after(2, Duration::from_millis(20)).unwrap();
+        tx.send_after(3, Duration::MAX).unwrap();
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        // still pending, so the channel stays open
+        drop(tx);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(20)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert_eq!(rx.len(), 0);
+    }
+
+    #[test]
+    fn test_channel_send_after_deadline_order() {
+        let (tx, rx) = new_channel();
+        tx.send_after(3, Duration::from_millis(90)).unwrap();
+        tx.send_after(1, Duration::from_millis(30)).unwrap();
+        tx.send_after(2, Duration::from_millis(60)).unwrap();
+        tx.send_after(4, Duration::from_millis(90)).unwrap();
+        sleep(Duration::from_millis(120));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_channel_send_after_wakes_parked_receiver() {
+        let (tx, rx) = new_channel();
+        let rx_handle = thread::spawn(move || {
+            let val = rx.recv();
+            (val, Instant::now())
+        });
+        sleep(Duration::from_millis(20));
+        let sent_at = Instant::now();
+        tx.send_after(1, Duration::from_millis(50)).unwrap();
+        let (val, received_at) = rx_handle.join().unwrap();
+        assert_eq!(val, Ok(1));
+        let waited = received_at.duration_since(sent_at);
+        assert!(waited >= Duration::from_millis(50));
+        assert!(waited < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_channel_send_after_pending_at_close() {
+        let (tx, rx) = new_channel();
+        tx.send_after(2, Duration::from_millis(50)).unwrap();
+        tx.send(1).unwrap();
+        drop(tx);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_send_after_without_receivers() {
+        let (tx, rx) = new_channel();
+        drop(rx);
+        assert_eq!(
+            tx.send_after(1, Duration::from_millis(10)),
+            Err(SendError::Disconnected(1))
+        );
+    }
+
+    #[test]
+    fn test_channel_bounded_send_blocks_when_full() {
+        let (tx, rx) = new_bounded(2);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        let tx_handle = thread::spawn(move || {
+            tx.send(3).unwrap();
+            Instant::now()
+        });
+        sleep(Duration::from_millis(50));
+        let popped_at = Instant::now();
+        assert_eq!(rx.recv(), Ok(1));
+        assert!(tx_handle.join().unwrap() >= popped_at);
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Ok(3));
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_send_timeout_completes_when_consumer_pops() {
+        let (tx, rx) = new_bounded(2);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        let tx_handle = thread::spawn(move || {
+            let result = tx.send_timeout(3, Duration::from_millis(500));
+            (tx, result)
+        });
+        sleep(Duration::from_millis(50));
+        assert_eq!(rx.recv(), Ok(1));
+        let (_tx, result) = tx_handle.join().unwrap();
+        assert_eq!(result, Ok(()));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_channel_send_timeout_times_out() {
+        let (tx, rx) = new_bounded(1);
+        tx.send(1).unwrap();
+        let start = Instant::now();
+        assert_eq!(
+            tx.send_timeout(2, Duration::from_millis(50)),
+            Err(SendTimeoutError::Timeout(2))
+        );
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(
+            tx.send_timeout(3, Duration::ZERO),
+            Err(SendTimeoutError::Timeout(3))
+        );
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(tx.send_timeout(4, Duration::ZERO), Ok(()));
+    }
+
+    #[test]
+    fn test_channel_send_timeout_disconnected_while_blocked() {
+        let (tx, rx) = new_bounded(1);
+        tx.send(1).unwrap();
+        let tx_handle = thread::spawn(move || tx.send_timeout(2, Duration::from_secs(5)));
+        sleep(Duration::from_millis(50));
+        drop(rx);
+        assert_eq!(
+            tx_handle.join().unwrap(),
+            Err(SendTimeoutError::Disconnected(2))
+        );
+    }
+
+    #[test]
+    fn test_channel_send_timeout_unbounded_never_times_out() {
+        let (tx, rx) = new_channel();
+        for i in 0..100 {
+            assert_eq!(tx.send_timeout(i, Duration::ZERO), Ok(()));
+        }
+        assert_eq!(rx.drain().len(), 100);
+    }
+
+    #[test]
+    fn test_channel_bounded_send_all_waits_for_room() {
+        let (tx, rx) = new_bounded(3);
+        let tx_handle = thread::spawn(move || tx.send_all(0..100));
+        let results = rx.into_iter().collect::<Vec<_>>();
+        assert_eq!(tx_handle.join().unwrap(), 100);
+        assert_eq!(results, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_channel_send_timeout_error_debug() {
+        struct NotDebug;
+        let (tx, rx) = new_bounded(1);
+        tx.send(NotDebug).unwrap();
+        let err = tx.send_timeout(NotDebug, Duration::ZERO).unwrap_err();
+        assert_eq!(format!("{:?}", err), "Timeout(..)");
+        drop(rx);
+        let err = tx.send_timeout(NotDebug, Duration::ZERO).unwrap_err();
+        assert_eq!(format!("{:?}", err), "Disconnected(..)");
+    }
+
+    #[test]
+    fn test_channel_try_send() {
+        let (tx, rx) = new_bounded(2);
+        assert_eq!(tx.try_send(1), Ok(()));
+        assert_eq!(tx.try_send(2), Ok(()));
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(tx.try_send(4), Ok(()));
+        drop(rx);
+        assert_eq!(tx.try_send(5), Err(TrySendError::Disconnected(5)));
+    }
+
+    #[test]
+    fn test_channel_try_send_unbounded() {
+        let (tx, rx) = new_channel();
+        for i in 0..100 {
+            assert_eq!(tx.try_send(i), Ok(()));
+        }
+        assert_eq!(rx.drain(), (0..100).collect::<Vec<_>>());
+        drop(rx);
+        assert_eq!(tx.try_send(100), Err(TrySendError::Disconnected(100)));
+    }
+
+    #[test]
+    fn test_channel_try_send_racing_consumer() {
+        let (tx, rx) = new_bounded(4);
+        let tx_handle = thread::spawn(move || {
+            let mut rejected = vec![];
+            for i in 0..10000 {
+                match tx.try_send(i) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(msg)) => rejected.push(msg),
+                    Err(TrySendError::Disconnected(_) | TrySendError::Closed(_)) => {
+                        panic!("receiver dropped")
+                    }
+                }
+            }
+            rejected
+        });
+
+        let mut received = vec![];
+        while let Ok(val) = rx.recv() {
+            received.push(val);
+        }
+        let rejected = tx_handle.join().unwrap();
+        let mut all = received
+            .iter()
+            .chain(rejected.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        all.sort();
+        assert_eq!(all, (0..10000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_channel_try_send_error_debug() {
+        struct NotDebug;
+        let (tx, rx) = new_bounded(1);
+        tx.send(NotDebug).unwrap();
+        let err = tx.try_send(NotDebug).unwrap_err();
+        assert_eq!(format!("{:?}", err), "Full(..)");
+        drop(rx);
+        let err = tx.try_send(NotDebug).unwrap_err();
+        assert_eq!(format!("{:?}", err), "Disconnected(..)");
+    }
+
+    #[test]
+    fn test_channel_send_tracked() {
+        let (tx, rx) = new_channel();
+        tx.send(0).unwrap();
+        let handle = tx.send_tracked(1).unwrap();
+        assert!(!handle.is_delivered());
+        assert_eq!(
+            handle.wait_timeout(Duration::from_millis(10)),
+            Err(DeliveryTimeoutError::Timeout)
+        );
+        assert_eq!(rx.recv(), Ok(0));
+        assert!(!handle.is_delivered());
+        assert_eq!(rx.recv(), Ok(1));
+        assert!(handle.is_delivered());
+        assert_eq!(handle.wait_timeout(Duration::ZERO), Ok(()));
+        assert_eq!(handle.wait(), Ok(()));
+    }
+
+    #[test]
+    fn test_channel_send_tracked_wait_blocks_until_received() {
+        let (tx, rx) = new_channel();
+        let handle = tx.send_tracked("job").unwrap();
+        let rx_handle = thread::spawn(move || {
+            sleep(Duration::from_millis(50));
+            let received_at = Instant::now();
+            assert_eq!(rx.recv(), Ok("job"));
+            received_at
+        });
+        assert_eq!(handle.wait(), Ok(()));
+        let waited_until = Instant::now();
+        assert!(waited_until >= rx_handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_channel_send_tracked_error_when_receivers_drop() {
+        let (tx, rx) = new_channel();
+        let handle = tx.send_tracked(1).unwrap();
+        thread::spawn(move || {
+            sleep(Duration::from_millis(50));
+            drop(rx);
+        });
+        assert_eq!(handle.wait(), Err(DeliveryError));
+        assert_eq!(tx.send_tracked(2).err(), Some(SendError::Disconnected(2)));
+    }
+
+    #[test]
+    fn test_channel_send_tracked_discarded_by_latest() {
+        let (tx, rx) = new_channel();
+        let discarded = tx.send_tracked(1).unwrap();
+        let kept = tx.send_tracked(2).unwrap();
+        assert_eq!(rx.latest(), Some((2, 1)));
+        assert_eq!(
+            discarded.wait_timeout(Duration::ZERO),
+            Err(DeliveryTimeoutError::Dropped)
+        );
+        assert_eq!(kept.wait(), Ok(()));
+    }
+
+    #[test]
+    fn test_channel_last_receiver_drop_releases_queue() {
+        let (tx, rx) = new_channel();
+        let msg = Arc::new(());
+        tx.send(Arc::clone(&msg)).unwrap();
+        tx.send_after(Arc::clone(&msg), Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(Arc::strong_count(&msg), 3);
+        drop(rx);
+        assert_eq!(Arc::strong_count(&msg), 1);
+    }
+
+    #[test]
+    fn test_channel_try_send_many() {
+        let (tx, rx) = new_bounded(3);
+        let mut batch = (0..10).collect::<VecDeque<_>>();
+        assert_eq!(tx.try_send_many(&mut batch), 3);
+        assert_eq!(batch, (3..10).collect::<VecDeque<_>>());
+        assert_eq!(tx.try_send_many(&mut batch), 0);
+        assert_eq!(batch.len(), 7);
+
+        assert_eq!(rx.drain(), vec![0, 1, 2]);
+        assert_eq!(tx.try_send_many(&mut batch), 3);
+        assert_eq!(batch, (6..10).collect::<VecDeque<_>>());
+        assert_eq!(rx.drain(), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn test_channel_try_send_many_unbounded_and_disconnected() {
+        let (tx, rx) = new_channel();
+        let mut batch = (0..10).collect::<VecDeque<_>>();
+        assert_eq!(tx.try_send_many(&mut batch), 10);
+        assert!(batch.is_empty());
+        assert_eq!(rx.drain(), (0..10).collect::<Vec<_>>());
+
+        drop(rx);
+        let mut batch = VecDeque::from(vec![10, 11]);
+        assert_eq!(tx.try_send_many(&mut batch), 0);
+        assert_eq!(batch, VecDeque::from(vec![10, 11]));
+    }
+
+    #[test]
+    fn test_channel_send_priority() {
+        let (tx, rx) = new_channel();
+        tx.send_priority("low 1", Priority::Low).unwrap();
+        tx.send_priority("high 1", Priority::High).unwrap();
+        tx.send("low 2").unwrap();
+        tx.send_priority("high 2", Priority::High).unwrap();
+        drop(tx);
+        assert_eq!(
+            rx.into_iter().collect::<Vec<_>>(),
+            vec!["high 1", "high 2", "low 1", "low 2"]
+        );
+    }
+
+    #[test]
+    fn test_channel_send_priority_with_other_queue_ops() {
+        let (tx, rx) = new_channel();
+        tx.send_all(0..3);
+        tx.send_priority(10, Priority::High).unwrap();
+        tx.send_priority(11, Priority::High).unwrap();
+        tx.send_front(20).unwrap();
+        assert_eq!(rx.peek(), Ok(20));
+        assert_eq!(rx.recv_back(), Ok(2));
+        assert_eq!(rx.recv_matching(|val| *val == 11), Ok(11));
+        tx.send_priority(12, Priority::High).unwrap();
+        assert_eq!(rx.recv_many(2), Ok(vec![20, 10]));
+        tx.send_priority(13, Priority::High).unwrap();
+        assert_eq!(rx.drain(), vec![12, 13, 0, 1]);
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_send_priority_starves_low() {
+        let (tx, rx) = new_channel();
+        tx.send_priority(-1, Priority::Low).unwrap();
+        // while High messages keep arriving, the Low backlog is never served
+        for i in 0..100 {
+            tx.send_priority(i, Priority::High).unwrap();
+            assert_eq!(rx.recv(), Ok(i));
+        }
+        assert_eq!(rx.recv(), Ok(-1));
+    }
+
+    #[test]
+    fn test_channel_flush_empty() {
+        let (tx, _rx) = new_channel::<i32>();
+        assert_eq!(tx.flush(), Ok(()));
+        assert_eq!(tx.flush_timeout(Duration::ZERO), Ok(()));
+    }
+
+    #[test]
+    fn test_channel_flush_unblocks_on_last_receive() {
+        let (tx, rx) = new_channel();
+        tx.send_all(0..3);
+        let rx_handle = thread::spawn(move || {
+            let mut last_received_at = Instant::now();
+            for _ in 0..3 {
+                sleep(Duration::from_millis(20));
+                last_received_at = Instant::now();
+                rx.recv().unwrap();
+            }
+            (rx, last_received_at)
         });
-        let rx2_handle = thread::spawn(move || {
-            let mut rx2_results: Vec<Option<String>> = vec![];
-            for _ in 0..5 {
-                rx2_results.push(rx2.recv());
-                sleep(Duration::new(0, 10000000));
+        assert_eq!(tx.flush(), Ok(()));
+        let flushed_at = Instant::now();
+        let (_rx, last_received_at) = rx_handle.join().unwrap();
+        assert!(flushed_at >= last_received_at);
+    }
+
+    #[test]
+    fn test_channel_flush_timeout() {
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        assert_eq!(
+            tx.flush_timeout(Duration::from_millis(20)),
+            Err(FlushTimeoutError::Timeout)
+        );
+        tx.send_after(2, Duration::from_millis(20)).unwrap();
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(
+            tx.flush_timeout(Duration::ZERO),
+            Err(FlushTimeoutError::Timeout)
+        );
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(tx.flush_timeout(Duration::ZERO), Ok(()));
+    }
+
+    #[test]
+    fn test_channel_flush_receivers_dropped() {
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        thread::spawn(move || {
+            sleep(Duration::from_millis(20));
+            drop(rx);
+        });
+        assert_eq!(tx.flush(), Err(FlushError));
+        assert_eq!(
+            tx.flush_timeout(Duration::ZERO),
+            Err(FlushTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_channel_reserve() {
+        let (tx, rx) = new_bounded(1);
+        let permit = tx.reserve().unwrap();
+        permit.send(1);
+        assert_eq!(rx.recv(), Ok(1));
+        let permit = tx.reserve().unwrap();
+        drop(permit);
+        assert_eq!(tx.try_send(2), Ok(()));
+        drop(rx);
+        assert_eq!(tx.reserve().err(), Some(ReserveError));
+    }
+
+    #[test]
+    fn test_channel_reserve_occupies_capacity() {
+        let (tx, rx) = new_bounded(2);
+        let tx1 = tx.clone();
+        let tx2 = tx.clone();
+        let permit1 = tx1.reserve().unwrap();
+        let permit2 = tx2.reserve().unwrap();
+        assert_eq!(tx.try_send(0), Err(TrySendError::Full(0)));
+
+        let tx_handle = thread::spawn(move || {
+            tx.send(3).unwrap();
+            tx
+        });
+        sleep(Duration::from_millis(20));
+        permit2.send(2);
+        permit1.send(1);
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Ok(1));
+        let _tx = tx_handle.join().unwrap();
+        assert_eq!(rx.recv(), Ok(3));
+    }
+
+    #[test]
+    fn test_channel_reserve_blocks_until_room() {
+        let (tx, rx) = new_bounded(1);
+        tx.send(1).unwrap();
+        let tx_handle = thread::spawn(move || {
+            let permit = tx.reserve().unwrap();
+            permit.send(2);
+        });
+        sleep(Duration::from_millis(20));
+        assert_eq!(rx.recv(), Ok(1));
+        tx_handle.join().unwrap();
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_dropped_permit_wakes_blocked_send() {
+        let (tx, rx) = new_bounded(1);
+        let tx1 = tx.clone();
+        let permit = tx.reserve().unwrap();
+        let tx1_handle = thread::spawn(move || tx1.send(1));
+        sleep(Duration::from_millis(20));
+        drop(permit);
+        assert_eq!(tx1_handle.join().unwrap(), Ok(()));
+        assert_eq!(rx.try_recv(), Ok(1));
+    }
+
+    #[test]
+    fn test_channel_reserve_owned() {
+        let (tx, rx) = new_bounded(1);
+        let permit = tx.reserve_owned().unwrap();
+        let tx_handle = thread::spawn(move || permit.send(1));
+        let tx = tx_handle.join().unwrap();
+        assert_eq!(rx.recv(), Ok(1));
+        tx.send(2).unwrap();
+        assert_eq!(rx.recv(), Ok(2));
+
+        let permit = tx.reserve_owned().unwrap();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        let tx = permit.release();
+        assert_eq!(tx.try_send(3), Ok(()));
+        assert_eq!(rx.recv(), Ok(3));
+
+        // the permit alone keeps the channel open
+        let permit = tx.reserve_owned().unwrap();
+        let tx_handle = thread::spawn(move || {
+            sleep(Duration::from_millis(20));
+            drop(permit.send(4));
+        });
+        assert_eq!(rx.recv(), Ok(4));
+        assert_eq!(rx.recv(), Err(RecvError));
+        tx_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_channel_dropped_owned_permit() {
+        let (tx, rx) = new_bounded(1);
+        let tx1 = tx.clone();
+        let permit = tx.reserve_owned().unwrap();
+        assert_eq!(tx1.try_send(1), Err(TrySendError::Full(1)));
+        drop(permit);
+        assert_eq!(tx1.try_send(1), Ok(()));
+        drop(tx1);
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_try_reserve_many() {
+        let (tx, rx) = new_bounded(4);
+        tx.send(0).unwrap();
+        assert_eq!(tx.try_reserve_many(4).err(), Some(TryReserveError::Full));
+        let batch = tx.try_reserve_many(3).unwrap();
+        assert_eq!(batch.send_all(1..), 3);
+        assert_eq!(rx.drain(), vec![0, 1, 2, 3]);
+
+        // unused slots are given back
+        let batch = tx.try_reserve_many(3).unwrap();
+        assert_eq!(batch.send_all(vec![1]), 1);
+        assert!(tx.try_reserve_many(3).is_ok());
+        assert_eq!(tx.try_reserve_many(4).err(), Some(TryReserveError::Full));
+        assert_eq!(rx.drain(), vec![1]);
+
+        drop(rx);
+        assert_eq!(
+            tx.try_reserve_many(1).err(),
+            Some(TryReserveError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_channel_reserve_bytes() {
+        let (tx, rx) = new_bounded_bytes(10);
+        // permits hold no bytes, so each message counts in full once sent
+        let first = tx.reserve().unwrap();
+        let second = tx.reserve().unwrap();
+        first.send(vec![0u8; 9]);
+        second.send(vec![1u8; 9]);
+        assert_eq!(tx.queued_bytes(), 18);
+        // but none are handed out while the budget is used up
+        assert_eq!(tx.try_reserve_many(1).err(), Some(TryReserveError::Full));
+        assert_eq!(
+            tx.try_send(vec![2u8; 1]),
+            Err(TrySendError::Full(vec![2u8; 1]))
+        );
+        assert_eq!(rx.recv(), Ok(vec![0u8; 9]));
+        assert_eq!(rx.recv(), Ok(vec![1u8; 9]));
+        let batch = tx.try_reserve_many(2).unwrap();
+        assert_eq!(batch.send_all([vec![3u8; 4]]), 1);
+        assert_eq!(rx.queued_bytes(), 4);
+    }
+
+    #[test]
+    fn test_channel_try_reserve_many_contiguous() {
+        const N: usize = 4;
+        const N_GROUPS: usize = 200;
+        let (tx, rx) = new_bounded(2 * N);
+        let handles: Vec<_> = (0..2)
+            .map(|id| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let mut n_groups = 0;
+                    while n_groups < N_GROUPS {
+                        match tx.try_reserve_many(N) {
+                            Ok(batch) => {
+                                assert_eq!(batch.send_all((0..N).map(|i| (id, i))), N);
+                                n_groups += 1;
+                            }
+                            Err(TryReserveError::Full) => thread::yield_now(),
+                            Err(TryReserveError::Disconnected | TryReserveError::Closed) => {
+                                unreachable!()
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        for _ in 0..2 * N_GROUPS {
+            let group = rx.recv_exact(N).unwrap();
+            let id = group[0].0;
+            assert_eq!(group, (0..N).map(|i| (id, i)).collect::<Vec<_>>());
+        }
+        assert_eq!(rx.recv(), Err(RecvError));
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_channel_extend_is_contiguous() {
+        const N: i32 = 1000;
+        let (mut tx, rx) = new_channel();
+        let tx1 = tx.clone();
+        let tx1_handle = thread::spawn(move || {
+            for _ in 0..N {
+                tx1.send(-1).unwrap();
             }
-            rx2_results
         });
-        let rx3_handle = thread::spawn(move || {
-            let mut rx3_results: Vec<Option<String>> = vec![];
-            for _ in 0..5 {
-                rx3_results.push(rx3.recv());
-                sleep(Duration::new(0, 10000000));
+        for _ in 0..10 {
+            tx.extend(0..N);
+        }
+        drop(tx);
+        tx1_handle.join().unwrap();
+
+        let msgs: Vec<i32> = rx.into_iter().collect();
+        assert_eq!(msgs.len(), 11 * N as usize);
+        let batched: Vec<i32> = msgs.iter().copied().filter(|&msg| msg >= 0).collect();
+        for chunk in batched.chunks(N as usize) {
+            assert_eq!(chunk, (0..N).collect::<Vec<_>>().as_slice());
+        }
+        // no single send lands inside a batch
+        let mut expected = 0;
+        for msg in msgs {
+            if msg >= 0 {
+                assert_eq!(msg, expected);
+                expected = (expected + 1) % N;
+            } else {
+                assert_eq!(expected, 0);
             }
-            rx3_results
+        }
+    }
+
+    #[test]
+    fn test_channel_send_iter() {
+        let (tx, rx) = new_bounded(1);
+        assert_eq!(tx.send_iter(vec![1]).ok(), Some(1));
+        let tx_handle = thread::spawn(move || tx.send_iter(2..5));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        sleep(Duration::from_millis(20));
+        drop(rx);
+        let err = tx_handle.join().unwrap().unwrap_err();
+        assert_eq!(err.n_sent, 2);
+        assert_eq!(err.into_inner().collect::<Vec<_>>(), vec![4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity must be nonzero")]
+    fn test_channel_bounded_rejects_zero_capacity() {
+        let _ = new_bounded::<i32>(0);
+    }
+
+    #[test]
+    fn test_channel_bounded_stress() {
+        const N_PRODUCERS: usize = 16;
+        const N_MSGS: usize = 2000;
+        let (tx, rx) = new_bounded(3);
+        let tx_handles: Vec<_> = (0..N_PRODUCERS)
+            .map(|id| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..N_MSGS {
+                        tx.send((id, i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        let rx_handles: Vec<_> = (0..2)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.into_iter().collect::<Vec<_>>())
+            })
+            .collect();
+        drop(rx);
+        for handle in tx_handles {
+            handle.join().unwrap();
+        }
+        let mut received: Vec<Vec<usize>> = vec![Vec::new(); N_PRODUCERS];
+        for handle in rx_handles {
+            let mut last = vec![None; N_PRODUCERS];
+            for (id, i) in handle.join().unwrap() {
+                // each receiver sees a producer's messages in send order
+                assert!(last[id] < Some(i));
+                last[id] = Some(i);
+                received[id].push(i);
+            }
+        }
+        for mut msgs in received {
+            msgs.sort_unstable();
+            assert_eq!(msgs, (0..N_MSGS).collect::<Vec<_>>());
+        }
+    }
+
+    // Fills a capacity-1 channel with 0, then races sends of 1 and 2.
+    fn race_into_full(policy: OverflowPolicy) -> (Receiver<i32>, Vec<Result<(), SendError<i32>>>) {
+        let (tx, rx) = new_bounded_with(1, policy);
+        tx.send(0).unwrap();
+        let handles: Vec<_> = [1, 2]
+            .into_iter()
+            .map(|msg| {
+                let tx = tx.clone();
+                thread::spawn(move || tx.send(msg))
+            })
+            .collect();
+        let results = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        (rx, results)
+    }
+
+    #[test]
+    fn test_channel_overflow_drop_newest() {
+        let (rx, results) = race_into_full(OverflowPolicy::DropNewest);
+        assert_eq!(results, vec![Ok(()), Ok(())]);
+        assert_eq!(rx.dropped_count(), 2);
+        assert_eq!(rx.recv(), Ok(0));
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_overflow_drop_oldest() {
+        let (rx, results) = race_into_full(OverflowPolicy::DropOldest);
+        assert_eq!(results, vec![Ok(()), Ok(())]);
+        assert_eq!(rx.dropped_count(), 2);
+        // whichever send came second survives
+        let survivor = rx.recv().unwrap();
+        assert!(survivor == 1 || survivor == 2);
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_overflow_reject() {
+        let (rx, results) = race_into_full(OverflowPolicy::Reject);
+        assert_eq!(
+            results,
+            vec![Err(SendError::Full(1)), Err(SendError::Full(2))]
+        );
+        assert_eq!(rx.dropped_count(), 0);
+        assert_eq!(rx.recv(), Ok(0));
+        assert_eq!(rx.recv(), Err(RecvError));
+
+        // a live receiver is not mistaken for a disconnected one
+        let (tx, rx) = new_bounded_with(1, OverflowPolicy::Reject);
+        tx.send(0).unwrap();
+        assert_eq!(tx.send(1), Err(SendError::Full(1)));
+        assert!(!tx.is_disconnected());
+        assert_eq!(rx.recv(), Ok(0));
+        tx.send(2).unwrap();
+        drop(rx);
+        assert_eq!(tx.send(3), Err(SendError::Disconnected(3)));
+    }
+
+    #[test]
+    fn test_channel_overflow_block() {
+        let (tx, rx) = new_bounded_with(1, OverflowPolicy::Block);
+        tx.send(0).unwrap();
+        let handles: Vec<_> = [1, 2]
+            .into_iter()
+            .map(|msg| {
+                let tx = tx.clone();
+                thread::spawn(move || tx.send(msg))
+            })
+            .collect();
+        drop(tx);
+        let mut msgs: Vec<i32> = rx.iter().collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Ok(()));
+        }
+        assert_eq!(msgs[0], 0);
+        msgs.sort_unstable();
+        assert_eq!(msgs, vec![0, 1, 2]);
+        assert_eq!(rx.dropped_count(), 0);
+    }
+
+    #[test]
+    fn test_channel_overflow_policy_other_paths() {
+        let (tx, rx) = new_bounded_with(1, OverflowPolicy::DropOldest);
+        let handle = tx.send_tracked(0).unwrap();
+        tx.send_front(1).unwrap();
+        assert_eq!(handle.wait(), Err(DeliveryError));
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+        assert_eq!(tx.dropped_count(), 1);
+        assert_eq!(rx.recv(), Ok(1));
+
+        let (tx, rx) = new_bounded_with(1, OverflowPolicy::DropNewest);
+        tx.send(0).unwrap();
+        assert_eq!(tx.send_tracked(1).unwrap().wait(), Err(DeliveryError));
+        assert_eq!(rx.recv(), Ok(0));
+    }
+
+    #[test]
+    fn test_channel_capacity_and_is_full() {
+        let (tx, rx) = new_channel::<i32>();
+        assert_eq!((tx.capacity(), rx.capacity()), (None, None));
+        tx.send_all(0..100);
+        assert!(!tx.is_full() && !rx.is_full());
+
+        let (tx, rx) = new_bounded(2);
+        assert_eq!((tx.capacity(), rx.capacity()), (Some(2), Some(2)));
+        assert!(!tx.is_full() && !rx.is_full());
+        tx.send(1).unwrap();
+        assert!(!tx.is_full() && !rx.is_full());
+        tx.send(2).unwrap();
+        assert!(tx.is_full() && rx.is_full());
+        assert_eq!(rx.recv(), Ok(1));
+        assert!(!tx.is_full() && !rx.is_full());
+        assert_eq!(rx.recv(), Ok(2));
+        assert!(!rx.is_full());
+
+        // permits occupy slots without changing the capacity
+        let tx1 = tx.clone();
+        let _permit1 = tx1.reserve().unwrap();
+        let _permit2 = tx.reserve().unwrap();
+        assert!(rx.is_full());
+        assert_eq!(rx.capacity(), Some(2));
+    }
+
+    fn queue_capacity<T>(rx: &Receiver<T>) -> usize {
+        rx.shared.inner.lock().unwrap().queue.entries.capacity()
+    }
+
+    #[test]
+    fn test_channel_with_capacity_and_shrink() {
+        let (tx, rx) = new_channel_with_capacity(1000);
+        let prealloc = queue_capacity(&rx);
+        assert!(prealloc >= 1000);
+        tx.send_all(0..1000);
+        assert_eq!(queue_capacity(&rx), prealloc);
+
+        assert_eq!(rx.recv_many(990).unwrap().len(), 990);
+        rx.shrink_to(100);
+        let capacity = queue_capacity(&rx);
+        assert!((100..1000).contains(&capacity));
+        tx.shrink_to_fit();
+        assert!(queue_capacity(&rx) < capacity);
+        assert_eq!(rx.drain(), (990..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_channel_shrink_keeps_priority_lanes() {
+        let (tx, rx) = new_channel();
+        tx.send_all(0..100);
+        tx.send_priority(-1, Priority::High).unwrap();
+        assert_eq!(rx.recv_many(50).unwrap().len(), 50);
+        rx.shrink_to_fit();
+        tx.send_priority(-2, Priority::High).unwrap();
+        assert_eq!(rx.recv(), Ok(-2));
+        assert_eq!(rx.recv(), Ok(49));
+    }
+
+    #[test]
+    fn test_channel_bounded_bytes_blocks() {
+        let (tx, rx) = new_bounded_bytes(10);
+        tx.send(vec![0u8; 4]).unwrap();
+        tx.send(vec![1u8; 6]).unwrap();
+        assert_eq!(tx.queued_bytes(), 10);
+        assert!(tx.is_full());
+        assert_eq!(
+            tx.try_send(vec![2u8; 1]),
+            Err(TrySendError::Full(vec![2u8; 1]))
+        );
+        let tx_handle = thread::spawn(move || {
+            tx.send(vec![2u8; 5]).unwrap();
+            tx
+        });
+        sleep(Duration::from_millis(20));
+        assert_eq!(rx.recv(), Ok(vec![0u8; 4]));
+        // 6 + 5 is still over budget
+        sleep(Duration::from_millis(20));
+        assert_eq!(rx.queued_bytes(), 6);
+        assert_eq!(rx.recv(), Ok(vec![1u8; 6]));
+        let tx = tx_handle.join().unwrap();
+        assert_eq!(tx.queued_bytes(), 5);
+        assert_eq!(rx.recv(), Ok(vec![2u8; 5]));
+        assert_eq!(rx.queued_bytes(), 0);
+    }
+
+    #[test]
+    fn test_channel_bounded_bytes_oversized_message() {
+        let (tx, rx) = new_bounded_bytes(4);
+        // larger than the whole budget, but the channel is empty
+        tx.send("too long".to_string()).unwrap();
+        assert_eq!(tx.queued_bytes(), 8);
+        assert_eq!(
+            tx.try_send("a".to_string()),
+            Err(TrySendError::Full("a".to_string()))
+        );
+        assert_eq!(rx.recv().as_deref(), Ok("too long"));
+        assert_eq!(tx.try_send("abcd".to_string()), Ok(()));
+        assert_eq!(rx.recv().as_deref(), Ok("abcd"));
+    }
+
+    #[test]
+    fn test_channel_bounded_bytes_send_all() {
+        let (tx, rx) = new_bounded_bytes(10);
+        let tx_handle =
+            thread::spawn(move || tx.send_all([vec![0u8; 6], vec![1u8; 6], vec![2u8; 6]]));
+        sleep(Duration::from_millis(20));
+        // each item waits for room for its own size
+        assert_eq!(rx.queued_bytes(), 6);
+        assert_eq!(rx.recv(), Ok(vec![0u8; 6]));
+        sleep(Duration::from_millis(20));
+        assert_eq!(rx.queued_bytes(), 6);
+        assert_eq!(rx.recv(), Ok(vec![1u8; 6]));
+        assert_eq!(rx.recv(), Ok(vec![2u8; 6]));
+        assert_eq!(tx_handle.join().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_channel_bounded_bytes_accounting() {
+        struct Sized3;
+        impl MessageSize for Sized3 {
+            fn size(&self) -> usize {
+                3
+            }
+        }
+        let (tx, rx) = new_bounded_bytes::<Sized3>(100);
+        tx.send_after(Sized3, Duration::from_millis(10)).unwrap();
+        tx.send_all([Sized3, Sized3, Sized3]);
+        tx.send_priority(Sized3, Priority::High).unwrap();
+        assert_eq!(rx.queued_bytes(), 15);
+        assert_eq!(rx.recv_many(2).unwrap().len(), 2);
+        assert_eq!(rx.queued_bytes(), 9);
+        let guard = rx.recv_guarded().unwrap();
+        assert_eq!(rx.queued_bytes(), 6);
+        drop(guard);
+        assert_eq!(rx.queued_bytes(), 9);
+        assert_eq!(rx.drain().len(), 2);
+        assert_eq!(rx.queued_bytes(), 3);
+        assert!(rx.recv().is_ok());
+        assert_eq!(rx.queued_bytes(), 0);
+
+        let (tx, rx) = new_channel();
+        tx.send(vec![0u8; 100]).unwrap();
+        assert_eq!(rx.queued_bytes(), 0);
+        assert_eq!(0u64.size(), 8);
+    }
+
+    #[test]
+    fn test_channel_watermark_events() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events1 = Arc::clone(&events);
+        let (tx, rx) = new_channel_with_watermarks(3, 1, move |w| events1.lock().unwrap().push(w));
+        tx.send_all(0..2);
+        assert!(events.lock().unwrap().is_empty());
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        assert_eq!(*events.lock().unwrap(), vec![Watermark::High]);
+        // in between the watermarks nothing fires either way
+        assert_eq!(rx.recv_many(2).unwrap(), vec![0, 1]);
+        tx.send(4).unwrap();
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(*events.lock().unwrap(), vec![Watermark::High]);
+        assert_eq!(rx.recv(), Ok(3));
+        assert_eq!(rx.recv(), Ok(4));
+        tx.send_all(5..8);
+        assert_eq!(rx.drain(), vec![5, 6, 7]);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                Watermark::High,
+                Watermark::Low,
+                Watermark::High,
+                Watermark::Low
+            ]
+        );
+    }
+
+    #[test]
+    fn test_channel_wait_below_high_bounds_producer() {
+        const HIGH: usize = 50;
+        let (tx, rx) = new_channel_with_watermarks(HIGH, 10, |_| {});
+        let max_len = Arc::new(Mutex::new(0usize));
+        let max_len1 = Arc::clone(&max_len);
+        let tx_handle = thread::spawn(move || {
+            for i in 0..2000 {
+                tx.wait_below_high();
+                tx.send(i).unwrap();
+                let len = tx.shared.inner.lock().unwrap().queue.len();
+                let mut max_len = max_len1.lock().unwrap();
+                *max_len = cmp::max(*max_len, len);
+            }
+        });
+        for i in 0..2000 {
+            assert_eq!(rx.recv(), Ok(i));
+            if i % 100 == 0 {
+                sleep(Duration::from_millis(1));
+            }
+        }
+        tx_handle.join().unwrap();
+        assert!(*max_len.lock().unwrap() <= HIGH);
+        assert_eq!(rx.recv(), Err(RecvError));
+
+        // a channel without watermarks never makes it wait
+        let (tx, _rx) = new_channel();
+        tx.send_all(0..1000);
+        tx.wait_below_high();
+    }
+
+    #[test]
+    fn test_channel_blocked_senders_admitted_in_order() {
+        let (tx, rx) = new_bounded(1);
+        tx.send(0).unwrap();
+        let mut handles = Vec::new();
+        for i in 1..=3 {
+            let tx = tx.clone();
+            handles.push(thread::spawn(move || tx.send(i).unwrap()));
+            // each parks before the next arrives
+            sleep(Duration::from_millis(20));
+        }
+        // fail fast instead of jumping the line, even once there is room
+        assert_eq!(rx.recv(), Ok(0));
+        assert_eq!(tx.try_send(9), Err(TrySendError::Full(9)));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Ok(3));
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(tx.try_send(4), Ok(()));
+    }
+
+    #[test]
+    fn test_channel_send_timeout_leaves_line() {
+        let (tx, rx) = new_bounded(1);
+        tx.send(0).unwrap();
+        let tx1 = tx.clone();
+        let tx1_handle = thread::spawn(move || tx1.send_timeout(1, Duration::from_millis(10)));
+        assert_eq!(
+            tx1_handle.join().unwrap(),
+            Err(SendTimeoutError::Timeout(1))
+        );
+        assert_eq!(rx.recv(), Ok(0));
+        // the abandoned ticket doesn't hold anyone up
+        assert_eq!(tx.try_send(2), Ok(()));
+        assert_eq!(rx.recv(), Ok(2));
+    }
+
+    #[test]
+    fn test_channel_bounded_senders_do_not_starve() {
+        const N_PRODUCERS: usize = 4;
+        const N_MSGS: usize = 200;
+        let (tx, rx) = new_bounded(1);
+        let barrier = Arc::new(std::sync::Barrier::new(N_PRODUCERS));
+        let handles: Vec<_> = (0..N_PRODUCERS)
+            .map(|id| {
+                let tx = tx.clone();
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    for _ in 0..N_MSGS {
+                        tx.send(id).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        let mut n_received = [0; N_PRODUCERS];
+        let mut at_first_finish = None;
+        while let Ok(id) = rx.recv() {
+            n_received[id] += 1;
+            if n_received[id] == N_MSGS && at_first_finish.is_none() {
+                at_first_finish = Some(n_received);
+            }
+            // a slow consumer keeps every producer parked
+            thread::sleep(Duration::from_micros(50));
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        // when the first producer is done, everyone else is nearly done as well
+        for n in at_first_finish.unwrap() {
+            assert!(n >= N_MSGS * 3 / 4, "{:?}", at_first_finish);
+        }
+    }
+
+    #[test]
+    fn test_channel_dropped_count() {
+        let (tx, rx) = new_bounded_with(2, OverflowPolicy::DropOldest);
+        for i in 0..10 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(tx.dropped_count(), 8);
+        assert_eq!(rx.dropped_count(), 8);
+        assert_eq!(rx.drain(), vec![8, 9]);
+
+        assert_eq!(rx.take_dropped_count(), 8);
+        assert_eq!(tx.dropped_count(), 0);
+        tx.send_all([10, 11]);
+        tx.send(12).unwrap();
+        assert_eq!(tx.take_dropped_count(), 1);
+        assert_eq!(rx.take_dropped_count(), 0);
+        assert_eq!(rx.drain(), vec![11, 12]);
+    }
+
+    struct DropCounter(Arc<AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_channel_last_receiver_drop_destroys_messages() {
+        let n_dropped = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = new_channel();
+        for _ in 0..5 {
+            tx.send(DropCounter(Arc::clone(&n_dropped))).unwrap();
+        }
+        tx.send_priority(DropCounter(Arc::clone(&n_dropped)), Priority::High)
+            .unwrap();
+        let rx1 = rx.clone();
+        drop(rx);
+        assert_eq!(n_dropped.load(Ordering::SeqCst), 0);
+        // with the sender still alive
+        drop(rx1);
+        assert_eq!(n_dropped.load(Ordering::SeqCst), 6);
+
+        // later sends are refused, and the message comes back rather than being leaked
+        let err = tx.send(DropCounter(Arc::clone(&n_dropped))).unwrap_err();
+        assert_eq!(n_dropped.load(Ordering::SeqCst), 6);
+        drop(err);
+        assert_eq!(n_dropped.load(Ordering::SeqCst), 7);
+    }
+
+    #[test]
+    fn test_channel_close() {
+        let (tx, rx) = new_channel();
+        tx.send_all(0..10);
+        rx.close();
+        rx.close();
+        for i in 10..15 {
+            assert_eq!(tx.send(i), Err(SendError::Closed(i)));
+        }
+        assert_eq!(tx.try_send(15), Err(TrySendError::Closed(15)));
+        assert_eq!(
+            rx.iter().take(10).collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_channel_close_wakes_blocked() {
+        let (tx, rx) = new_bounded(1);
+        tx.send(1).unwrap();
+        let tx1 = tx.clone();
+        let tx1_handle = thread::spawn(move || tx1.send(2));
+        let rx1 = rx.clone();
+        sleep(Duration::from_millis(20));
+        rx.close();
+        assert_eq!(tx1_handle.join().unwrap(), Err(SendError::Closed(2)));
+        assert_eq!(rx1.recv(), Ok(1));
+
+        assert_eq!(rx1.recv(), Err(RecvError));
+        assert_eq!(tx.send(3), Err(SendError::Closed(3)));
+
+        // a receiver parked on an empty queue returns Err(RecvError)
+        let (_tx, rx) = new_channel::<i32>();
+        let rx1 = rx.clone();
+        let rx1_handle = thread::spawn(move || rx1.recv());
+        sleep(Duration::from_millis(20));
+        rx.close();
+        assert_eq!(rx1_handle.join().unwrap(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_close_keeps_delayed() {
+        let (tx, rx) = new_channel();
+        tx.send_after(1, Duration::from_millis(20)).unwrap();
+        rx.close();
+        assert_eq!(tx.send_after(2, Duration::ZERO), Err(SendError::Closed(2)));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_close_and_drain() {
+        let (tx, rx) = new_channel();
+        tx.send_all(0..10);
+        let mut seen = Vec::new();
+        let report = rx.close_and_drain(Duration::from_secs(10), |msg| seen.push(msg));
+        assert_eq!(
+            report,
+            DrainReport {
+                n_processed: 10,
+                n_discarded: 0
+            }
+        );
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+        assert_eq!(tx.send(10), Err(SendError::Closed(10)));
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_close_and_drain_timeout() {
+        let (tx, rx) = new_channel();
+        tx.send_all(0..100);
+        tx.send_after(100, Duration::from_secs(60)).unwrap();
+        let report = rx.close_and_drain(Duration::from_millis(30), |_| {
+            sleep(Duration::from_millis(10))
         });
+        assert!(report.n_processed > 0);
+        assert!(report.n_discarded > 0);
+        assert_eq!(report.n_processed + report.n_discarded, 101);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        // senders see the queue as drained rather than waiting on discarded messages
+        assert_eq!(tx.flush(), Ok(()));
+    }
+
+    #[test]
+    fn test_channel_reset() {
+        let (tx, rx) = new_bounded_with(8, OverflowPolicy::DropNewest);
+        let tx1 = tx.clone();
+        assert_eq!(rx.reset().err(), Some(ResetError));
+        drop(tx);
+        assert_eq!(rx.reset().err(), Some(ResetError));
+        drop(tx1);
 
-        for i in 0..15 {
-            tx.send(format!("hello #{:02}", i));
+        let mut tx = rx.reset().unwrap();
+        for session in 0..3 {
+            // leave residue behind in every way a session can
+            tx.send_all(session * 10..session * 10 + 7);
+            tx.send_after(-1, Duration::from_secs(60)).unwrap();
+            tx.send(-3).unwrap();
+            assert!(rx.dropped_count() > 0);
+            assert_eq!(rx.recv(), Ok(session * 10));
+            rx.close();
+            assert_eq!(tx.send(-2), Err(SendError::Closed(-2)));
+            assert_eq!(rx.reset().err(), Some(ResetError));
+            drop(tx);
+
+            tx = rx.reset().unwrap();
+            assert_eq!(rx.dropped_count(), 0);
+            assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+            tx.send(100 + session).unwrap();
+            assert_eq!(rx.recv(), Ok(100 + session));
         }
-        let mut rx1_results = rx1_handle.join().unwrap();
-        let mut rx2_results = rx2_handle.join().unwrap();
-        let mut rx3_results = rx3_handle.join().unwrap();
+        drop(tx);
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
 
-        let mut results: Vec<Option<String>> = vec![];
-        results.append(&mut rx1_results);
-        results.append(&mut rx2_results);
-        results.append(&mut rx3_results);
-        let mut sorted_results = results
-            .into_iter()
-            .map(|item| item.unwrap())
-            .collect::<Vec<_>>();
-        sorted_results.sort();
+    #[test]
+    fn test_channel_state() {
+        let (tx, rx) = new_channel();
+        let rx1 = rx.clone();
+        assert_eq!(tx.state(), ChannelState::Open);
+        tx.send(1).unwrap();
+        tx.send_after(2, Duration::from_millis(20)).unwrap();
+        let keep_alive = rx1.keep_alive();
+        drop(tx);
+        assert_eq!(rx1.state(), ChannelState::Open);
+        drop(keep_alive);
+        // senders are gone but messages remain
+        assert_eq!(rx1.state(), ChannelState::SendersGone { pending: 2 });
+        assert_eq!(rx1.recv(), Ok(1));
+        assert_eq!(rx1.state(), ChannelState::SendersGone { pending: 1 });
+        assert_eq!(rx1.recv(), Ok(2));
+        assert_eq!(rx1.state(), ChannelState::SendersGone { pending: 0 });
 
-        assert_eq!(
-            sorted_results,
-            vec![
-                "hello #00".to_string(),
-                "hello #01".to_string(),
-                "hello #02".to_string(),
-                "hello #03".to_string(),
-                "hello #04".to_string(),
-                "hello #05".to_string(),
-                "hello #06".to_string(),
-                "hello #07".to_string(),
-                "hello #08".to_string(),
-                "hello #09".to_string(),
-                "hello #10".to_string(),
-                "hello #11".to_string(),
-                "hello #12".to_string(),
-                "hello #13".to_string(),
-                "hello #14".to_string(),
-            ]
-        )
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        rx.close();
+        assert_eq!(tx.state(), ChannelState::Closed { pending: 1 });
+        drop(tx);
+        assert_eq!(rx.state(), ChannelState::Closed { pending: 1 });
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.state(), ChannelState::Closed { pending: 0 });
+
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        drop(rx);
+        assert_eq!(tx.state(), ChannelState::ReceiversGone);
     }
 
     #[test]
-    fn test_channel_mpmc() {
-        let (tx, mut rx) = new_channel();
-        let mut tx1 = tx.clone();
-        let mut tx2 = tx.clone();
-        let mut tx3 = tx.clone();
+    fn test_channel_is_disconnected() {
+        let (tx, rx) = new_channel();
+        assert!(!tx.is_disconnected());
+        assert!(!rx.is_disconnected());
+        assert!(!rx.is_closed_and_empty());
+        tx.send(1).unwrap();
+        tx.send_after(2, Duration::from_millis(20)).unwrap();
         drop(tx);
+        // senders are gone but messages remain
+        assert!(rx.is_disconnected());
+        assert!(!rx.is_closed_and_empty());
+        assert_eq!(rx.recv(), Ok(1));
+        assert!(!rx.is_closed_and_empty());
+        assert_eq!(rx.recv(), Ok(2));
+        assert!(rx.is_disconnected());
+        assert!(rx.is_closed_and_empty());
+        assert_eq!(rx.recv(), Err(RecvError));
 
-        let mut rx1 = rx.clone();
-        let mut rx2 = rx.clone();
-        let mut rx3 = rx.clone();
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        rx.close();
+        assert!(rx.is_disconnected());
+        assert!(!rx.is_closed_and_empty());
+        assert_eq!(rx.recv(), Ok(1));
+        assert!(rx.is_closed_and_empty());
+        assert!(!tx.is_disconnected());
+        drop(rx);
+        assert!(tx.is_disconnected());
 
-        let rx1_handle = thread::spawn(move || {
-            let mut rx1_results: Vec<Option<String>> = vec![];
-            for _ in 0..5 {
-                rx1_results.push(rx1.recv());
-                sleep(Duration::new(0, 10000000));
+        // a KeepAlive stands in for the departed senders
+        let (tx, rx) = new_channel::<u8>();
+        let keep_alive = rx.keep_alive();
+        drop(tx);
+        assert!(!rx.is_disconnected());
+        drop(keep_alive);
+        assert!(rx.is_disconnected());
+    }
+
+    #[test]
+    fn test_channel_endpoint_counts() {
+        let (tx, rx) = new_channel::<i32>();
+        let rx1 = rx.clone();
+        assert_eq!((tx.sender_count(), tx.receiver_count()), (1, 2));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let mut clones: Vec<_> = (0..25).map(|_| tx.clone()).collect();
+                    clones.truncate(13);
+                    clones
+                })
+            })
+            .collect();
+        let mut kept: Vec<_> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        kept.truncate(50);
+        assert_eq!(kept.len(), 50);
+        assert_eq!((rx.sender_count(), rx.receiver_count()), (51, 2));
+        drop(rx1);
+        drop(kept);
+        assert_eq!((tx.sender_count(), tx.receiver_count()), (1, 1));
+        drop(rx);
+        assert_eq!(tx.receiver_count(), 0);
+    }
+
+    #[test]
+    fn test_channel_len() {
+        let (tx, rx) = new_channel();
+        assert!(tx.is_empty() && rx.is_empty());
+        assert_eq!(rx.iter().size_hint(), (0, None));
+        let tx_handle = thread::spawn(move || {
+            for i in 0..1000 {
+                tx.send(i).unwrap();
+                assert!(tx.len() <= 1000);
             }
-            (rx1, rx1_results)
+            tx
         });
-        let rx2_handle = thread::spawn(move || {
-            let mut rx2_results: Vec<Option<String>> = vec![];
-            for _ in 0..5 {
-                rx2_results.push(rx2.recv());
-                sleep(Duration::new(0, 10000000));
+        let mut n_received = 0;
+        while n_received < 1000 {
+            assert!(rx.len() <= 1000 - n_received);
+            if rx.try_recv().is_ok() {
+                n_received += 1;
             }
-            (rx2, rx2_results)
-        });
-        let rx3_handle = thread::spawn(move || {
-            let mut rx3_results: Vec<Option<String>> = vec![];
-            for _ in 0..5 {
-                rx3_results.push(rx3.recv());
-                sleep(Duration::new(0, 10000000));
+        }
+        let tx = tx_handle.join().unwrap();
+        assert!(tx.is_empty() && rx.is_empty());
+
+        tx.send_all(0..3);
+        tx.send_after(3, Duration::from_millis(20)).unwrap();
+        assert_eq!((tx.len(), rx.len()), (3, 3));
+        drop(tx);
+        // the delayed message may still arrive
+        assert_eq!(rx.iter().size_hint(), (3, None));
+        sleep(Duration::from_millis(30));
+        assert_eq!(rx.recv(), Ok(0));
+        assert_eq!(rx.len(), 3);
+        assert!(!rx.is_empty());
+        assert_eq!(rx.iter().size_hint(), (3, Some(3)));
+        let rx1 = rx.clone();
+        assert_eq!(rx.iter().size_hint(), (0, Some(3)));
+        drop(rx1);
+        assert_eq!(rx.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_channel_collect_preallocates() {
+        const N: usize = 100_000;
+        let (tx, rx) = new_channel();
+        tx.send_all(0..N);
+        drop(tx);
+        let iter = rx.into_iter();
+        assert_eq!(iter.size_hint(), (N, Some(N)));
+        let msgs = iter.collect::<Vec<_>>();
+        // a single allocation of exactly the right size
+        assert_eq!((msgs.len(), msgs.capacity()), (N, N));
+
+        let (tx, rx) = new_channel();
+        tx.send_all(0..N);
+        let mut msgs = Vec::new();
+        let mut n_reallocs = 0;
+        for msg in rx.iter().take(N) {
+            if msgs.len() == msgs.capacity() {
+                n_reallocs += 1;
             }
-            (rx3, rx3_results)
+            msgs.push(msg);
+        }
+        assert!(n_reallocs <= 2 * N.ilog2() as usize);
+    }
+
+    #[test]
+    fn test_channel_iter_fused() {
+        let (tx, rx) = new_channel();
+        tx.send(1).unwrap();
+        drop(tx);
+        let mut iter = rx.iter();
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next(), None);
+        let tx = rx.reset().unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(rx.iter().next(), Some(2));
+    }
+
+    #[test]
+    fn test_channel_into_exact_iter() {
+        let (tx, rx) = new_channel();
+        tx.send_all(0..5);
+        let rx = rx.into_exact_iter().err().unwrap();
+        let rx1 = rx.clone();
+        drop(tx);
+        let mut iter = rx.into_exact_iter().ok().unwrap();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(rx1.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_channel_closed() {
+        let (tx, rx) = new_channel::<i32>();
+        let rx1 = rx.clone();
+        assert!(!tx.closed_timeout(Duration::from_millis(10)));
+        let rx_handle = thread::spawn(move || {
+            sleep(Duration::from_millis(20));
+            drop(rx);
+            sleep(Duration::from_millis(20));
+            let dropped_at = Instant::now();
+            drop(rx1);
+            dropped_at
+        });
+        let tx1 = tx.clone();
+        let watchdog = thread::spawn(move || {
+            tx1.closed();
+            Instant::now()
         });
+        assert!(tx.closed_timeout(Duration::from_secs(10)));
+        let dropped_at = rx_handle.join().unwrap();
+        assert!(watchdog.join().unwrap() >= dropped_at);
+        assert!(dropped_at.elapsed() < Duration::from_secs(1));
+        // already closed
+        tx.closed();
+        assert!(tx.closed_timeout(Duration::ZERO));
+    }
 
-        let tx1_handle = thread::spawn(move || {
-            for i in 0..5 {
-                tx1.send(format!("hello #{} from tx1", i));
-                sleep(Duration::new(0, 11000000));
+    #[test]
+    fn test_channel_weak_sender() {
+        let (tx, rx) = new_channel();
+        let weak_tx = tx.downgrade();
+        assert_eq!(tx.sender_count(), 1);
+        let tx1 = weak_tx.upgrade().unwrap();
+        assert_eq!(tx.sender_count(), 2);
+        tx1.send(1).unwrap();
+        drop(tx1);
+        drop(tx);
+        // only the weak sender is left
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert!(weak_tx.clone().upgrade().is_none());
+
+        // a weak sender from before a reset does not revive
+        let tx = rx.reset().unwrap();
+        assert!(weak_tx.upgrade().is_none());
+        let weak_tx = tx.downgrade();
+        let tx_handle = thread::spawn(move || {
+            let tx = weak_tx.upgrade().unwrap();
+            tx.send(3).unwrap();
+        });
+        tx.send(2).unwrap();
+        tx_handle.join().unwrap();
+        drop(tx);
+        let mut received: Vec<_> = rx.iter().collect();
+        received.sort();
+        assert_eq!(received, vec![2, 3]);
+
+        // nor does one that outlived the whole channel
+        let (tx, rx) = new_channel::<i32>();
+        let weak_tx = tx.downgrade();
+        drop((tx, rx));
+        assert!(weak_tx.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_channel_weak_receiver() {
+        let (tx, rx) = new_channel();
+        let weak_rx = rx.downgrade();
+        assert_eq!(tx.receiver_count(), 1);
+        let rx1 = weak_rx.upgrade().unwrap();
+        tx.send(1).unwrap();
+        drop(rx);
+        assert_eq!(rx1.recv(), Ok(1));
+        tx.send(2).unwrap();
+        drop(rx1);
+        // only the weak receiver is left
+        assert_eq!(tx.send(3), Err(SendError::Disconnected(3)));
+        assert!(weak_rx.clone().upgrade().is_none());
+        drop(tx);
+        assert!(weak_rx.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_channel_weak_receiver_upgrade_races_last_drop() {
+        for _ in 0..500 {
+            let (tx, rx) = new_channel();
+            let weak_rx = rx.downgrade();
+            let barrier = Arc::new(std::sync::Barrier::new(2));
+            let barrier1 = Arc::clone(&barrier);
+            let drop_handle = thread::spawn(move || {
+                barrier1.wait();
+                drop(rx);
+            });
+            barrier.wait();
+            let upgraded = weak_rx.upgrade();
+            drop_handle.join().unwrap();
+            // either the upgrade kept the channel connected or it lost to the drop
+            match upgraded {
+                Some(rx) => {
+                    assert_eq!(tx.receiver_count(), 1);
+                    tx.send(1).unwrap();
+                    assert_eq!(rx.recv(), Ok(1));
+                }
+                None => {
+                    assert_eq!(tx.receiver_count(), 0);
+                    assert_eq!(tx.send(1), Err(SendError::Disconnected(1)));
+                }
             }
+        }
+    }
+
+    #[test]
+    fn test_channel_identity() {
+        let (tx, rx) = new_channel::<i32>();
+        let (tx1, rx1) = new_channel::<i32>();
+        let tx_clone = tx.clone();
+        let rx_clone = rx.clone();
+        assert!(tx.same_channel(&tx_clone) && rx.same_channel(&rx_clone));
+        assert!(tx_clone.is_paired_with(&rx_clone));
+        assert!(!tx.same_channel(&tx1) && !rx.same_channel(&rx1));
+        assert!(!tx.is_paired_with(&rx1) && !tx1.is_paired_with(&rx));
+        assert!(tx1.is_paired_with(&rx1));
+
+        assert_eq!(tx.channel_id(), rx_clone.channel_id());
+        assert_ne!(tx.channel_id(), tx1.channel_id());
+        let mut by_channel = std::collections::HashMap::new();
+        for tx in [&tx, &tx1, &tx_clone] {
+            *by_channel.entry(tx.channel_id()).or_insert(0) += 1;
+        }
+        assert_eq!(by_channel[&rx.channel_id()], 2);
+        assert_eq!(by_channel[&rx1.channel_id()], 1);
+    }
+
+    #[test]
+    fn test_channel_debug() {
+        struct Opaque;
+        let (tx, rx) = new_bounded(4);
+        tx.send(Opaque).unwrap();
+        tx.send(Opaque).unwrap();
+        let _tx1 = tx.clone();
+        assert_eq!(
+            format!("{tx:?}"),
+            "Sender { len: 2, n_senders: 2, n_receivers: 1, capacity: Some(4), closed: false }"
+        );
+        rx.close();
+        assert_eq!(
+            format!("{rx:?}"),
+            "Receiver { len: 2, n_senders: 2, n_receivers: 1, capacity: Some(4), closed: true }"
+        );
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct Holder(Receiver<Opaque>);
+        assert!(format!("{:?}", Holder(new_channel().1)).contains("capacity: None"));
+
+        // the predicate runs with the channel locked
+        let msg = rx.recv_matching(|_| {
+            assert_eq!(format!("{tx:?}"), "Sender { <locked> }");
+            true
         });
-        let tx2_handle = thread::spawn(move || {
-            for i in 0..5 {
-                tx2.send(format!("hello #{} from tx2", i));
-                sleep(Duration::new(0, 13000000));
+        assert!(msg.is_ok());
+    }
+
+    #[test]
+    fn test_channel_purge() {
+        let (tx, rx) = new_bounded(8);
+        tx.send_all(0..8);
+        assert!(tx.is_full());
+        assert_eq!(tx.purge(), 8);
+        tx.send_after(8, Duration::from_millis(10)).unwrap();
+        tx.send(9).unwrap();
+        assert_eq!(rx.purge(), 1);
+        assert_eq!(rx.purge(), 0);
+        assert_eq!(rx.recv(), Ok(8));
+        drop(tx);
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_purge_races_sends() {
+        const N_MESSAGES: usize = 10_000;
+        let (tx, rx) = new_channel();
+        let tx_handle = thread::spawn(move || {
+            for i in 0..N_MESSAGES {
+                tx.send(i).unwrap();
             }
         });
-        let tx3_handle = thread::spawn(move || {
-            for i in 0..5 {
-                tx3.send(format!("hello #{} from tx3", i));
-                sleep(Duration::new(0, 15000000));
+        let (mut n_received, mut n_purged) = (0, 0);
+        while !tx_handle.is_finished() {
+            n_purged += rx.purge();
+            n_received += rx.try_iter().take(10).count();
+        }
+        tx_handle.join().unwrap();
+        let n_queued = rx.iter().count();
+        assert_eq!(n_received + n_purged + n_queued, N_MESSAGES);
+    }
+
+    #[test]
+    fn test_channel_keep_alive() {
+        let (tx, rx) = new_channel();
+        let keep_alive = rx.keep_alive();
+        let keep_alive1 = keep_alive.clone();
+        tx.send(1).unwrap();
+        drop(tx);
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert!(!rx.is_closed_and_empty());
+        drop(keep_alive);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        // dropping the last guard wakes a blocked receiver
+        let rx_handle = thread::spawn(move || rx.recv());
+        sleep(Duration::from_millis(20));
+        assert!(!rx_handle.is_finished());
+        drop(keep_alive1);
+        assert_eq!(rx_handle.join().unwrap(), Err(RecvError));
+
+        // closing still ends recv
+        let (_tx, rx) = new_channel::<i32>();
+        let _keep_alive = rx.keep_alive();
+        rx.close();
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_new_sender() {
+        let (tx, rx) = new_channel();
+        let tx1 = rx.new_sender().unwrap();
+        assert_eq!(tx.sender_count(), 2);
+        drop(tx);
+        tx1.send(1).unwrap();
+        drop(tx1);
+        assert_eq!(rx.new_sender().err(), Some(ChannelClosed));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError));
+
+        let (_tx, rx) = new_channel::<i32>();
+        rx.close();
+        assert_eq!(rx.new_sender().err(), Some(ChannelClosed));
+
+        // with a guard, senders can be minted after the last one is gone
+        let (tx, rx) = new_channel();
+        let keep_alive = rx.keep_alive();
+        drop(tx);
+        let rx1 = rx.clone();
+        let rx_handle = thread::spawn(move || rx.recv());
+        sleep(Duration::from_millis(20));
+        assert!(!rx_handle.is_finished());
+        let tx = rx1.new_sender().unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(rx_handle.join().unwrap(), Ok(2));
+        drop(keep_alive);
+    }
+
+    #[test]
+    fn test_channel_new_sender_races_blocked_recv() {
+        for _ in 0..200 {
+            let (tx, rx) = new_channel::<i32>();
+            let rx1 = rx.clone();
+            let rx_handle = thread::spawn(move || rx.recv());
+            let tx_handle = thread::spawn(move || drop(tx));
+            // once the last sender is gone the channel stays ended, however the
+            // receiver's wakeup interleaves with minting
+            let new_tx = rx1.new_sender();
+            tx_handle.join().unwrap();
+            match new_tx {
+                Ok(new_tx) => drop(new_tx),
+                Err(ChannelClosed) => assert_eq!(rx1.sender_count(), 0),
+            }
+            assert_eq!(rx1.new_sender().err(), Some(ChannelClosed));
+            assert_eq!(rx_handle.join().unwrap(), Err(RecvError));
+        }
+    }
+
+    #[test]
+    fn test_channel_subscribe() {
+        let (tx, rx) = new_channel();
+        let rx1 = tx.subscribe();
+        assert_eq!(tx.receiver_count(), 2);
+        tx.send(1).unwrap();
+        drop(rx);
+        assert_eq!(rx1.recv(), Ok(1));
+
+        // the last receiver's drop discards the backlog; a later subscriber starts empty
+        tx.send(2).unwrap();
+        drop(rx1);
+        assert_eq!(tx.send(3), Err(SendError::Disconnected(3)));
+        let rx2 = tx.subscribe();
+        assert_eq!(rx2.try_recv(), Err(TryRecvError::Empty));
+        tx.send(4).unwrap();
+        let tx1 = tx.clone();
+        drop(tx);
+        drop(tx1);
+        assert_eq!(rx2.into_iter().collect::<Vec<_>>(), vec![4]);
+
+        let (tx, _rx) = new_channel();
+        let rx1 = tx.subscribe();
+        tx.send_all(0..3);
+        rx1.close();
+        assert_eq!(
+            tx.subscribe().into_iter().collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_channel_named() {
+        let (tx, rx) = new_channel_named::<i32>("ingest→parser");
+        let tx1 = tx.clone();
+        assert_eq!(tx1.name(), Some("ingest→parser"));
+        assert_eq!(rx.clone().name(), Some("ingest→parser"));
+        assert_eq!(new_channel::<i32>().0.name(), None);
+        assert_eq!(
+            format!("{tx1:?}"),
+            "Sender { name: \"ingest→parser\", len: 0, n_senders: 2, n_receivers: 1, \
+             capacity: None, closed: false }"
+        );
+        let (_, rx1) = new_channel_named::<i32>(String::from("other"));
+        assert_ne!(rx1.name(), rx.name());
+        drop(rx1);
+        tx1.send(1).unwrap();
+        rx.recv_matching(|_| {
+            assert_eq!(
+                format!("{tx:?}"),
+                "Sender { name: \"ingest→parser\", <locked> }"
+            );
+            true
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_channel_shared_by_reference() {
+        let (tx, rx) = new_channel();
+        thread::scope(|s| {
+            for i in 0..4 {
+                let tx = &tx;
+                let rx = &rx;
+                s.spawn(move || {
+                    tx.send(i).unwrap();
+                    rx.recv().unwrap()
+                });
             }
         });
-        let _ = tx1_handle.join().unwrap();
-        let _ = tx2_handle.join().unwrap();
-        let _ = tx3_handle.join().unwrap();
-        let (mut rx1, mut rx1_results) = rx1_handle.join().unwrap();
-        let (mut rx2, mut rx2_results) = rx2_handle.join().unwrap();
-        let (mut rx3, mut rx3_results) = rx3_handle.join().unwrap();
+        assert!(rx.is_empty());
 
-        let mut results: Vec<Option<String>> = vec![];
-        results.append(&mut rx1_results);
-        results.append(&mut rx2_results);
-        results.append(&mut rx3_results);
-        let mut sorted_results = results
-            .into_iter()
-            .map(|item| item.unwrap())
+        let tx = Arc::new(tx);
+        let handles = (0..4)
+            .map(|i| {
+                let tx = Arc::clone(&tx);
+                thread::spawn(move || tx.send(i).unwrap())
+            })
             .collect::<Vec<_>>();
-        sorted_results.sort();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        drop(tx);
+        let mut received = rx.into_iter().collect::<Vec<_>>();
+        received.sort();
+        assert_eq!(received, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_channel_borrowed_iter() {
+        let (tx, mut rx) = new_channel();
+        tx.send_all(0..6);
+        for val in &mut rx {
+            if val == 2 {
+                break;
+            }
+        }
+        assert_eq!(rx.recv(), Ok(3));
+        assert_eq!(rx.iter().take(1).collect::<Vec<_>>(), vec![4]);
+        drop(tx);
+        for val in &rx {
+            assert_eq!(val, 5);
+        }
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_into_iter() {
+        struct Worker {
+            jobs: IntoIter<i32>,
+        }
+        let (tx, rx) = new_channel();
+        let mut worker = Worker {
+            jobs: rx.into_iter(),
+        };
+        tx.send_all([1, 2, 3]);
+        assert_eq!(worker.jobs.next(), Some(1));
+        drop(tx);
+        assert_eq!(worker.jobs.by_ref().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(worker.jobs.next(), None);
+        assert_eq!(worker.jobs.into_inner().recv(), Err(RecvError));
+    }
 
+    #[test]
+    fn test_channel_never() {
+        let rx = never::<i32>();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
         assert_eq!(
-            sorted_results,
-            vec![
-                "hello #0 from tx1".to_string(),
-                "hello #0 from tx2".to_string(),
-                "hello #0 from tx3".to_string(),
-                "hello #1 from tx1".to_string(),
-                "hello #1 from tx2".to_string(),
-                "hello #1 from tx3".to_string(),
-                "hello #2 from tx1".to_string(),
-                "hello #2 from tx2".to_string(),
-                "hello #2 from tx3".to_string(),
-                "hello #3 from tx1".to_string(),
-                "hello #3 from tx2".to_string(),
-                "hello #3 from tx3".to_string(),
-                "hello #4 from tx1".to_string(),
-                "hello #4 from tx2".to_string(),
-                "hello #4 from tx3".to_string(),
-            ]
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert!(!rx.is_closed_and_empty());
+
+        let (tx, other) = new_channel();
+        let merged = merge(vec![never(), other]);
+        tx.send(1).unwrap();
+        drop(tx);
+        assert_eq!(merged.recv(), Ok(1));
+        assert_eq!(merged.try_recv(), Err(TryRecvError::Empty));
+
+        rx.close();
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_tick() {
+        let period = Duration::from_millis(20);
+        let started_at = Instant::now();
+        let ticker = tick(period);
+        let mut last = started_at;
+        for _ in 0..5 {
+            let fired_at = ticker.recv().unwrap();
+            assert!(fired_at >= last + period);
+            assert!(Instant::now() >= fired_at);
+            last = fired_at;
+        }
+        assert!(started_at.elapsed() >= period * 5);
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+
+        // a slow receiver gets a single stale tick, not a burst
+        sleep(period * 5);
+        assert_eq!(ticker.recv(), Ok(last + period));
+        assert_eq!(ticker.try_recv(), Err(TryRecvError::Empty));
+        let fired_at = ticker.recv().unwrap();
+        assert!(fired_at >= last + period * 5);
+
+        ticker.close();
+        assert_eq!(
+            ticker.recv_timeout(period * 2),
+            Err(RecvTimeoutError::Disconnected)
+        );
+
+        let ticker = tick(Duration::MAX);
+        assert_eq!(ticker.recv_timeout(period), Err(RecvTimeoutError::Timeout));
+    }
+
+    #[test]
+    fn test_channel_after() {
+        let timeout = Duration::from_millis(50);
+        let started_at = Instant::now();
+        let timer = after(timeout);
+        let timer2 = timer.clone();
+        assert_eq!(timer.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            timer.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        let fired_at = timer.recv().unwrap();
+        assert!(fired_at >= started_at + timeout);
+        assert!(Instant::now() >= fired_at);
+        assert_eq!(timer.recv(), Err(RecvError));
+        assert_eq!(timer2.try_recv(), Err(TryRecvError::Disconnected));
+
+        let timer = after(Duration::ZERO);
+        assert!(timer.try_recv().is_ok());
+        assert_eq!(timer.try_recv(), Err(TryRecvError::Disconnected));
+
+        let timer = after(Duration::MAX);
+        assert_eq!(
+            timer.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
         );
-        assert_eq!(rx.recv(), None);
-        assert_eq!(rx1.recv(), None);
-        assert_eq!(rx2.recv(), None);
-        assert_eq!(rx3.recv(), None);
+        timer.close();
+        assert_eq!(timer.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_channel_ready_callback() {
+        let (tx, rx) = new_channel();
+        let n_ready = Arc::new(AtomicUsize::new(0));
+        let n_ready1 = Arc::clone(&n_ready);
+        rx.set_ready_callback(move || {
+            n_ready1.fetch_add(1, Ordering::SeqCst);
+        });
+        let n_fired = || n_ready.load(Ordering::SeqCst);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(n_fired(), 1);
+        rx.recv().unwrap();
+        tx.send(5).unwrap();
+        assert_eq!(n_fired(), 1);
+        assert_eq!(rx.drain().len(), 5);
+        tx.send_all(6..10);
+        tx.try_send(10).unwrap();
+        assert_eq!(n_fired(), 2);
+        rx.drain();
+        tx.send_tracked(11).unwrap();
+        assert_eq!(n_fired(), 3);
+        rx.drain();
+        tx.send_front(12).unwrap();
+        tx.reserve().unwrap().send(13);
+        assert_eq!(n_fired(), 4);
+
+        rx.clear_ready_callback();
+        rx.drain();
+        tx.send(14).unwrap();
+        assert_eq!(n_fired(), 4);
+    }
+
+    #[test]
+    fn test_channel_ready_callback_drains() {
+        let (tx, rx) = new_channel();
+        let weak_rx = rx.downgrade();
+        let received = Arc::new(Mutex::new(vec![]));
+        let received1 = Arc::clone(&received);
+        rx.set_ready_callback(move || {
+            if let Some(rx) = weak_rx.upgrade() {
+                received1.lock().unwrap().extend(rx.try_iter());
+            }
+        });
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(*received.lock().unwrap(), vec![0, 1, 2]);
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_channel_ready_callback_panic() {
+        let (tx, rx) = new_channel();
+        rx.set_ready_callback(|| panic!("ready"));
+        tx.send(1).unwrap();
+        assert_eq!(rx.recv(), Ok(1));
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        assert_eq!(rx.drain(), vec![2, 3]);
     }
 }
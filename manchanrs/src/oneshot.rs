@@ -0,0 +1,201 @@
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{Recover, RecvError, RecvTimeoutError, TryRecvError, Wait};
+
+enum State<T> {
+    Empty,
+    Sent(T),
+    // the sender dropped without sending, or the value was taken
+    SenderGone,
+    ReceiverGone,
+}
+
+// A single slot; no queue, and no counts since neither end can be cloned.
+struct Slot<T> {
+    state: Mutex<State<T>>,
+    sent: Condvar,
+}
+
+pub struct OneshotSender<T> {
+    slot: Arc<Slot<T>>,
+}
+
+pub struct OneshotReceiver<T> {
+    slot: Arc<Slot<T>>,
+}
+
+// Carries one value, e.g. a reply. Sending consumes the sender, so a second send does not
+// compile.
+pub fn oneshot<T>() -> (OneshotSender<T>, OneshotReceiver<T>) {
+    let slot = Arc::new(Slot {
+        state: Mutex::new(State::Empty),
+        sent: Condvar::new(),
+    });
+    let tx = OneshotSender {
+        slot: Arc::clone(&slot),
+    };
+    (tx, OneshotReceiver { slot })
+}
+
+impl<T> OneshotSender<T> {
+    // Hands the value back if the receiver is gone.
+    pub fn send(self, msg: T) -> Result<(), T> {
+        let mut state_guard = self.slot.state.lock().recover();
+        if let State::ReceiverGone = *state_guard {
+            return Err(msg);
+        }
+        *state_guard = State::Sent(msg);
+        drop(state_guard);
+        self.slot.sent.notify_one();
+        Ok(())
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        matches!(*self.slot.state.lock().recover(), State::ReceiverGone)
+    }
+}
+
+impl<T> Drop for OneshotSender<T> {
+    fn drop(&mut self) {
+        let mut state_guard = self.slot.state.lock().recover();
+        if let State::Empty = *state_guard {
+            *state_guard = State::SenderGone;
+            drop(state_guard);
+            self.slot.sent.notify_one();
+        }
+    }
+}
+
+impl<T> OneshotReceiver<T> {
+    // Errors if the sender was dropped without sending.
+    pub fn recv(self) -> Result<T, RecvError> {
+        let mut state_guard = self.slot.state.lock().recover();
+        while let State::Empty = *state_guard {
+            state_guard = self.slot.sent.wait_on(state_guard);
+        }
+        take(&mut state_guard).ok_or(RecvError)
+    }
+
+    // Once the value has been taken, the channel reports Disconnected.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state_guard = self.slot.state.lock().recover();
+        if let State::Empty = *state_guard {
+            return Err(TryRecvError::Empty);
+        }
+        take(&mut state_guard).ok_or(TryRecvError::Disconnected)
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut state_guard = self.slot.state.lock().recover();
+        while let State::Empty = *state_guard {
+            state_guard = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    self.slot.sent.wait_on_timeout(state_guard, deadline - now)
+                }
+                None => self.slot.sent.wait_on(state_guard),
+            };
+        }
+        take(&mut state_guard).ok_or(RecvTimeoutError::Disconnected)
+    }
+}
+
+impl<T> Drop for OneshotReceiver<T> {
+    fn drop(&mut self) {
+        let mut state_guard = self.slot.state.lock().recover();
+        let state = mem::replace(&mut *state_guard, State::ReceiverGone);
+        drop(state_guard);
+        // a value sent but never received is dropped here, outside the lock
+        drop(state);
+    }
+}
+
+// Takes a sent value, leaving the slot as if the sender were gone.
+fn take<T>(state: &mut State<T>) -> Option<T> {
+    match mem::replace(state, State::SenderGone) {
+        State::Sent(msg) => Some(msg),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread::{self, sleep};
+
+    #[test]
+    fn test_oneshot_handoff() {
+        let (tx, rx) = oneshot();
+        let sender = thread::spawn(move || {
+            sleep(Duration::from_millis(20));
+            tx.send(String::from("reply")).unwrap();
+        });
+        assert_eq!(rx.recv(), Ok(String::from("reply")));
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_oneshot_sender_dropped() {
+        let (tx, rx) = oneshot::<i32>();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx.recv(), Err(RecvError));
+
+        let (tx, rx) = oneshot::<i32>();
+        let sender = thread::spawn(move || {
+            sleep(Duration::from_millis(20));
+            drop(tx);
+        });
+        assert_eq!(rx.recv(), Err(RecvError));
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_oneshot_try_recv_takes_once() {
+        let (tx, rx) = oneshot();
+        tx.send(7).unwrap();
+        assert_eq!(rx.try_recv(), Ok(7));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(
+            rx.recv_timeout(Duration::ZERO),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_oneshot_receiver_dropped() {
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let n_dropped = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = oneshot();
+        tx.send(Counted(Arc::clone(&n_dropped))).ok().unwrap();
+        assert_eq!(n_dropped.load(Ordering::SeqCst), 0);
+        drop(rx);
+        assert_eq!(n_dropped.load(Ordering::SeqCst), 1);
+
+        let (tx, rx) = oneshot();
+        drop(rx);
+        assert!(tx.is_disconnected());
+        let msg = tx.send(Counted(Arc::clone(&n_dropped))).err().unwrap();
+        assert_eq!(n_dropped.load(Ordering::SeqCst), 1);
+        drop(msg);
+        assert_eq!(n_dropped.load(Ordering::SeqCst), 2);
+    }
+}
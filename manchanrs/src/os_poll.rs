@@ -0,0 +1,133 @@
+// A pipe that is readable exactly while the queue holds messages, so a receiver can sit
+// in a poll(2) or epoll set. The queue writes one byte when it fills and reads it back
+// when it empties, so the pipe never holds more than that byte and neither call blocks.
+use std::io::{self, PipeReader, PipeWriter, Read, Write};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::sync::Arc;
+
+use crate::{Receiver, Recover};
+
+pub(crate) struct ReadyPipe {
+    reader: PipeReader,
+    writer: PipeWriter,
+}
+
+// The queue's end of the pipe.
+pub(crate) struct ReadySignal {
+    pipe: Arc<ReadyPipe>,
+    // whether the pipe holds the byte
+    is_set: bool,
+}
+
+impl<T> Receiver<T> {
+    // Readable while messages are queued, level-triggered; delayed messages and ticks
+    // count once they mature in a receive. The pipe is made on first call and lives as
+    // long as the channel. Panics if it cannot be made, e.g. when out of descriptors.
+    pub fn readiness_fd(&self) -> BorrowedFd<'_> {
+        let pipe = self.shared.ready_pipe.get_or_init(|| {
+            let (reader, writer) = io::pipe().expect("failed to create readiness pipe");
+            let pipe = Arc::new(ReadyPipe { reader, writer });
+            let mut inner_guard = self.shared.inner.lock().recover();
+            let mut signal = ReadySignal {
+                pipe: Arc::clone(&pipe),
+                is_set: false,
+            };
+            signal.update(!inner_guard.queue.is_empty());
+            inner_guard.queue.ready_signal = Some(signal);
+            pipe
+        });
+        pipe.reader.as_fd()
+    }
+
+    // Takes every queued message, for when poll reports the fd readable; emptying the
+    // queue clears the fd.
+    pub fn drain_ready(&mut self) -> Vec<T> {
+        self.drain()
+    }
+}
+
+impl ReadySignal {
+    pub(crate) fn update(&mut self, is_ready: bool) {
+        if is_ready == self.is_set {
+            return;
+        }
+        // read_exact and write_all retry when interrupted; anything else would mean the
+        // pipe is broken, and leaves the fd as it was
+        let result = if is_ready {
+            (&self.pipe.writer).write_all(&[0])
+        } else {
+            (&self.pipe.reader).read_exact(&mut [0])
+        };
+        if result.is_ok() {
+            self.is_set = is_ready;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::new_channel;
+    use std::ffi::{c_int, c_short, c_ulong};
+    use std::os::fd::{AsRawFd, BorrowedFd};
+    use std::thread;
+    use std::time::Duration;
+
+    #[repr(C)]
+    struct PollFd {
+        fd: c_int,
+        events: c_short,
+        revents: c_short,
+    }
+
+    const POLLIN: c_short = 1;
+
+    extern "C" {
+        fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+    }
+
+    fn is_readable(fd: BorrowedFd<'_>, timeout_ms: c_int) -> bool {
+        let mut poll_fd = PollFd {
+            fd: fd.as_raw_fd(),
+            events: POLLIN,
+            revents: 0,
+        };
+        let n_ready = unsafe { poll(&mut poll_fd, 1, timeout_ms) };
+        assert!(n_ready >= 0, "poll failed");
+        poll_fd.revents & POLLIN != 0
+    }
+
+    #[test]
+    fn test_os_poll_tracks_emptiness() {
+        let (tx, mut rx) = new_channel();
+        tx.send(1).unwrap();
+        assert!(is_readable(rx.readiness_fd(), 0));
+        assert_eq!(rx.drain_ready(), vec![1]);
+        assert!(!is_readable(rx.readiness_fd(), 0));
+
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        assert!(is_readable(rx.readiness_fd(), 0));
+        assert_eq!(rx.recv(), Ok(2));
+        assert!(is_readable(rx.readiness_fd(), 0));
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert!(!is_readable(rx.readiness_fd(), 0));
+
+        tx.send_all(4..8);
+        assert!(is_readable(rx.readiness_fd(), 0));
+        assert_eq!(rx.drain_ready(), vec![4, 5, 6, 7]);
+        assert!(!is_readable(rx.readiness_fd(), 0));
+    }
+
+    #[test]
+    fn test_os_poll_wakes_poller() {
+        let (tx, mut rx) = new_channel();
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx.send(1).unwrap();
+        });
+        assert!(is_readable(rx.readiness_fd(), 5000));
+        assert_eq!(rx.drain_ready(), vec![1]);
+        sender.join().unwrap();
+        assert!(!is_readable(rx.readiness_fd(), 0));
+    }
+}
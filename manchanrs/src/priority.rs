@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use crate::{ChannelBuilder, Receiver, RecvError, RecvTimeoutError, Sender, TryRecvError};
+
+// Receives the highest-ranking pending message first; see priority_channel.
+pub struct PriorityReceiver<T> {
+    rx: Receiver<T>,
+}
+
+// Like new_channel, but receivers take the greatest pending message rather than the
+// oldest, and equal ones in the order sent; pending messages are kept in a binary heap.
+// Messages sent with send_front or send_priority(.., Priority::High) still go ahead of
+// everything, as on any channel.
+pub fn priority_channel<T: Ord + 'static>() -> (Sender<T>, PriorityReceiver<T>) {
+    let (tx, rx) = ChannelBuilder::new().order_by(T::cmp).finish();
+    (tx, PriorityReceiver { rx })
+}
+
+// Like priority_channel, ranked by `key`. The key is recomputed on each comparison, under
+// the channel lock, so it should be cheap; one that panics fails the send or receive that
+// ran it but leaves the channel usable.
+pub fn priority_channel_by_key<T: 'static, K: Ord>(
+    key: impl Fn(&T) -> K + Send + Sync + 'static,
+) -> (Sender<T>, PriorityReceiver<T>) {
+    let (tx, rx) = ChannelBuilder::new()
+        .order_by(move |a, b| key(a).cmp(&key(b)))
+        .finish();
+    (tx, PriorityReceiver { rx })
+}
+
+impl<T> PriorityReceiver<T> {
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.rx.recv()
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.rx.try_recv()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.rx.recv_timeout(timeout)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rx.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rx.is_empty()
+    }
+
+    // The underlying receiver, which still receives in priority order.
+    pub fn into_inner(self) -> Receiver<T> {
+        self.rx
+    }
+}
+
+impl<T> Clone for PriorityReceiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            rx: self.rx.clone(),
+        }
+    }
+}
+
+impl<T> Iterator for PriorityReceiver<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_priority_order() {
+        let (tx, rx) = priority_channel();
+        for msg in [3, 1, 4, 1, 5, 9, 2, 6] {
+            tx.send(msg).unwrap();
+        }
+        assert_eq!(rx.len(), 8);
+        assert_eq!(rx.recv(), Ok(9));
+        tx.send(7).unwrap();
+        drop(tx);
+        assert_eq!(rx.collect::<Vec<_>>(), vec![7, 6, 5, 4, 3, 2, 1, 1]);
+    }
+
+    #[test]
+    fn test_priority_ties_keep_send_order() {
+        let (tx, rx) = priority_channel_by_key(|&(rank, _): &(u8, char)| rank);
+        for msg in [(1, 'a'), (2, 'b'), (1, 'c'), (2, 'd'), (1, 'e')] {
+            tx.send(msg).unwrap();
+        }
+        drop(tx);
+        let order = rx.map(|(_, name)| name).collect::<String>();
+        assert_eq!(order, "bdace");
+    }
+
+    #[test]
+    fn test_priority_high_lane_and_selective_recv() {
+        let (tx, rx) = priority_channel();
+        for msg in [2, 7, 4] {
+            tx.send(msg).unwrap();
+        }
+        tx.send_priority(1, Priority::High).unwrap();
+        tx.send_front(0).unwrap();
+        assert_eq!(rx.rx.peek(), Ok(0));
+        // the first match in receive order
+        assert_eq!(rx.rx.recv_matching(|&msg| msg % 2 == 0), Ok(0));
+        assert_eq!(rx.rx.recv_matching(|&msg| msg % 2 == 0), Ok(4));
+        assert_eq!(rx.rx.recv_latest(), Ok((2, 2)));
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_priority_panicking_key() {
+        let panics = Arc::new(AtomicBool::new(false));
+        let panics1 = Arc::clone(&panics);
+        let (tx, rx) = ChannelBuilder::new()
+            .max_bytes(100)
+            .order_by(move |a: &Vec<u8>, b| {
+                assert!(!panics1.load(Ordering::SeqCst), "bad key");
+                a.cmp(b)
+            })
+            .finish();
+        tx.send(vec![1]).unwrap();
+        tx.send(vec![2, 2]).unwrap();
+        panics.store(true, Ordering::SeqCst);
+        let sent = panic::catch_unwind(AssertUnwindSafe(|| tx.send(vec![3, 3, 3])));
+        assert!(sent.is_err());
+        panics.store(false, Ordering::SeqCst);
+        // the message made it in, and the byte count agrees
+        assert_eq!(rx.len(), 3);
+        assert_eq!(rx.queued_bytes(), 6);
+        let mut msgs = rx.drain();
+        msgs.sort();
+        assert_eq!(msgs, [vec![1], vec![2, 2], vec![3, 3, 3]]);
+        assert_eq!(rx.queued_bytes(), 0);
+    }
+
+    #[test]
+    fn test_priority_concurrent_senders() {
+        let (tx, rx) = priority_channel();
+        let senders: Vec<_> = (0..4)
+            .map(|n| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..250 {
+                        tx.send((i * 7 + n) % 100).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        for sender in senders {
+            sender.join().unwrap();
+        }
+        let msgs = rx.collect::<Vec<_>>();
+        assert_eq!(msgs.len(), 1000);
+        assert!(msgs.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn test_priority_interleaved_recv() {
+        let (tx, rx) = priority_channel();
+        let sender = thread::spawn(move || {
+            for i in 0..500 {
+                tx.send(i % 17).unwrap();
+            }
+        });
+        // each snapshot of what is pending comes out highest first
+        let mut msgs = vec![];
+        while let Ok(msg) = rx.recv() {
+            let mut pending = rx.rx.drain();
+            assert!(pending.windows(2).all(|pair| pair[0] >= pair[1]));
+            msgs.push(msg);
+            msgs.append(&mut pending);
+        }
+        sender.join().unwrap();
+        assert_eq!(msgs.len(), 500);
+    }
+}
@@ -0,0 +1,209 @@
+use std::convert;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{Receiver, RecvCancelError, SendTimeoutError, Sender};
+
+// How long a send to a full destination blocks before the pump checks for a stop.
+const SEND_POLL: Duration = Duration::from_millis(10);
+
+// A thread moving messages between channels; see Receiver::forward.
+pub struct PumpHandle {
+    stop: Arc<AtomicBool>,
+    wake: Box<dyn Fn() + Send + Sync>,
+    thread: JoinHandle<PumpReport>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PumpReport {
+    pub n_forwarded: usize,
+    pub stopped_by: PumpStop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PumpStop {
+    // The source was drained after its senders went away or it was closed.
+    SourceEnded,
+    // A send failed; the message it carried was dropped.
+    DestinationClosed,
+    // PumpHandle::stop was called.
+    Stopped,
+}
+
+impl<T: Send + 'static> Receiver<T> {
+    // Spawns a thread that receives each message, passes it through `f` and sends the
+    // result to `tx`, until the source ends, a send fails, or the pump is stopped. A
+    // destination that loses its receivers is noticed on the next send.
+    pub fn forward<U: Send + 'static>(
+        self,
+        tx: Sender<U>,
+        mut f: impl FnMut(T) -> U + Send + 'static,
+    ) -> PumpHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let shared = Arc::downgrade(&self.shared);
+        let wake = Box::new(move || {
+            if let Some(shared) = shared.upgrade() {
+                shared.wake_all();
+            }
+        });
+        let name = match self.name() {
+            Some(name) => format!("{name}-pump"),
+            None => "manchan-pump".to_string(),
+        };
+        let thread_stop = Arc::clone(&stop);
+        let thread = thread::Builder::new()
+            .name(name)
+            .spawn(move || pump(self, tx, &mut f, &thread_stop))
+            .expect("failed to spawn pump thread");
+        PumpHandle { stop, wake, thread }
+    }
+
+    pub fn forward_identity(self, tx: Sender<T>) -> PumpHandle {
+        self.forward(tx, convert::identity)
+    }
+}
+
+fn pump<T, U>(
+    rx: Receiver<T>,
+    tx: Sender<U>,
+    f: &mut impl FnMut(T) -> U,
+    stop: &AtomicBool,
+) -> PumpReport {
+    let mut n_forwarded = 0;
+    let stopped_by = 'pump: loop {
+        let mut msg = match rx.recv_cancellable(stop) {
+            Ok(msg) => f(msg),
+            Err(RecvCancelError::Cancelled) => break PumpStop::Stopped,
+            Err(RecvCancelError::Disconnected) => break PumpStop::SourceEnded,
+        };
+        loop {
+            match tx.send_timeout(msg, SEND_POLL) {
+                Ok(()) => break,
+                Err(SendTimeoutError::Timeout(_)) if stop.load(Ordering::Acquire) => {
+                    break 'pump PumpStop::Stopped;
+                }
+                Err(SendTimeoutError::Timeout(unsent)) => msg = unsent,
+                Err(_) => break 'pump PumpStop::DestinationClosed,
+            }
+        }
+        n_forwarded += 1;
+    };
+    PumpReport {
+        n_forwarded,
+        stopped_by,
+    }
+}
+
+impl PumpHandle {
+    // Makes the pump finish after the message in hand, if any, which is dropped when the
+    // destination is full. Messages still in the source are left there.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Release);
+        (self.wake)();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    // Waits for the pump to finish; a panic in `f` resumes here.
+    pub fn join(self) -> PumpReport {
+        self.thread
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_bounded, new_channel, new_channel_named, RecvError};
+    use std::time::Instant;
+
+    #[test]
+    fn test_pump_source_ended() {
+        let (src_tx, src_rx) = new_channel();
+        let (dst_tx, dst_rx) = new_channel();
+        let pump = src_rx.forward(dst_tx, |val: i32| val.to_string());
+        src_tx.send_all(0..5);
+        drop(src_tx);
+        assert_eq!(
+            pump.join(),
+            PumpReport {
+                n_forwarded: 5,
+                stopped_by: PumpStop::SourceEnded,
+            }
+        );
+        assert_eq!(dst_rx.drain(), vec!["0", "1", "2", "3", "4"]);
+        assert_eq!(dst_rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_pump_destination_closed() {
+        let (src_tx, src_rx) = new_channel();
+        let (dst_tx, dst_rx) = new_channel();
+        let pump = src_rx.forward_identity(dst_tx);
+        src_tx.send(1).unwrap();
+        assert_eq!(dst_rx.recv(), Ok(1));
+        drop(dst_rx);
+        src_tx.send(2).unwrap();
+        assert_eq!(
+            pump.join(),
+            PumpReport {
+                n_forwarded: 1,
+                stopped_by: PumpStop::DestinationClosed,
+            }
+        );
+        // the pump gave up on the source along with its receiver
+        assert!(src_tx.send(4).is_err());
+    }
+
+    #[test]
+    fn test_pump_stop() {
+        let (src_tx, src_rx) = new_channel_named("events");
+        let (dst_tx, dst_rx) = new_channel();
+        let pump = src_rx.forward(dst_tx, |val: i32| {
+            assert_eq!(thread::current().name(), Some("events-pump"));
+            val * 10
+        });
+        src_tx.send(1).unwrap();
+        assert_eq!(dst_rx.recv(), Ok(10));
+
+        // parked on the empty source
+        thread::sleep(Duration::from_millis(20));
+        assert!(!pump.is_finished());
+        let stopped_at = Instant::now();
+        pump.stop();
+        assert_eq!(
+            pump.join(),
+            PumpReport {
+                n_forwarded: 1,
+                stopped_by: PumpStop::Stopped,
+            }
+        );
+        assert!(stopped_at.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_pump_stop_while_destination_full() {
+        let (src_tx, src_rx) = new_channel();
+        let (dst_tx, dst_rx) = new_bounded(1);
+        let pump = src_rx.forward_identity(dst_tx);
+        src_tx.send_all(0..3);
+        thread::sleep(Duration::from_millis(20));
+        let stopped_at = Instant::now();
+        pump.stop();
+        assert_eq!(
+            pump.join(),
+            PumpReport {
+                n_forwarded: 1,
+                stopped_by: PumpStop::Stopped,
+            }
+        );
+        assert!(stopped_at.elapsed() < Duration::from_millis(500));
+        assert_eq!(dst_rx.drain(), vec![0]);
+    }
+}
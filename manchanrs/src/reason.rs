@@ -0,0 +1,172 @@
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use crate::{new_channel, Receiver, Recover, Sender};
+
+// Endpoints of a channel whose end carries a reason of type `R`. Everything else works as
+// on the wrapped Sender and Receiver.
+pub struct ReasonSender<T, R> {
+    tx: Sender<T>,
+    _reason: PhantomData<fn(R) -> R>,
+}
+
+pub struct ReasonReceiver<T, R> {
+    rx: Receiver<T>,
+    _reason: PhantomData<fn(R) -> R>,
+}
+
+// Why the channel ended: the first reason passed to close_with, or `R::default()` if it
+// closed otherwise, e.g. because every sender was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed<R>(pub R);
+
+pub fn new_channel_with_reason<T, R>() -> (ReasonSender<T, R>, ReasonReceiver<T, R>) {
+    let (tx, rx) = new_channel();
+    let tx = ReasonSender {
+        tx,
+        _reason: PhantomData,
+    };
+    let rx = ReasonReceiver {
+        rx,
+        _reason: PhantomData,
+    };
+    (tx, rx)
+}
+
+impl<T, R: Clone + Send + 'static> ReasonSender<T, R> {
+    // Closes the channel for every endpoint. Returns the reason that stuck, which is an
+    // earlier one if the channel was already closed with a reason.
+    pub fn close_with(&self, reason: R) -> R {
+        self.tx.shared.close(Some(reason)).unwrap()
+    }
+}
+
+impl<T, R: Clone + Default + Send + 'static> ReasonReceiver<T, R> {
+    pub fn close_with(&self, reason: R) -> R {
+        self.rx.shared.close(Some(reason)).unwrap()
+    }
+
+    // Once the backlog is drained after a close, returns Err(Closed(reason)) rather than
+    // a bare RecvError.
+    pub fn recv(&self) -> Result<T, Closed<R>> {
+        self.rx.recv().map_err(|_| Closed(self.reason()))
+    }
+
+    fn reason(&self) -> R {
+        let inner_guard = self.rx.shared.inner.lock().recover();
+        inner_guard
+            .close_reason
+            .as_ref()
+            .and_then(|r| r.downcast_ref::<R>())
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl<T, R> Clone for ReasonSender<T, R> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            _reason: PhantomData,
+        }
+    }
+}
+
+impl<T, R> Clone for ReasonReceiver<T, R> {
+    fn clone(&self) -> Self {
+        Self {
+            rx: self.rx.clone(),
+            _reason: PhantomData,
+        }
+    }
+}
+
+impl<T, R> Deref for ReasonSender<T, R> {
+    type Target = Sender<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+impl<T, R> DerefMut for ReasonSender<T, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tx
+    }
+}
+
+impl<T, R> Deref for ReasonReceiver<T, R> {
+    type Target = Receiver<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.rx
+    }
+}
+
+impl<T, R> DerefMut for ReasonReceiver<T, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SendError;
+    use std::thread::{self, sleep};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    enum Reason {
+        #[default]
+        Finished,
+        Aborted(String),
+    }
+
+    #[test]
+    fn test_reason_channel_sender_close() {
+        let (tx, rx) = new_channel_with_reason::<i32, Reason>();
+        let tx1 = tx.clone();
+        tx.send(1).unwrap();
+        let aborted = Reason::Aborted("coordinator".to_string());
+        assert_eq!(tx.close_with(aborted.clone()), aborted);
+        // the first reason wins
+        assert_eq!(tx1.close_with(Reason::Finished), aborted);
+        assert_eq!(tx1.send(2), Err(SendError::Closed(2)));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(Closed(aborted.clone())));
+        assert_eq!(rx.recv(), Err(Closed(aborted)));
+    }
+
+    #[test]
+    fn test_reason_channel_receiver_close_wakes_all() {
+        let (_tx, rx) = new_channel_with_reason::<i32, Reason>();
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.recv())
+            })
+            .collect();
+        sleep(Duration::from_millis(20));
+        let aborted = Reason::Aborted("shutdown".to_string());
+        assert_eq!(rx.close_with(aborted.clone()), aborted);
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Err(Closed(aborted.clone())));
+        }
+    }
+
+    #[test]
+    fn test_reason_channel_implicit_close() {
+        let (tx, rx) = new_channel_with_reason::<i32, Reason>();
+        tx.send(1).unwrap();
+        drop(tx);
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(Closed(Reason::Finished)));
+
+        // a plain close leaves room for a reason later
+        let (_tx, rx) = new_channel_with_reason::<i32, Reason>();
+        rx.close();
+        assert_eq!(rx.recv(), Err(Closed(Reason::Finished)));
+        let aborted = Reason::Aborted("late".to_string());
+        assert_eq!(rx.close_with(aborted.clone()), aborted);
+        assert_eq!(rx.recv(), Err(Closed(aborted)));
+    }
+}
@@ -0,0 +1,193 @@
+// Unix signals delivered as channel messages. The handler only bumps a counter and, on
+// the first pending signal of a kind, writes that kind's index to a pipe, both of which
+// are async-signal-safe; a forwarding thread reads the pipe and sends one message per
+// signal. The pipe holds at most one byte per kind, so the handler's write never blocks.
+use std::ffi::{c_int, c_void};
+use std::io::{self, PipeReader, Read};
+use std::os::fd::IntoRawFd;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::{new_channel_named, Receiver, Recover, Sender};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SignalKind {
+    Hangup,
+    Interrupt,
+    Quit,
+    Terminate,
+    User1,
+    User2,
+}
+
+const KINDS: [SignalKind; 6] = [
+    SignalKind::Hangup,
+    SignalKind::Interrupt,
+    SignalKind::Quit,
+    SignalKind::Terminate,
+    SignalKind::User1,
+    SignalKind::User2,
+];
+
+// signals delivered but not yet forwarded, by index in KINDS
+static PENDING: [AtomicU32; KINDS.len()] = [const { AtomicU32::new(0) }; KINDS.len()];
+// write end of the pipe, or -1 until the first call to signals
+static PIPE_FD: AtomicI32 = AtomicI32::new(-1);
+// the sender for each kind with a handler installed
+static ROUTES: Mutex<Vec<(SignalKind, Sender<SignalKind>)>> = Mutex::new(Vec::new());
+
+type SigHandler = usize;
+
+const SIG_DFL: SigHandler = 0;
+const SIG_ERR: SigHandler = !0;
+
+extern "C" {
+    fn signal(signum: c_int, handler: SigHandler) -> SigHandler;
+    fn raise(signum: c_int) -> c_int;
+    fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+}
+
+// A receiver for each of `kinds`, one message per delivered signal, replacing their
+// default dispositions until it is dropped; other signals are left alone. Once the
+// receiver is gone, the next such signal restores the default and is re-raised, so e.g.
+// Ctrl-C still ends the process. Fails with AlreadyExists if a live receiver already
+// has one of the kinds.
+pub fn signals(kinds: &[SignalKind]) -> io::Result<Receiver<SignalKind>> {
+    let mut routes = ROUTES.lock().recover();
+    start_forwarder()?;
+    for kind in kinds {
+        let is_taken = routes
+            .iter()
+            .any(|(routed, tx)| routed == kind && !tx.is_disconnected());
+        if is_taken {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{kind:?} is already delivered to a live receiver"),
+            ));
+        }
+    }
+    let (tx, rx) = new_channel_named("signals");
+    for &kind in kinds {
+        if let Some(route) = routes.iter_mut().find(|(routed, _)| *routed == kind) {
+            route.1 = tx.clone();
+            continue;
+        }
+        let handler = on_signal as extern "C" fn(c_int) as SigHandler;
+        if unsafe { signal(kind.number(), handler) } == SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+        routes.push((kind, tx.clone()));
+    }
+    Ok(rx)
+}
+
+impl SignalKind {
+    fn number(self) -> c_int {
+        match self {
+            SignalKind::Hangup => 1,
+            SignalKind::Interrupt => 2,
+            SignalKind::Quit => 3,
+            SignalKind::Terminate => 15,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            SignalKind::User1 => 10,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            SignalKind::User2 => 12,
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            SignalKind::User1 => 30,
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            SignalKind::User2 => 31,
+        }
+    }
+
+    fn index(self) -> usize {
+        KINDS.iter().position(|&kind| kind == self).unwrap()
+    }
+}
+
+extern "C" fn on_signal(signum: c_int) {
+    let Some(idx) = KINDS.iter().position(|kind| kind.number() == signum) else {
+        return;
+    };
+    if PENDING[idx].fetch_add(1, Ordering::SeqCst) == 0 {
+        let byte = idx as u8;
+        let fd = PIPE_FD.load(Ordering::SeqCst);
+        unsafe { write(fd, (&byte as *const u8).cast(), 1) };
+    }
+}
+
+// Call with ROUTES locked, so the pipe is made once and before any handler is installed.
+fn start_forwarder() -> io::Result<()> {
+    if PIPE_FD.load(Ordering::SeqCst) >= 0 {
+        return Ok(());
+    }
+    let (reader, writer) = io::pipe()?;
+    thread::Builder::new()
+        .name("manchan-signals".into())
+        .spawn(move || forward(reader))?;
+    // handlers may write to it at any time from now on, so it is never closed
+    PIPE_FD.store(writer.into_raw_fd(), Ordering::SeqCst);
+    Ok(())
+}
+
+fn forward(mut reader: PipeReader) {
+    let mut byte = [0];
+    while reader.read_exact(&mut byte).is_ok() {
+        let Some(&kind) = KINDS.get(usize::from(byte[0])) else {
+            continue;
+        };
+        let n_pending = PENDING[kind.index()].swap(0, Ordering::SeqCst);
+        let mut routes = ROUTES.lock().recover();
+        let Some(idx) = routes.iter().position(|(routed, _)| *routed == kind) else {
+            continue;
+        };
+        let is_delivered = (0..n_pending).all(|_| routes[idx].1.send(kind).is_ok());
+        if !is_delivered {
+            routes.remove(idx);
+            drop(routes);
+            unsafe {
+                signal(kind.number(), SIG_DFL);
+                raise(kind.number());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // Signals are process-wide, so each test uses its own kinds.
+
+    #[test]
+    fn test_signals_delivered() {
+        let rx = signals(&[SignalKind::User1]).unwrap();
+        for _ in 0..3 {
+            assert_eq!(unsafe { raise(SignalKind::User1.number()) }, 0);
+        }
+        for _ in 0..3 {
+            assert_eq!(
+                rx.recv_timeout(Duration::from_secs(5)),
+                Ok(SignalKind::User1)
+            );
+        }
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn test_signals_registered_twice() {
+        let rx = signals(&[SignalKind::User2]).unwrap();
+        let err = signals(&[SignalKind::Hangup, SignalKind::User2]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        drop(rx);
+
+        let rx = signals(&[SignalKind::User2]).unwrap();
+        assert_eq!(unsafe { raise(SignalKind::User2.number()) }, 0);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Ok(SignalKind::User2)
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{new_channel, Receiver, Recover, Sender};
+
+// Routes messages by topic to the receivers subscribed to it. Each subscriber has its own
+// channel, so a slow or departed one does not affect the rest.
+pub struct Topics<T> {
+    subs: Arc<Mutex<Vec<Subscription<T>>>>,
+}
+
+pub struct TopicSender<T> {
+    subs: Arc<Mutex<Vec<Subscription<T>>>>,
+}
+
+struct Subscription<T> {
+    pattern: Pattern,
+    tx: Sender<(Arc<str>, T)>,
+}
+
+enum Pattern {
+    Exact(Box<str>),
+    // from a pattern ending in `*`
+    Prefix(Box<str>),
+    // from Topics::catch_all
+    Unmatched,
+}
+
+impl<T> Topics<T> {
+    // Messages no subscriber matches are dropped, unless there is a catch_all receiver.
+    pub fn new() -> Self {
+        Self {
+            subs: Arc::default(),
+        }
+    }
+
+    pub fn sender(&self) -> TopicSender<T> {
+        TopicSender {
+            subs: Arc::clone(&self.subs),
+        }
+    }
+
+    // Receives messages whose topic equals `pattern`, or, if it ends in `*`, starts with
+    // the rest of it; `*` alone matches every topic. Dropping the receiver unsubscribes.
+    pub fn subscribe(&self, pattern: &str) -> Receiver<(Arc<str>, T)> {
+        let pattern = match pattern.strip_suffix('*') {
+            Some(prefix) => Pattern::Prefix(prefix.into()),
+            None => Pattern::Exact(pattern.into()),
+        };
+        self.add(pattern)
+    }
+
+    // Receives the messages that no subscribe pattern matched.
+    pub fn catch_all(&self) -> Receiver<(Arc<str>, T)> {
+        self.add(Pattern::Unmatched)
+    }
+
+    fn add(&self, pattern: Pattern) -> Receiver<(Arc<str>, T)> {
+        let (tx, rx) = new_channel();
+        self.subs
+            .lock()
+            .recover()
+            .push(Subscription { pattern, tx });
+        rx
+    }
+}
+
+impl<T> Default for Topics<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> TopicSender<T> {
+    // Delivers a copy to every matching subscriber and returns how many got one; zero
+    // means the message was dropped. Unbounded channels never make this block.
+    pub fn send(&self, topic: &str, msg: T) -> usize {
+        let topic: Arc<str> = topic.into();
+        let mut subs = self.subs.lock().recover();
+        // departed subscribers are pruned as they are found
+        subs.retain(|sub| !sub.tx.is_disconnected());
+        let mut matched: Vec<_> = subs.iter().filter(|sub| sub.matches(&topic)).collect();
+        if matched.is_empty() {
+            matched = subs
+                .iter()
+                .filter(|sub| matches!(sub.pattern, Pattern::Unmatched))
+                .collect();
+        }
+        let Some((last, rest)) = matched.split_last() else {
+            return 0;
+        };
+        let mut n_delivered = 0;
+        for sub in rest {
+            if sub.tx.send((Arc::clone(&topic), msg.clone())).is_ok() {
+                n_delivered += 1;
+            }
+        }
+        if last.tx.send((topic, msg)).is_ok() {
+            n_delivered += 1;
+        }
+        n_delivered
+    }
+}
+
+impl<T> Clone for TopicSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            subs: Arc::clone(&self.subs),
+        }
+    }
+}
+
+impl<T> Subscription<T> {
+    fn matches(&self, topic: &str) -> bool {
+        match &self.pattern {
+            Pattern::Exact(exact) => **exact == *topic,
+            Pattern::Prefix(prefix) => topic.starts_with(&**prefix),
+            Pattern::Unmatched => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TryRecvError;
+
+    fn topic_of<T>(rx: &Receiver<(Arc<str>, T)>) -> Option<String> {
+        rx.try_recv().ok().map(|(topic, _)| topic.to_string())
+    }
+
+    #[test]
+    fn test_topics_overlapping_subscriptions() {
+        let topics = Topics::new();
+        let tx = topics.sender();
+        let exact = topics.subscribe("orders.new");
+        let prefix = topics.subscribe("orders.*");
+        let all = topics.subscribe("*");
+        let other = topics.subscribe("users.new");
+
+        assert_eq!(tx.send("orders.new", 1), 3);
+        assert_eq!(exact.try_recv(), Ok(("orders.new".into(), 1)));
+        assert_eq!(prefix.try_recv(), Ok(("orders.new".into(), 1)));
+        assert_eq!(all.try_recv(), Ok(("orders.new".into(), 1)));
+        assert_eq!(other.try_recv(), Err(TryRecvError::Empty));
+
+        assert_eq!(tx.send("orders.paid", 2), 2);
+        assert_eq!(topic_of(&exact), None);
+        assert_eq!(topic_of(&prefix).as_deref(), Some("orders.paid"));
+        assert_eq!(topic_of(&all).as_deref(), Some("orders.paid"));
+    }
+
+    #[test]
+    fn test_topics_unmatched() {
+        let topics = Topics::new();
+        let tx = topics.sender();
+        let logs = topics.subscribe("logs");
+        assert_eq!(tx.send("metrics", 1), 0);
+
+        let rest = topics.catch_all();
+        assert_eq!(tx.send("metrics", 2), 1);
+        assert_eq!(tx.send("logs", 3), 1);
+        assert_eq!(rest.try_recv(), Ok(("metrics".into(), 2)));
+        assert_eq!(rest.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(logs.try_recv(), Ok(("logs".into(), 3)));
+    }
+
+    #[test]
+    fn test_topics_unsubscribe() {
+        let topics = Topics::new();
+        let tx = topics.sender();
+        let first = topics.subscribe("jobs");
+        let second = topics.subscribe("jobs");
+        assert_eq!(tx.send("jobs", 1), 2);
+        drop(first);
+        assert_eq!(tx.send("jobs", 2), 1);
+        assert_eq!(second.try_recv(), Ok(("jobs".into(), 1)));
+        assert_eq!(second.try_recv(), Ok(("jobs".into(), 2)));
+        drop(second);
+        assert_eq!(tx.send("jobs", 3), 0);
+
+        let third = topics.subscribe("jobs");
+        assert_eq!(tx.send("jobs", 4), 1);
+        assert_eq!(third.try_recv(), Ok(("jobs".into(), 4)));
+    }
+}
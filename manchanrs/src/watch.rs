@@ -0,0 +1,185 @@
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::{Recover, RecvError, Wait};
+
+struct Inner<T> {
+    // behind an Arc so readers can hold it without holding the lock
+    value: Arc<T>,
+    // bumped by every send
+    version: u64,
+    has_sender: bool,
+}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    changed: Condvar,
+}
+
+pub struct WatchSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+// Tracks the last version it has seen; clones start from the same one.
+pub struct WatchReceiver<T> {
+    shared: Arc<Shared<T>>,
+    seen: u64,
+}
+
+// Holds only the latest value: each send replaces it, and readers that fall behind skip
+// straight to the newest. Receivers start having seen `initial`.
+pub fn watch<T>(initial: T) -> (WatchSender<T>, WatchReceiver<T>) {
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(Inner {
+            value: Arc::new(initial),
+            version: 0,
+            has_sender: true,
+        }),
+        changed: Condvar::new(),
+    });
+    let tx = WatchSender {
+        shared: Arc::clone(&shared),
+    };
+    (tx, WatchReceiver { shared, seen: 0 })
+}
+
+impl<T> WatchSender<T> {
+    // Never blocks on readers, which only ever hold the lock briefly.
+    pub fn send(&self, value: T) {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        let old = mem::replace(&mut inner_guard.value, Arc::new(value));
+        inner_guard.version += 1;
+        drop(inner_guard);
+        self.shared.changed.notify_all();
+        drop(old);
+    }
+
+    pub fn borrow(&self) -> Arc<T> {
+        Arc::clone(&self.shared.inner.lock().recover().value)
+    }
+
+    // A receiver that has seen the current value.
+    pub fn subscribe(&self) -> WatchReceiver<T> {
+        let seen = self.shared.inner.lock().recover().version;
+        WatchReceiver {
+            shared: Arc::clone(&self.shared),
+            seen,
+        }
+    }
+}
+
+impl<T> Drop for WatchSender<T> {
+    fn drop(&mut self) {
+        self.shared.inner.lock().recover().has_sender = false;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl<T> WatchReceiver<T> {
+    // The current value, without marking it seen. The writer can replace it meanwhile;
+    // the returned one stays as it was.
+    pub fn borrow(&self) -> Arc<T> {
+        Arc::clone(&self.shared.inner.lock().recover().value)
+    }
+
+    // Like borrow, but marks the value seen.
+    pub fn borrow_and_update(&mut self) -> Arc<T> {
+        let inner_guard = self.shared.inner.lock().recover();
+        self.seen = inner_guard.version;
+        Arc::clone(&inner_guard.value)
+    }
+
+    pub fn has_changed(&self) -> bool {
+        self.shared.inner.lock().recover().version != self.seen
+    }
+
+    // Blocks until a value newer than the last seen one is sent, then marks it seen.
+    // Errors once the sender is gone with nothing newer left to see.
+    pub fn changed(&mut self) -> Result<(), RecvError> {
+        let mut inner_guard = self.shared.inner.lock().recover();
+        loop {
+            if inner_guard.version != self.seen {
+                self.seen = inner_guard.version;
+                return Ok(());
+            }
+            if !inner_guard.has_sender {
+                return Err(RecvError);
+            }
+            inner_guard = self.shared.changed.wait_on(inner_guard);
+        }
+    }
+}
+
+impl<T> Clone for WatchReceiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+            seen: self.seen,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::{self, sleep};
+    use std::time::Duration;
+
+    #[test]
+    fn test_watch_readers_see_latest() {
+        let (tx, rx) = watch(0);
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let mut rx = rx.clone();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    while rx.changed().is_ok() {
+                        let value = *rx.borrow();
+                        assert!(value >= last);
+                        last = value;
+                    }
+                    last
+                })
+            })
+            .collect();
+        for i in 1..=1000 {
+            tx.send(i);
+        }
+        drop(tx);
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), 1000);
+        }
+        assert_eq!(*rx.borrow(), 1000);
+    }
+
+    #[test]
+    fn test_watch_changed() {
+        let (tx, mut rx) = watch("a");
+        assert!(!rx.has_changed());
+        tx.send("b");
+        tx.send("c");
+        assert!(rx.has_changed());
+        assert_eq!(*rx.borrow(), "c");
+        assert!(rx.has_changed());
+        rx.changed().unwrap();
+        assert!(!rx.has_changed());
+
+        let waiter = thread::spawn(move || {
+            rx.changed().unwrap();
+            let value = *rx.borrow_and_update();
+            (value, rx)
+        });
+        sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        tx.send("d");
+        let (value, mut rx) = waiter.join().unwrap();
+        assert_eq!(value, "d");
+
+        let mut rx2 = tx.subscribe();
+        assert!(!rx2.has_changed());
+        drop(tx);
+        assert_eq!(rx.changed(), Err(RecvError));
+        assert_eq!(rx2.changed(), Err(RecvError));
+        assert_eq!(*rx2.borrow(), "d");
+    }
+}